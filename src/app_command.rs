@@ -1,10 +1,133 @@
 pub mod print_command {
+    use crate::file_manager::FileOperation;
     use crate::model::file_tree::FileEntry;
+    use crate::model::search::SearchFileResult;
+    use crate::support::encoding::DecodedText;
+    use crate::support::git::{GitStatus, LineChange};
+    use crate::support::indentation::Indentation;
     use druid::Selector;
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
 
     pub const REBUILD_MENUS: Selector = Selector::new("print.rebuild-menus");
     pub const OPEN: Selector = Selector::new("print.open-project");
     pub const SET_FILE: Selector<FileEntry> = Selector::new("print.open-file");
     // todo: add reload dir
     pub const RELOAD_DIR: Selector = Selector::new("print.reload-dir");
+    /// close the project dir and stop watching it.
+    pub const CLOSE_FOLDER: Selector = Selector::new("print.close-folder");
+    /// open another window on the same buffer and project.
+    pub const NEW_WINDOW: Selector = Selector::new("print.new-window");
+    pub const NEW_FILE: Selector<FileEntry> = Selector::new("print.new-file");
+    pub const NEW_FOLDER: Selector<FileEntry> = Selector::new("print.new-folder");
+    pub const RENAME_FILE: Selector<FileEntry> = Selector::new("print.rename-file");
+    pub const DELETE_FILE: Selector<FileEntry> = Selector::new("print.delete-file");
+    /// a tree node dropped on a dir node.
+    pub const MOVE_FILE: Selector<(FileEntry, FileEntry)> = Selector::new("print.move-file");
+    pub const COPY_PATH: Selector<FileEntry> = Selector::new("print.copy-path");
+    pub const COPY_RELATIVE_PATH: Selector<FileEntry> = Selector::new("print.copy-relative-path");
+    pub const REVEAL_IN_FILE_MANAGER: Selector<FileEntry> =
+        Selector::new("print.reveal-in-file-manager");
+    /// sent by the file operation modal once the user confirmed it.
+    pub const APPLY_FILE_OPERATION: Selector<FileOperation> =
+        Selector::new("print.apply-file-operation");
+    /// save the open file quietly, sent by the auto-save timer.
+    pub const AUTO_SAVE: Selector = Selector::new("print.auto-save");
+    /// ask for a path and save the buffer there.
+    pub const SAVE_AS: Selector = Selector::new("print.save-as");
+    /// save the buffer to the path as UTF-8, offered when its encoding
+    /// can't hold the text.
+    pub const SAVE_AS_UTF8: Selector<PathBuf> = Selector::new("print.save-as-utf8");
+    /// answers of the unsaved changes prompt shown on closing the window.
+    pub const SAVE_AND_CLOSE: Selector = Selector::new("print.save-and-close");
+    pub const DISCARD_AND_CLOSE: Selector = Selector::new("print.discard-and-close");
+    /// quit the app, after asking about unsaved changes. druid handles
+    /// `QUIT_APP` before the delegate sees it, so menus send this instead.
+    pub const QUIT: Selector = Selector::new("print.quit");
+    pub const RELOAD_FILE: Selector = Selector::new("print.reload-file");
+    /// sent by the file watcher thread when new events are queued.
+    pub const FILE_EVENTS: Selector = Selector::new("print.file-events");
+    pub const REFRESH_DIRS: Selector = Selector::new("print.refresh-dirs");
+    /// the result of a background scan of a project dir.
+    pub const DIR_LOADED: Selector<(Arc<Path>, FileEntry)> = Selector::new("print.dir-loaded");
+    pub const REFRESH_GIT_STATUS: Selector = Selector::new("print.refresh-git-status");
+    /// the git status of a project dir, read in the background.
+    pub const GIT_STATUS_LOADED: Selector<(Arc<Path>, Arc<HashMap<String, GitStatus>>)> =
+        Selector::new("print.git-status-loaded");
+    /// compare the buffer to the open file in git HEAD, in the background.
+    pub const DIFF_BUFFER: Selector = Selector::new("print.diff-buffer");
+    pub const LINE_CHANGES_LOADED: Selector<(Arc<Path>, Arc<Vec<LineChange>>)> =
+        Selector::new("print.line-changes-loaded");
+    /// the content of a file read in the background, or why it could not be read.
+    pub const FILE_LOADED: Selector<(Arc<Path>, Result<DecodedText, String>)> =
+        Selector::new("print.file-loaded");
+    /// sent instead of `FILE_LOADED` when the file looks binary.
+    pub const BINARY_FILE: Selector<Arc<Path>> = Selector::new("print.binary-file");
+    /// open a file even though it looks binary.
+    pub const FORCE_OPEN_FILE: Selector<Arc<Path>> = Selector::new("print.force-open-file");
+    pub const SHOW_GO_TO_FILE: Selector = Selector::new("print.show-go-to-file");
+    /// run the `run_command` of the project config.
+    pub const RUN: Selector = Selector::new("print.run");
+    /// a line printed by the running project command.
+    pub const RUN_OUTPUT: Selector<String> = Selector::new("print.run-output");
+    /// the project command exited, with its exit code if it has one.
+    pub const RUN_FINISHED: Selector<Option<i32>> = Selector::new("print.run-finished");
+    pub const CLEAR_OUTPUT: Selector = Selector::new("print.clear-output");
+    pub const TOGGLE_OUTPUT: Selector = Selector::new("print.toggle-output");
+    pub const SHOW_FIND_IN_FILES: Selector = Selector::new("print.show-find-in-files");
+    /// search the project for the query of the find in files panel.
+    pub const FIND_IN_FILES: Selector = Selector::new("print.find-in-files");
+    /// the matches in one file, for the search with the given id.
+    pub const SEARCH_RESULT: Selector<(u64, SearchFileResult)> =
+        Selector::new("print.search-result");
+    pub const SEARCH_DONE: Selector<u64> = Selector::new("print.search-done");
+    /// open a file and move the caret to a line of it.
+    pub const OPEN_SEARCH_RESULT: Selector<(Arc<Path>, usize)> =
+        Selector::new("print.open-search-result");
+    pub const SHOW_GO_TO_LINE: Selector = Selector::new("print.show-go-to-line");
+    /// open the file the editor was last switched away from again.
+    pub const REOPEN_CLOSED_FILE: Selector = Selector::new("print.reopen-closed-file");
+    /// move the caret to the line typed into the go to line modal.
+    pub const GO_TO_LINE: Selector = Selector::new("print.go-to-line");
+    /// move the caret to the start of a line, counted from 1.
+    pub const REVEAL_LINE: Selector<usize> = Selector::new("print.reveal-line");
+    /// line edits on the lines touched by the selection of the editor.
+    pub const INDENT: Selector = Selector::new("print.indent");
+    pub const OUTDENT: Selector = Selector::new("print.outdent");
+    pub const TOGGLE_COMMENT: Selector = Selector::new("print.toggle-comment");
+    pub const MOVE_LINE_UP: Selector = Selector::new("print.move-line-up");
+    pub const MOVE_LINE_DOWN: Selector = Selector::new("print.move-line-down");
+    pub const DUPLICATE_LINE: Selector = Selector::new("print.duplicate-line");
+    /// select the word at the caret, or add the next match of the selection.
+    pub const SELECT_NEXT_OCCURRENCE: Selector = Selector::new("print.select-next-occurrence");
+    /// replace the selection with the date and time in `Params::date_format`.
+    pub const INSERT_DATE_TIME: Selector = Selector::new("print.insert-date-time");
+    /// replace the selection with a tab, whatever the indentation of the file.
+    pub const INSERT_LITERAL_TAB: Selector = Selector::new("print.insert-literal-tab");
+    /// replace the selection with the snippet of that name.
+    pub const INSERT_SNIPPET: Selector<String> = Selector::new("print.insert-snippet");
+    pub const SHOW_COMMAND_PALETTE: Selector = Selector::new("print.show-command-palette");
+    pub const TOGGLE_DARK_MODE: Selector = Selector::new("print.toggle-dark-mode");
+    /// look up the OS appearance in the background.
+    pub const DETECT_SYSTEM_THEME: Selector = Selector::new("print.detect-system-theme");
+    pub const SYSTEM_THEME_DETECTED: Selector<Option<bool>> =
+        Selector::new("print.system-theme-detected");
+    pub const TOGGLE_WORD_WRAP: Selector = Selector::new("print.toggle-word-wrap");
+    /// hide or bring back everything around the editor.
+    pub const TOGGLE_ZEN_MODE: Selector = Selector::new("print.toggle-zen-mode");
+    /// lock or unlock the open file against edits and saves.
+    pub const TOGGLE_READ_ONLY: Selector = Selector::new("print.toggle-read-only");
+    /// expand the project tree to the open file and select it.
+    pub const REVEAL_FILE: Selector = Selector::new("print.reveal-file");
+    /// expand the project tree to a file or dir and select it.
+    pub const REVEAL_PATH: Selector<PathBuf> = Selector::new("print.reveal-path");
+    /// override the detected language of the open file.
+    pub const SET_LANGUAGE: Selector<String> = Selector::new("print.set-language");
+    /// override the detected indentation of the open file, kept for the project.
+    pub const SET_INDENTATION: Selector<Indentation> = Selector::new("print.set-indentation");
+    /// open a file or dir from the recent list.
+    pub const OPEN_RECENT: Selector<Arc<Path>> = Selector::new("print.open-recent");
+    /// read the open file again, decoded with the given encoding.
+    pub const REOPEN_WITH_ENCODING: Selector<String> = Selector::new("print.reopen-with-encoding");
 }
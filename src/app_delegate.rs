@@ -1,101 +1,1741 @@
 use crate::app_command::print_command;
-use crate::app_state::{AppState, Workspace};
+use crate::app_state::{content_hash, AppState, Params, Workspace, MAX_FONT_SIZE, MIN_FONT_SIZE};
+use crate::components::icon_button::IconButton;
 use crate::components::modal_host::ModalHost;
-use druid::widget::{Flex, Label};
-use druid::{AppDelegate, Command, DelegateCtx, Env, FileInfo, Handled, Target, Widget, WidgetExt};
+use crate::conan_text::custom_input_component::CustomTextComponent;
+use crate::file_manager::{self, FileOperation};
+use crate::model::file_tree::FileEntry;
+use crate::print::command_palette::CommandPalette;
+use crate::print::save_preferences::SavePreferences;
+use crate::print::watcher::{FileWatcher, Notify, WatchToken, DEBOUNCE_WAIT_MILLIS};
+use crate::print::{find_in_files, output_panel, runner};
+use crate::support::appearance;
+use crate::support::directory;
+use crate::support::encoding;
+use crate::support::git;
+use crate::support::indentation::Indentation;
+use crate::support::language;
+use crate::support::line_edit::{self, LineEdit};
+use crate::support::snippet;
+use crate::support::whitespace::{self, Tidy};
+use druid::text::Selection;
+use druid::widget::{Checkbox, CrossAxisAlignment, Flex, Label, Stepper, TextBox};
+use druid::{
+    commands, lens, AppDelegate, Application, Command, DelegateCtx, Env, ExtEventSink,
+    FileDialogOptions, Handled, LensExt, SingleUse, Target, Widget, WidgetExt, WindowId,
+};
+use notify::event::{EventKind, ModifyKind};
+use std::collections::HashSet;
+use std::fs;
 use std::fs::OpenOptions;
-use std::io::Write;
-use std::path::PathBuf;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
-#[derive(Debug, Default)]
-pub struct Delegate;
+/// token for the file opened in the editor.
+const OPEN_FILE_TOKEN: WatchToken = WatchToken(1);
+
+/// where the About dialog points to.
+const REPOSITORY_URL: &str = "https://github.com/phodal/conan";
+/// token for the project dir shown in the file tree.
+const PROJECT_DIR_TOKEN: WatchToken = WatchToken(2);
+
+/// Wakes up the delegate from the watcher thread, so that queued file events
+/// are handled on the main thread.
+struct CommandNotify(ExtEventSink);
+
+/// What the unsaved changes prompt was shown for, run once the changes were
+/// saved or discarded.
+enum AfterPrompt {
+    CloseWindow(WindowId),
+    NewFile,
+    Quit,
+    /// read a file into the buffer, see `Delegate::load_file_as`, and go to
+    /// the line when there is one.
+    Open {
+        path: PathBuf,
+        label: Option<String>,
+        force: bool,
+        line: Option<usize>,
+    },
+    ReopenClosed,
+    /// open a file or folder picked in a panel or from the recent list.
+    OpenPath(PathBuf),
+}
+
+impl Notify for CommandNotify {
+    fn notify(&self) {
+        if let Err(err) = self
+            .0
+            .submit_command(print_command::FILE_EVENTS, (), Target::Auto)
+        {
+            log::error!("notify file events error: {:?}", err);
+        }
+    }
+}
+
+pub struct Delegate {
+    sink: ExtEventSink,
+    watcher: FileWatcher,
+    watched_file: Option<PathBuf>,
+    watched_dir: Option<PathBuf>,
+    /// dirs (relative to the project dir) waiting for a debounced rescan.
+    pending_dirs: HashSet<PathBuf>,
+    /// a debounced read of the git status is scheduled.
+    git_pending: bool,
+    /// the file being read by `load_file`, older reads are dropped.
+    loading_file: Option<PathBuf>,
+    /// lets the next `CLOSE_WINDOW` through, once the prompt was answered.
+    close_confirmed: bool,
+    /// what waits on the unsaved changes prompt.
+    after_prompt: Option<AfterPrompt>,
+    /// Save in the prompt of an untitled buffer went to the save panel,
+    /// `after_prompt` goes on once it saved.
+    resume_after_save: bool,
+    /// the open windows, in the order they were opened.
+    windows: Vec<WindowId>,
+    /// the window commands last came from, where modals show up.
+    active_window: Option<WindowId>,
+    /// the id of the latest find in files search, older ones stop.
+    search_id: Arc<AtomicU64>,
+    /// the line to move to once the file being loaded is shown.
+    pending_line: Option<usize>,
+}
 
 impl AppDelegate<AppState> for Delegate {
     #[rustfmt::skip]
-    fn command<'a>(&mut self, ctx: &mut DelegateCtx<'a>, _target: Target, cmd: &Command, data: &mut AppState, _env: &Env, ) -> Handled {
+    fn command<'a>(&mut self, ctx: &mut DelegateCtx<'a>, target: Target, cmd: &Command, data: &mut AppState, _env: &Env, ) -> Handled {
+        if let Target::Window(id) = target {
+            self.active_window = Some(id);
+        }
+
+        // a modal asked for without a window goes to the one last used,
+        // not to every open window
+        if let Some(builder) = cmd.get(ModalHost::<AppState>::SHOW_MODAL) {
+            if let (Target::Global, Some(id)) = (target, self.active_window) {
+                if let Some(builder) = builder.take() {
+                    ctx.submit_command(ModalHost::<AppState>::SHOW_MODAL.with(SingleUse::new(builder)).to(id));
+                }
+                return Handled::Yes;
+            }
+        }
+
         if let Some(info) = cmd.get(print_command::SET_FILE) {
             let path = PathBuf::from(info.path.as_str());
             log::info!("open file: {:?}", path.display());
-            data.open_file(path);
+            self.unless_dirty(ctx, data, AfterPrompt::Open { path, label: None, force: false, line: None });
             return Handled::Yes;
         } else if cmd.is(druid::commands::SAVE_FILE) {
-            return Delegate::save_file(data);
+            return Delegate::save_file(ctx, data);
+        } else if cmd.is(print_command::NEW_WINDOW) {
+            ctx.new_window(crate::main_window());
+            return Handled::Yes;
+        } else if cmd.is(commands::CLOSE_WINDOW) {
+            // the buffer stays open in the other windows
+            if self.windows.len() > 1 {
+                return Handled::No;
+            }
+            if !data.workspace.dirty || self.close_confirmed {
+                self.close_confirmed = false;
+                data.remember_expanded();
+                data.remember_position();
+                data.save_global_config();
+                return Handled::No;
+            }
+
+            // veto the close until the user picked what to do with the changes
+            self.after_prompt = match target {
+                Target::Window(id) => Some(AfterPrompt::CloseWindow(id)),
+                _ => None,
+            };
+            self.resume_after_save = false;
+            ctx.submit_command(ModalHost::<AppState>::make_modal_command(Delegate::unsaved_prompt));
+            return Handled::Yes;
+        } else if cmd.is(print_command::QUIT) {
+            self.unless_dirty(ctx, data, AfterPrompt::Quit);
+            return Handled::Yes;
+        } else if cmd.is(print_command::SAVE_AND_CLOSE) {
+            // an untitled buffer goes to the save panel first, and goes on once
+            // it saved there; a failed save keeps it dirty and stops here.
+            if data.current_file.is_none() {
+                self.resume_after_save = true;
+            }
+            Delegate::save_file(ctx, data);
+            if !data.workspace.dirty {
+                self.after_unsaved_prompt(ctx, data);
+            }
+            return Handled::Yes;
+        } else if cmd.is(commands::SAVE_PANEL_CANCELLED) {
+            self.resume_after_save = false;
+            return Handled::No;
+        } else if cmd.is(print_command::DISCARD_AND_CLOSE) {
+            self.after_unsaved_prompt(ctx, data);
+            return Handled::Yes;
+        } else if cmd.is(commands::NEW_FILE) {
+            self.unless_dirty(ctx, data, AfterPrompt::NewFile);
+            return Handled::Yes;
+        } else if cmd.is(print_command::AUTO_SAVE) {
+            Delegate::auto_save(data);
+            return Handled::Yes;
+        } else if cmd.is(print_command::SAVE_AS) {
+            self.resume_after_save = false;
+            ctx.submit_command(commands::SHOW_SAVE_PANEL.with(FileDialogOptions::new()));
+            return Handled::Yes;
+        } else if let Some(info) = cmd.get(commands::SAVE_FILE_AS) {
+            self.save_file_as(ctx, data, info.path());
+            return Handled::Yes;
+        } else if let Some(path) = cmd.get(print_command::SAVE_AS_UTF8) {
+            data.workspace.encoding = "UTF-8".to_string();
+            data.workspace.bom = false;
+            self.save_file_as(ctx, data, path);
+            return Handled::Yes;
         } else if cmd.is(print_command::RELOAD_DIR) {
-            data.set_dir(data.current_dir.as_ref().unwrap().to_path_buf());
+            // the palette offers it with no folder open too
+            if let Some(dir) = data.current_dir.clone() {
+                data.set_dir(dir.to_path_buf());
+                self.watch_dir(data);
+                self.load_dir(data);
+            }
+            return Handled::Yes;
+        } else if cmd.is(print_command::CLOSE_FOLDER) {
+            data.close_dir();
+            if let Some(old) = self.watched_dir.take() {
+                self.watcher.unwatch(&old, PROJECT_DIR_TOKEN);
+            }
+            self.pending_dirs.clear();
+            self.git_pending = false;
+            if data.current_file.is_none() {
+                if let Some(old) = self.watched_file.take() {
+                    self.watcher.unwatch(&old, OPEN_FILE_TOKEN);
+                }
+            }
+            return Handled::Yes;
+        } else if let Some((dir, entry)) = cmd.get(print_command::DIR_LOADED) {
+            // drop scans of a dir that is no longer open
+            if data.current_dir.as_ref() == Some(dir) {
+                // a freshly opened dir takes the expansion it was left with
+                let mut expanded = data.entry.expanded_paths();
+                if expanded.is_empty() {
+                    expanded = data.saved_expanded();
+                }
+                data.entry = entry.to_owned();
+                data.entry.apply_expanded(&expanded);
+                data.entry.apply_git_status(&data.git_status);
+                if data.params.auto_reveal {
+                    data.reveal_current_file();
+                }
+            }
+            return Handled::Yes;
+        } else if cmd.is(print_command::FILE_EVENTS) {
+            self.handle_file_events(ctx, data);
+            return Handled::Yes;
+        } else if cmd.is(print_command::REFRESH_DIRS) {
+            self.refresh_dirs(data);
+            return Handled::Yes;
+        } else if cmd.is(print_command::DIFF_BUFFER) {
+            self.diff_buffer(data);
+            return Handled::Yes;
+        } else if let Some((path, changes)) = cmd.get(print_command::LINE_CHANGES_LOADED) {
+            if data.current_file.as_ref() == Some(path) {
+                data.workspace.line_changes = changes.to_owned();
+            }
+            return Handled::Yes;
+        } else if cmd.is(print_command::REFRESH_GIT_STATUS) {
+            self.git_pending = false;
+            self.load_git_status(data);
+            return Handled::Yes;
+        } else if let Some((dir, statuses)) = cmd.get(print_command::GIT_STATUS_LOADED) {
+            if data.current_dir.as_ref() == Some(dir) {
+                data.git_status = statuses.to_owned();
+                data.entry.apply_git_status(&data.git_status);
+            }
+            return Handled::Yes;
+        } else if cmd.is(print_command::RELOAD_FILE) {
+            if let Some(path) = data.current_file.clone() {
+                self.load_file_as(path.to_path_buf(), Some(data.workspace.encoding.clone()), true);
+            }
+            return Handled::Yes;
+        } else if cmd.is(print_command::SHOW_COMMAND_PALETTE) {
+            data.modal_input = "".to_string();
+            ctx.submit_command(ModalHost::<AppState>::make_modal_command(CommandPalette::new));
+            return Handled::Yes;
+        } else if cmd.is(print_command::SHOW_GO_TO_FILE) {
+            let base = match &data.current_dir {
+                Some(dir) => dir.to_path_buf(),
+                None => return Handled::Yes,
+            };
+            data.modal_input = "".to_string();
+            let entry = data.entry.clone();
+            ctx.submit_command(ModalHost::<AppState>::make_modal_command(move || {
+                CommandPalette::go_to_file(&entry, &base)
+            }));
+            return Handled::Yes;
+        } else if cmd.is(print_command::RUN) {
+            self.run(ctx, data);
+            return Handled::Yes;
+        } else if let Some(output) = cmd.get(print_command::RUN_OUTPUT) {
+            output_panel::append_output(Arc::make_mut(&mut data.output), output);
+            return Handled::Yes;
+        } else if let Some(code) = cmd.get(print_command::RUN_FINISHED) {
+            data.running = false;
+            let status = match code {
+                Some(code) => format!("\n[exited with code {}]\n", code),
+                None => "\n[terminated]\n".to_string(),
+            };
+            output_panel::append_output(Arc::make_mut(&mut data.output), &status);
+            return Handled::Yes;
+        } else if cmd.is(print_command::CLEAR_OUTPUT) {
+            data.output = Default::default();
+            return Handled::Yes;
+        } else if cmd.is(print_command::TOGGLE_OUTPUT) {
+            data.params.show_output = !data.params.show_output;
+            data.save_global_config();
+            return Handled::Yes;
+        } else if cmd.is(print_command::SHOW_FIND_IN_FILES) {
+            data.search.visible = !data.search.visible;
+            return Handled::Yes;
+        } else if cmd.is(print_command::FIND_IN_FILES) {
+            self.find_in_files(data);
+            return Handled::Yes;
+        } else if let Some((id, result)) = cmd.get(print_command::SEARCH_RESULT) {
+            if *id == self.search_id.load(Ordering::SeqCst) {
+                Arc::make_mut(&mut data.search.results).push(result.to_owned());
+            }
+            return Handled::Yes;
+        } else if let Some(id) = cmd.get(print_command::SEARCH_DONE) {
+            if *id == self.search_id.load(Ordering::SeqCst) {
+                data.search.searching = false;
+            }
+            return Handled::Yes;
+        } else if let Some((path, line)) = cmd.get(print_command::OPEN_SEARCH_RESULT) {
+            if data.current_file.as_ref() == Some(path) {
+                Delegate::go_to_line(ctx, data, *line);
+            } else {
+                let path = path.to_path_buf();
+                self.unless_dirty(ctx, data, AfterPrompt::Open { path, label: None, force: false, line: Some(*line) });
+            }
+            return Handled::Yes;
+        } else if cmd.is(print_command::REOPEN_CLOSED_FILE) {
+            self.unless_dirty(ctx, data, AfterPrompt::ReopenClosed);
+            return Handled::Yes;
+        } else if cmd.is(print_command::SHOW_GO_TO_LINE) {
+            data.modal_input = "".to_string();
+            ctx.submit_command(ModalHost::<AppState>::make_modal_command(Delegate::go_to_line_modal));
+            return Handled::Yes;
+        } else if let Some(line) = cmd.get(print_command::REVEAL_LINE) {
+            Delegate::go_to_line(ctx, data, *line);
+            return Handled::Yes;
+        } else if cmd.is(print_command::GO_TO_LINE) {
+            match data.workspace.go_to_line_offset(&data.modal_input) {
+                Some(offset) => {
+                    ctx.submit_command(CustomTextComponent::SET_SELECTION.with(Selection::caret(offset)));
+                }
+                None => log::info!("not a line: {:?}", data.modal_input),
+            }
+            data.modal_input = "".to_string();
+            return Handled::Yes;
+        } else if cmd.is(print_command::INDENT) {
+            let workspace = &data.workspace;
+            let edit = line_edit::indent(&workspace.input_text, workspace.selection_anchor, workspace.selection_active, workspace.indentation);
+            Delegate::apply_line_edit(ctx, data, edit);
+            return Handled::Yes;
+        } else if cmd.is(print_command::INSERT_DATE_TIME) {
+            let text = snippet::now(&data.params.date_format);
+            Delegate::insert_text(ctx, data, &text);
+            return Handled::Yes;
+        } else if cmd.is(print_command::INSERT_LITERAL_TAB) {
+            Delegate::insert_text(ctx, data, "\t");
+            return Handled::Yes;
+        } else if let Some(name) = cmd.get(print_command::INSERT_SNIPPET) {
+            match data.params.snippets.get(name) {
+                Some(template) => {
+                    let text = snippet::expand(template, &data.workspace.file_name());
+                    Delegate::insert_text(ctx, data, &text);
+                }
+                None => log::warn!("no snippet named {:?}", name),
+            }
+            return Handled::Yes;
+        } else if cmd.is(print_command::OUTDENT) {
+            let workspace = &data.workspace;
+            let edit = line_edit::outdent(&workspace.input_text, workspace.selection_anchor, workspace.selection_active, workspace.indentation);
+            Delegate::apply_line_edit(ctx, data, edit);
+            return Handled::Yes;
+        } else if cmd.is(print_command::TOGGLE_COMMENT) {
+            let workspace = &data.workspace;
+            match language::line_comment(&workspace.language) {
+                Some(token) => {
+                    let edit = line_edit::toggle_comment(&workspace.input_text, workspace.selection_anchor, workspace.selection_active, token);
+                    Delegate::apply_line_edit(ctx, data, edit);
+                }
+                None => log::info!("no line comment for {}", workspace.language),
+            }
+            return Handled::Yes;
+        } else if cmd.is(print_command::MOVE_LINE_UP) {
+            let workspace = &data.workspace;
+            if let Some(edit) = line_edit::move_lines_up(&workspace.input_text, workspace.selection_anchor, workspace.selection_active) {
+                Delegate::apply_line_edit(ctx, data, edit);
+            }
+            return Handled::Yes;
+        } else if cmd.is(print_command::MOVE_LINE_DOWN) {
+            let workspace = &data.workspace;
+            if let Some(edit) = line_edit::move_lines_down(&workspace.input_text, workspace.selection_anchor, workspace.selection_active) {
+                Delegate::apply_line_edit(ctx, data, edit);
+            }
+            return Handled::Yes;
+        } else if cmd.is(print_command::DUPLICATE_LINE) {
+            let workspace = &data.workspace;
+            let edit = line_edit::duplicate_lines(&workspace.input_text, workspace.selection_anchor, workspace.selection_active);
+            Delegate::apply_line_edit(ctx, data, edit);
+            return Handled::Yes;
+        } else if cmd.is(print_command::SELECT_NEXT_OCCURRENCE) {
+            ctx.submit_command(CustomTextComponent::SELECT_NEXT_OCCURRENCE);
+            return Handled::Yes;
+        } else if cmd.is(print_command::TOGGLE_DARK_MODE) {
+            data.toggle_dark_mode();
+            return Handled::Yes;
+        } else if cmd.is(print_command::DETECT_SYSTEM_THEME) {
+            self.detect_system_theme();
+            return Handled::Yes;
+        } else if let Some(dark) = cmd.get(print_command::SYSTEM_THEME_DETECTED) {
+            data.system_dark = *dark;
+            return Handled::Yes;
+        } else if cmd.is(print_command::TOGGLE_READ_ONLY) {
+            data.workspace.read_only = !data.workspace.read_only;
+            return Handled::Yes;
+        } else if cmd.is(print_command::TOGGLE_ZEN_MODE) {
+            data.params.zen_mode = !data.params.zen_mode;
+            data.save_global_config();
             return Handled::Yes;
-        } else if cmd.is(druid::commands::SHOW_ABOUT) {
-            let host = ModalHost::new(Delegate::paint_preferences());
-            host.lens(AppState::workspace);
+        } else if cmd.is(print_command::TOGGLE_WORD_WRAP) {
+            data.toggle_word_wrap();
+            return Handled::Yes;
+        } else if cmd.is(print_command::REVEAL_FILE) {
+            data.reveal_current_file();
+            return Handled::Yes;
+        } else if let Some(path) = cmd.get(print_command::REVEAL_PATH) {
+            data.reveal_path(path);
+            return Handled::Yes;
+        } else if let Some(indentation) = cmd.get(print_command::SET_INDENTATION) {
+            data.set_indentation(*indentation);
+            return Handled::Yes;
+        } else if let Some(name) = cmd.get(print_command::SET_LANGUAGE) {
+            data.set_language(name.to_owned());
+            return Handled::Yes;
+        } else if let Some(label) = cmd.get(print_command::REOPEN_WITH_ENCODING) {
+            if let Some(path) = data.current_file.clone() {
+                let (path, label) = (path.to_path_buf(), Some(label.to_owned()));
+                self.unless_dirty(ctx, data, AfterPrompt::Open { path, label, force: true, line: None });
+            }
+            return Handled::Yes;
+        } else if let Some(path) = cmd.get(print_command::BINARY_FILE) {
+            if self.loading_file.as_deref() != Some(&**path) {
+                return Handled::Yes;
+            }
+            self.loading_file = None;
+
+            log::info!("refuse to open binary file: {:?}", path);
+            let path = path.clone();
+            ctx.submit_command(ModalHost::<AppState>::make_modal_command(move || {
+                Delegate::binary_file_prompt(path.clone())
+            }));
+            return Handled::Yes;
+        } else if let Some(path) = cmd.get(print_command::FORCE_OPEN_FILE) {
+            self.load_file_as(path.to_path_buf(), None, true);
+            return Handled::Yes;
+        } else if let Some((path, result)) = cmd.get(print_command::FILE_LOADED) {
+            // drop reads of a file that is no longer wanted
+            if self.loading_file.as_deref() != Some(&**path) {
+                return Handled::Yes;
+            }
+            self.loading_file = None;
+
+            match result {
+                Ok(content) => {
+                    data.set_file(path.to_path_buf(), content.to_owned());
+                    self.watch_file(data);
+                    if let Some(line) = self.pending_line.take() {
+                        Delegate::go_to_line(ctx, data, line);
+                    } else {
+                        Delegate::restore_position(ctx, data, path);
+                    }
+                    if data.params.auto_reveal {
+                        data.reveal_current_file();
+                    }
+                }
+                Err(err) => {
+                    log::error!("open file error: {:?}, {}", path, err);
+                    let message = format!("Unable to open {}: {}", path.display(), err);
+                    ctx.submit_command(ModalHost::<AppState>::make_modal_command(move || {
+                        Delegate::alert(message.clone())
+                    }));
+                }
+            }
+            return Handled::Yes;
+        } else if let Some(entry) = cmd.get(print_command::NEW_FILE) {
+            Delegate::show_file_operation(ctx, data, FileOperation::NewFile(Delegate::entry_dir(entry)));
+            return Handled::Yes;
+        } else if let Some(entry) = cmd.get(print_command::NEW_FOLDER) {
+            Delegate::show_file_operation(ctx, data, FileOperation::NewFolder(Delegate::entry_dir(entry)));
+            return Handled::Yes;
+        } else if let Some(entry) = cmd.get(print_command::RENAME_FILE) {
+            Delegate::show_file_operation(ctx, data, FileOperation::Rename(PathBuf::from(&entry.path)));
+            return Handled::Yes;
+        } else if let Some(entry) = cmd.get(print_command::DELETE_FILE) {
+            Delegate::show_file_operation(ctx, data, FileOperation::Delete(PathBuf::from(&entry.path)));
+            return Handled::Yes;
+        } else if let Some(entry) = cmd.get(print_command::COPY_PATH) {
+            Application::global().clipboard().put_string(&entry.path);
+            return Handled::Yes;
+        } else if let Some(entry) = cmd.get(print_command::COPY_RELATIVE_PATH) {
+            let path = data.relative_to_dir(Path::new(&entry.path));
+            Application::global().clipboard().put_string(path);
+            return Handled::Yes;
+        } else if let Some(entry) = cmd.get(print_command::REVEAL_IN_FILE_MANAGER) {
+            if let Err(err) = file_manager::reveal(Path::new(&entry.path)) {
+                log::error!("reveal {:?} error: {:?}", entry.path, err);
+            }
+            return Handled::Yes;
+        } else if let Some((entry, dir)) = cmd.get(print_command::MOVE_FILE) {
+            let operation = FileOperation::Move(PathBuf::from(&entry.path), PathBuf::from(&dir.path));
+            self.move_file(ctx, data, operation);
+            return Handled::Yes;
+        } else if let Some(operation) = cmd.get(print_command::APPLY_FILE_OPERATION) {
+            self.apply_file_operation(ctx, data, operation);
+            return Handled::Yes;
+        } else if cmd.is(commands::SHOW_ABOUT) {
+            ctx.submit_command(ModalHost::<AppState>::make_modal_command(Delegate::about));
+            return Handled::Yes;
+        } else if cmd.is(commands::SHOW_PREFERENCES) {
+            ctx.submit_command(ModalHost::<AppState>::make_modal_command(Delegate::preferences));
             return Handled::Yes;
         } else if let Some(info) = cmd.get(druid::commands::OPEN_FILE) {
-            return Delegate::open_file(ctx, data, info);
+            self.unless_dirty(ctx, data, AfterPrompt::OpenPath(info.path().to_path_buf()));
+            return Handled::Yes;
+        } else if let Some(path) = cmd.get(print_command::OPEN_RECENT) {
+            self.unless_dirty(ctx, data, AfterPrompt::OpenPath(path.to_path_buf()));
+            return Handled::Yes;
         }
 
         Handled::No
     }
+
+    fn window_added(
+        &mut self,
+        id: WindowId,
+        data: &mut AppState,
+        _env: &Env,
+        ctx: &mut DelegateCtx,
+    ) {
+        self.windows.push(id);
+        self.active_window = Some(id);
+        // later windows show the state the first one set up
+        if self.windows.len() > 1 {
+            return;
+        }
+
+        if let Some(path) = data.current_file.clone() {
+            self.load_file(path.to_path_buf());
+        }
+        self.watch_dir(data);
+        self.load_dir(data);
+
+        if let Some(message) = data.startup_alert.take() {
+            ctx.submit_command(ModalHost::<AppState>::make_modal_command(move || {
+                Delegate::alert(message.clone())
+            }));
+        }
+    }
+
+    fn window_removed(
+        &mut self,
+        id: WindowId,
+        _data: &mut AppState,
+        _env: &Env,
+        _ctx: &mut DelegateCtx,
+    ) {
+        self.windows.retain(|window| *window != id);
+        if self.active_window == Some(id) {
+            self.active_window = self.windows.last().copied();
+        }
+    }
 }
 
 impl Delegate {
-    fn open_file(ctx: &mut DelegateCtx, state: &mut AppState, info: &FileInfo) -> Handled {
-        if info.path().is_dir() {
-            state.set_dir(info.path().to_owned());
-            ctx.submit_command(print_command::OPEN);
-            return Handled::Yes;
+    pub fn new(sink: ExtEventSink) -> Delegate {
+        Delegate {
+            sink: sink.clone(),
+            watcher: FileWatcher::new(CommandNotify(sink)),
+            watched_file: None,
+            watched_dir: None,
+            pending_dirs: HashSet::new(),
+            git_pending: false,
+            loading_file: None,
+            close_confirmed: false,
+            after_prompt: None,
+            resume_after_save: false,
+            windows: vec![],
+            active_window: None,
+            search_id: Arc::new(AtomicU64::new(0)),
+            pending_line: None,
         }
+    }
+
+    /// Move the recursive watch for the file tree over to `current_dir`.
+    fn watch_dir(&mut self, data: &AppState) {
+        let path = match &data.current_dir {
+            None => return,
+            Some(path) => path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+        };
 
-        if let Ok(typ) = infer::get_from_path(info.path()) {
-            if let Some(_file_type) = typ {
-                if let Some(parent) = info.path().parent() {
-                    state.set_dir(Some(parent.to_owned()));
+        if self.watched_dir.as_ref() == Some(&path) {
+            return;
+        }
+
+        if let Some(old) = self.watched_dir.take() {
+            self.watcher.unwatch(&old, PROJECT_DIR_TOKEN);
+        }
+
+        let base = path.clone();
+        self.watcher
+            .watch_filtered(&path, true, PROJECT_DIR_TOKEN, move |p: &Path| {
+                match p.strip_prefix(&base) {
+                    Ok(relative) => !FileEntry::is_hidden_path(relative),
+                    Err(_) => false,
                 }
+            });
+        self.pending_dirs.clear();
+        self.watched_dir = Some(path);
+    }
 
-                state.open_file(info.path().to_owned());
-                ctx.submit_command(print_command::OPEN);
-                return Handled::Yes;
+    /// Scan `current_dir` on a background thread, large projects can take
+    /// seconds to walk. The result arrives as `DIR_LOADED`.
+    fn load_dir(&self, data: &AppState) {
+        let dir = match &data.current_dir {
+            None => return,
+            Some(dir) => dir.clone(),
+        };
+
+        let sink = self.sink.clone();
+        let title = data.workspace.project.clone();
+        thread::spawn(move || {
+            let entry = FileEntry::from_dir(title, &dir);
+            if let Err(err) =
+                sink.submit_command(print_command::DIR_LOADED, (dir, entry), Target::Auto)
+            {
+                log::error!("submit loaded dir error: {:?}", err);
+            }
+        });
+        self.load_git_status(data);
+    }
+
+    /// Read the git status of `current_dir` on a background thread, the
+    /// result arrives as `GIT_STATUS_LOADED`. Outside of a repository it
+    /// comes back empty.
+    fn load_git_status(&self, data: &AppState) {
+        let dir = match &data.current_dir {
+            None => return,
+            Some(dir) => dir.clone(),
+        };
+
+        let sink = self.sink.clone();
+        thread::spawn(move || {
+            let statuses = Arc::new(git::status(&dir).unwrap_or_default());
+            if let Err(err) = sink.submit_command(
+                print_command::GIT_STATUS_LOADED,
+                (dir, statuses),
+                Target::Auto,
+            ) {
+                log::error!("submit git status error: {:?}", err);
+            }
+        });
+    }
+
+    /// Diff the buffer against the open file in git HEAD on a background
+    /// thread, the result arrives as `LINE_CHANGES_LOADED`. Untitled buffers
+    /// and files that are not committed have no changes.
+    fn diff_buffer(&self, data: &mut AppState) {
+        let path = match &data.current_file {
+            None => {
+                data.workspace.line_changes = Default::default();
+                return;
+            }
+            Some(path) => path.clone(),
+        };
+        if data.workspace.large_file {
+            data.workspace.line_changes = Default::default();
+            return;
+        }
+
+        let text = data.workspace.input_text.clone();
+        let sink = self.sink.clone();
+        thread::spawn(move || {
+            let changes = git::head_text(&path)
+                .map(|head| git::line_changes(&head, &text))
+                .unwrap_or_default();
+            if let Err(err) = sink.submit_command(
+                print_command::LINE_CHANGES_LOADED,
+                (path, Arc::new(changes)),
+                Target::Auto,
+            ) {
+                log::error!("submit line changes error: {:?}", err);
+            }
+        });
+    }
+
+    /// Look up the OS appearance on a background thread, it can take a
+    /// process, the result arrives as `SYSTEM_THEME_DETECTED`.
+    fn detect_system_theme(&self) {
+        let sink = self.sink.clone();
+        thread::spawn(move || {
+            let dark = appearance::system_dark();
+            if let Err(err) =
+                sink.submit_command(print_command::SYSTEM_THEME_DETECTED, dark, Target::Auto)
+            {
+                log::error!("submit system theme error: {:?}", err);
+            }
+        });
+    }
+
+    /// Schedule a read of the git status once the burst of file events is
+    /// over. Commits and staging don't touch the tree, so they show up with
+    /// the next change or a Reload.
+    fn queue_git_refresh(&mut self) {
+        if self.git_pending {
+            return;
+        }
+        self.git_pending = true;
+        let sink = self.sink.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(DEBOUNCE_WAIT_MILLIS));
+            let _ = sink.submit_command(print_command::REFRESH_GIT_STATUS, (), Target::Auto);
+        });
+    }
+
+    fn load_file(&mut self, path: PathBuf) {
+        self.load_file_as(path, None, false);
+    }
+
+    /// Read `path` on a background thread, the content is shown once
+    /// `FILE_LOADED` comes back. The encoding is detected unless one is given.
+    /// Files that look binary are only read when `force` is set.
+    fn load_file_as(&mut self, path: PathBuf, label: Option<String>, force: bool) {
+        self.loading_file = Some(path.clone());
+        self.pending_line = None;
+
+        let sink = self.sink.clone();
+        thread::spawn(move || {
+            if !force {
+                if let Ok(head) = Delegate::read_head(&path) {
+                    if encoding::is_binary(&head) {
+                        let path: Arc<Path> = path.into();
+                        if let Err(err) =
+                            sink.submit_command(print_command::BINARY_FILE, path, Target::Auto)
+                        {
+                            log::error!("submit binary file error: {:?}", err);
+                        }
+                        return;
+                    }
+                }
+            }
+
+            let result = fs::read(&path)
+                .map(|content| match &label {
+                    Some(label) => encoding::decode_with(&content, label),
+                    None => encoding::decode(&content),
+                })
+                .map_err(|err| err.to_string());
+            let path: Arc<Path> = path.into();
+            if let Err(err) =
+                sink.submit_command(print_command::FILE_LOADED, (path, result), Target::Auto)
+            {
+                log::error!("submit loaded file error: {:?}", err);
+            }
+        });
+    }
+
+    /// the start of a file, enough for `encoding::is_binary`.
+    fn read_head(path: &Path) -> io::Result<Vec<u8>> {
+        let mut head = vec![];
+        fs::File::open(path)?
+            .take(encoding::BINARY_SNIFF_LEN as u64)
+            .read_to_end(&mut head)?;
+        Ok(head)
+    }
+
+    /// Remember the parent dir of a changed path, and schedule a rescan once
+    /// the burst of events is over.
+    fn queue_dir_refresh(&mut self, changed: &Path) {
+        let base = match &self.watched_dir {
+            None => return,
+            Some(base) => base,
+        };
+
+        let parent = match changed.parent().map(|p| p.strip_prefix(base)) {
+            Some(Ok(relative)) => relative.to_path_buf(),
+            _ => return,
+        };
+
+        if self.pending_dirs.is_empty() {
+            let sink = self.sink.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(DEBOUNCE_WAIT_MILLIS));
+                let _ = sink.submit_command(print_command::REFRESH_DIRS, (), Target::Auto);
+            });
+        }
+        self.pending_dirs.insert(parent);
+    }
+
+    fn refresh_dirs(&mut self, data: &mut AppState) {
+        let base = match &data.current_dir {
+            None => return,
+            Some(dir) => dir.to_path_buf(),
+        };
+
+        for relative in self.pending_dirs.drain() {
+            // a dir created inside a new dir has no node yet, so we fall back
+            // to the nearest ancestor that is already in the tree.
+            let mut dir = relative.as_path();
+            while !data.entry.refresh_dir(dir, &base) {
+                match dir.parent() {
+                    Some(parent) => dir = parent,
+                    None => break,
+                }
             }
+        }
+        data.entry.apply_git_status(&data.git_status);
+    }
+
+    /// Move the watch for the open file over to `current_file`.
+    fn watch_file(&mut self, data: &AppState) {
+        let path = match &data.current_file {
+            None => return,
+            Some(path) => path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
         };
 
-        log::info!("under type: {:?}", info);
-        return Handled::No;
+        if self.watched_file.as_ref() == Some(&path) {
+            return;
+        }
+
+        if let Some(old) = self.watched_file.take() {
+            self.watcher.unwatch(&old, OPEN_FILE_TOKEN);
+        }
+
+        self.watcher.watch(&path, false, OPEN_FILE_TOKEN);
+        self.watched_file = Some(path);
     }
 
-    fn save_file(data: &mut AppState) -> Handled {
-        let file_path;
-        match &data.current_file {
-            None => return Handled::Yes,
-            Some(path) => {
-                file_path = path;
+    fn handle_file_events(&mut self, ctx: &mut DelegateCtx, data: &mut AppState) {
+        let mut file_changed = false;
+        for (token, event) in self.watcher.take_events() {
+            if token == PROJECT_DIR_TOKEN {
+                if !matches!(event.kind, EventKind::Access(_)) {
+                    self.queue_git_refresh();
+                }
+                match event.kind {
+                    EventKind::Create(_)
+                    | EventKind::Remove(_)
+                    | EventKind::Modify(ModifyKind::Name(_)) => {
+                        for path in event.paths.iter() {
+                            self.queue_dir_refresh(path);
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            match event.kind {
+                EventKind::Create(_)
+                | EventKind::Modify(ModifyKind::Any)
+                | EventKind::Modify(ModifyKind::Data(_)) => {
+                    file_changed = true;
+                }
+                _ => {}
             }
+        }
+
+        if !file_changed {
+            return;
+        }
+
+        let path = match &data.current_file {
+            None => return,
+            Some(path) => path.to_path_buf(),
         };
 
-        let buf = file_path.to_path_buf();
+        // our own saves also show up as modify events; only prompt when the
+        // content on disk differs from what we last read or wrote.
+        match fs::read(&path) {
+            Ok(content) => {
+                let decoded = encoding::decode_with(&content, &data.workspace.encoding);
+                if content_hash(&decoded.text) == data.workspace.disk_hash {
+                    return;
+                }
+            }
+            Err(err) => {
+                log::info!("read changed file error: {:?}", err);
+                return;
+            }
+        }
+
+        log::info!("file changed on disk: {:?}", path);
+        ctx.submit_command(ModalHost::<AppState>::make_modal_command(
+            Delegate::reload_prompt,
+        ));
+    }
+
+    /// the dir new entries go into: the entry itself, or the dir of a file.
+    fn entry_dir(entry: &FileEntry) -> PathBuf {
+        let path = PathBuf::from(&entry.path);
+        if entry.is_dir {
+            return path;
+        }
+        path.parent().map(Path::to_path_buf).unwrap_or(path)
+    }
+
+    fn show_file_operation(ctx: &mut DelegateCtx, data: &mut AppState, operation: FileOperation) {
+        let is_project_dir = match &operation {
+            FileOperation::Rename(path) | FileOperation::Delete(path) => {
+                data.current_dir.as_deref() == Some(path.as_path())
+            }
+            _ => false,
+        };
+        if is_project_dir {
+            log::warn!("can not {:?} the project dir", operation);
+            return;
+        }
+
+        data.modal_input = operation.initial_input();
+        ctx.submit_command(ModalHost::<AppState>::make_modal_command(move || {
+            Delegate::file_operation_modal(operation)
+        }));
+    }
+
+    /// Move a file dropped on a dir of the tree, asking first when that
+    /// replaces one of the same name.
+    fn move_file(&mut self, ctx: &mut DelegateCtx, data: &mut AppState, operation: FileOperation) {
+        if let FileOperation::Move(path, dir) = &operation {
+            if dir.starts_with(path) {
+                log::warn!("can not move {:?} into itself", path);
+                return;
+            }
+        }
+
+        match operation.move_target() {
+            Some(target) if target.exists() => {
+                ctx.submit_command(ModalHost::<AppState>::make_modal_command(move || {
+                    Delegate::file_operation_modal(operation.clone())
+                }));
+            }
+            _ => self.apply_file_operation(ctx, data, &operation),
+        }
+    }
+
+    fn apply_file_operation(
+        &mut self,
+        ctx: &mut DelegateCtx,
+        data: &mut AppState,
+        operation: &FileOperation,
+    ) {
+        match operation.apply(&data.modal_input) {
+            Ok(target) => {
+                match (operation, target) {
+                    (FileOperation::NewFile(_), Some(path)) => self.unless_dirty(
+                        ctx,
+                        data,
+                        AfterPrompt::Open {
+                            path,
+                            label: None,
+                            force: false,
+                            line: None,
+                        },
+                    ),
+                    (FileOperation::Rename(from), Some(to)) => {
+                        data.move_current_file(from, &to);
+                        self.watch_file(data);
+                    }
+                    (FileOperation::Delete(path), _) => data.close_file_under(path),
+                    (FileOperation::Move(from, _), Some(to)) => {
+                        data.move_current_file(from, &to);
+                        self.watch_file(data);
+                    }
+                    _ => {}
+                }
+
+                if let Some(base) = data.current_dir.clone() {
+                    for dir in operation.affected_dirs() {
+                        if let Ok(relative) = dir.strip_prefix(&base) {
+                            data.entry.refresh_dir(relative, &base);
+                        }
+                    }
+                    data.entry.apply_git_status(&data.git_status);
+                }
+            }
+            Err(err) => log::error!("{} error: {:?}", operation.title(), err),
+        }
+
+        data.modal_input = "".to_string();
+    }
+
+    fn file_operation_modal(operation: FileOperation) -> impl Widget<AppState> {
+        let mut column = Flex::column().with_child(
+            Label::new(operation.title()).with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        );
+        column.add_default_spacer();
+
+        match &operation {
+            FileOperation::Delete(path) => column.add_child(
+                Label::new(format!(
+                    "Move {} to the trash? Without a trash it is deleted for good.",
+                    path.display()
+                ))
+                .with_text_color(crate::theme::BASIC_TEXT_COLOR),
+            ),
+            FileOperation::Move(path, dir) => column.add_child(
+                Label::new(format!(
+                    "{} already exists in {}. Replace it?",
+                    path.file_name().unwrap_or_default().to_string_lossy(),
+                    dir.display()
+                ))
+                .with_text_color(crate::theme::BASIC_TEXT_COLOR),
+            ),
+            _ => column.add_child(TextBox::new().fix_width(240.0).lens(AppState::modal_input)),
+        }
+
+        let confirm = IconButton::from_label(
+            Label::new(operation.confirm_label()).with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(move |ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(print_command::APPLY_FILE_OPERATION.with(operation.clone()));
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
+
+        let cancel = IconButton::from_label(
+            Label::new("Cancel").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
+
+        column
+            .with_default_spacer()
+            .with_child(
+                Flex::row()
+                    .with_child(confirm)
+                    .with_default_spacer()
+                    .with_child(cancel),
+            )
+            .padding(10.0)
+            .background(crate::theme::TOOL_WINDOW_COLOR)
+    }
+
+    /// Run the `run_command` of the project config in the project dir, it
+    /// is read again each time so edits of the config apply right away.
+    fn run(&self, ctx: &mut DelegateCtx, data: &mut AppState) {
+        let dir = match &data.current_dir {
+            Some(dir) if !data.running => dir.to_path_buf(),
+            _ => return,
+        };
+
+        let command = directory::read_project_config(&dir).and_then(|config| config.run_command);
+        let command = match command {
+            Some(command) => command,
+            None => {
+                let message = format!(
+                    "No command to run, set \"run_command\" in {}",
+                    directory::project_config_path(&dir).display()
+                );
+                ctx.submit_command(ModalHost::<AppState>::make_modal_command(move || {
+                    Delegate::alert(message.clone())
+                }));
+                return;
+            }
+        };
+
+        log::info!("run: {:?} in {:?}", command, dir);
+        data.output = Arc::new(format!("$ {}\n", command));
+        data.running = true;
+        if !data.params.show_output {
+            data.params.show_output = true;
+            data.save_global_config();
+        }
+        runner::run(command, dir, self.sink.clone());
+    }
+
+    /// Start a search for the query of the find in files panel, results of
+    /// an older search still coming in are dropped.
+    fn find_in_files(&mut self, data: &mut AppState) {
+        let dir = match &data.current_dir {
+            Some(dir) => dir.clone(),
+            None => return,
+        };
+        if data.search.query.is_empty() {
+            return;
+        }
+
+        let id = self.search_id.fetch_add(1, Ordering::SeqCst) + 1;
+        data.search.results = Default::default();
+        match find_in_files::build_regex(&data.search) {
+            Ok(regex) => {
+                data.search.error = None;
+                data.search.searching = true;
+                find_in_files::search(id, self.search_id.clone(), dir, regex, self.sink.clone());
+            }
+            Err(err) => {
+                data.search.error = Some(err);
+                data.search.searching = false;
+            }
+        }
+    }
+
+    /// Put the caret and scroll back where `path` was left, offsets past the
+    /// end of a file that shrank in the meantime go to its end.
+    fn restore_position(ctx: &mut DelegateCtx, data: &AppState, path: &Path) {
+        let position = match data.position_of(path) {
+            Some(position) => position,
+            None => return,
+        };
+        let text = &data.workspace.input_text;
+        let clamp = |offset: usize| {
+            let mut offset = offset.min(text.len());
+            while !text.is_char_boundary(offset) {
+                offset -= 1;
+            }
+            offset
+        };
+        let selection = Selection::new(clamp(position.anchor), clamp(position.active));
+        ctx.submit_command(CustomTextComponent::SET_SELECTION.with(selection));
+        ctx.submit_command(CustomTextComponent::SCROLL_TO_FRACTION.with(position.scroll));
+    }
+
+    fn go_to_line(ctx: &mut DelegateCtx, data: &AppState, line: usize) {
+        if let Some(offset) = data.workspace.go_to_line_offset(&line.to_string()) {
+            ctx.submit_command(CustomTextComponent::SET_SELECTION.with(Selection::caret(offset)));
+        }
+    }
+
+    /// Put the result of a line edit into the editor.
+    fn apply_line_edit(ctx: &mut DelegateCtx, data: &mut AppState, edit: LineEdit) {
+        if data.workspace.read_only {
+            log::info!("not editing, the file is read-only");
+            ctx.submit_command(CustomTextComponent::EDIT_REFUSED);
+            return;
+        }
+        if edit.text != data.workspace.input_text {
+            data.workspace.input_text = edit.text;
+            data.workspace.dirty = true;
+        }
+        let selection = Selection::new(edit.anchor, edit.active);
+        ctx.submit_command(CustomTextComponent::SET_SELECTION.with(selection));
+    }
+
+    /// Replace the selection of the editor with `text`.
+    fn insert_text(ctx: &mut DelegateCtx, data: &mut AppState, text: &str) {
+        let workspace = &data.workspace;
+        let edit = snippet::insert(
+            &workspace.input_text,
+            workspace.selection_anchor,
+            workspace.selection_active,
+            text,
+        );
+        Delegate::apply_line_edit(ctx, data, edit);
+    }
+
+    fn go_to_line_modal() -> impl Widget<AppState> {
+        let input = TextBox::new()
+            .with_placeholder("line, line:col, +N or -N")
+            .fix_width(240.0)
+            .lens(AppState::modal_input);
+
+        let go = IconButton::from_label(
+            Label::new("Go").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+            ctx.submit_command(print_command::GO_TO_LINE);
+        });
+
+        let cancel = IconButton::from_label(
+            Label::new("Cancel").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
+
+        Flex::column()
+            .with_child(Label::new("Go to Line").with_text_color(crate::theme::BASIC_TEXT_COLOR))
+            .with_default_spacer()
+            .with_child(input)
+            .with_default_spacer()
+            .with_child(
+                Flex::row()
+                    .with_child(go)
+                    .with_default_spacer()
+                    .with_child(cancel),
+            )
+            .padding(10.0)
+            .background(crate::theme::TOOL_WINDOW_COLOR)
+    }
+
+    fn reload_prompt() -> impl Widget<AppState> {
+        let reload = IconButton::from_label(
+            Label::new("Reload").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(print_command::RELOAD_FILE);
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
 
+        let keep = IconButton::from_label(
+            Label::new("Keep mine").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
+
+        Flex::column()
+            .with_child(
+                Label::new("The file has been changed on disk. Reload it?")
+                    .with_text_color(crate::theme::BASIC_TEXT_COLOR),
+            )
+            .with_default_spacer()
+            .with_child(
+                Flex::row()
+                    .with_child(reload)
+                    .with_default_spacer()
+                    .with_child(keep),
+            )
+            .padding(10.0)
+            .background(crate::theme::TOOL_WINDOW_COLOR)
+    }
+
+    /// Go on with what waited for the unsaved changes prompt, once the
+    /// changes were saved or discarded.
+    fn after_unsaved_prompt(&mut self, ctx: &mut DelegateCtx, data: &mut AppState) {
+        self.resume_after_save = false;
+        if let Some(action) = self.after_prompt.take() {
+            self.run_after_prompt(ctx, data, action);
+        }
+    }
+
+    /// Run `action` right away when the buffer has no unsaved changes, else
+    /// ask about them first.
+    fn unless_dirty(&mut self, ctx: &mut DelegateCtx, data: &mut AppState, action: AfterPrompt) {
+        if !data.workspace.dirty {
+            self.run_after_prompt(ctx, data, action);
+            return;
+        }
+        self.after_prompt = Some(action);
+        self.resume_after_save = false;
+        ctx.submit_command(ModalHost::<AppState>::make_modal_command(
+            Delegate::unsaved_prompt,
+        ));
+    }
+
+    fn run_after_prompt(
+        &mut self,
+        ctx: &mut DelegateCtx,
+        data: &mut AppState,
+        action: AfterPrompt,
+    ) {
+        match action {
+            AfterPrompt::CloseWindow(id) => {
+                self.close_confirmed = true;
+                ctx.submit_command(commands::CLOSE_WINDOW.to(id));
+            }
+            AfterPrompt::NewFile => self.new_file(data),
+            AfterPrompt::Quit => Delegate::quit(ctx, data),
+            AfterPrompt::Open {
+                path,
+                label,
+                force,
+                line,
+            } => {
+                self.load_file_as(path, label, force);
+                self.pending_line = line;
+            }
+            AfterPrompt::ReopenClosed => match data.pop_closed() {
+                Some(path) => self.load_file(path.to_path_buf()),
+                None => log::info!("no closed file to reopen"),
+            },
+            AfterPrompt::OpenPath(path) => {
+                self.open_path(ctx, data, &path);
+                self.watch_dir(data);
+                self.load_dir(data);
+            }
+        }
+    }
+
+    /// Keep what the next start restores, then quit.
+    fn quit(ctx: &mut DelegateCtx, data: &mut AppState) {
+        data.remember_expanded();
+        data.remember_position();
+        data.save_global_config();
+        ctx.submit_command(commands::QUIT_APP);
+    }
+
+    /// Replace the open file with an empty untitled buffer, it goes to the
+    /// save panel when first saved.
+    fn new_file(&mut self, data: &mut AppState) {
+        self.loading_file = None;
+        self.pending_line = None;
+        if let Some(old) = self.watched_file.take() {
+            self.watcher.unwatch(&old, OPEN_FILE_TOKEN);
+        }
+        data.new_untitled();
+    }
+
+    fn unsaved_prompt() -> impl Widget<AppState> {
+        let save = IconButton::from_label(
+            Label::new("Save").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+            ctx.submit_command(print_command::SAVE_AND_CLOSE);
+        });
+
+        let discard = IconButton::from_label(
+            Label::new("Don't Save").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+            ctx.submit_command(print_command::DISCARD_AND_CLOSE);
+        });
+
+        let cancel = IconButton::from_label(
+            Label::new("Cancel").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
+
+        let buttons = Flex::row()
+            .with_child(save)
+            .with_default_spacer()
+            .with_child(discard)
+            .with_default_spacer()
+            .with_child(cancel);
+
+        Flex::column()
+            .with_child(
+                Label::new(|data: &AppState, _env: &Env| {
+                    format!("Save the changes to {}?", data.workspace.file_title())
+                })
+                .with_text_color(crate::theme::BASIC_TEXT_COLOR),
+            )
+            .with_default_spacer()
+            .with_child(buttons)
+            .padding(10.0)
+            .background(crate::theme::TOOL_WINDOW_COLOR)
+    }
+
+    fn binary_file_prompt(path: Arc<Path>) -> impl Widget<AppState> {
+        let message = format!(
+            "{} looks like a binary file and can't be shown as text.",
+            path.display()
+        );
+        let open = IconButton::from_label(
+            Label::new("Open anyway").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(move |ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(print_command::FORCE_OPEN_FILE.with(path.clone()));
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
+
+        let cancel = IconButton::from_label(
+            Label::new("Cancel").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
+
+        Flex::column()
+            .with_child(Label::new(message).with_text_color(crate::theme::BASIC_TEXT_COLOR))
+            .with_default_spacer()
+            .with_child(
+                Flex::row()
+                    .with_child(open)
+                    .with_default_spacer()
+                    .with_child(cancel),
+            )
+            .padding(10.0)
+            .background(crate::theme::TOOL_WINDOW_COLOR)
+    }
+
+    fn about() -> impl Widget<AppState> {
+        let version = format!("Version {}", env!("CARGO_PKG_VERSION"));
+        // there is no browser opener at hand, a click copies the link instead
+        let link = Label::new(REPOSITORY_URL)
+            .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+            .on_click(|_ctx, _data: &mut AppState, _env| {
+                Application::global().clipboard().put_string(REPOSITORY_URL);
+            });
+
+        let ok = IconButton::from_label(
+            Label::new("OK").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
+
+        Flex::column()
+            .with_child(
+                Label::new("Conan")
+                    .with_text_size(20.0)
+                    .with_text_color(crate::theme::BASIC_TEXT_COLOR),
+            )
+            .with_default_spacer()
+            .with_child(Label::new(version).with_text_color(crate::theme::BASIC_TEXT_COLOR))
+            .with_default_spacer()
+            .with_child(link)
+            .with_default_spacer()
+            .with_child(ok)
+            .padding(10.0)
+            .background(crate::theme::TOOL_WINDOW_COLOR)
+    }
+
+    fn alert(message: String) -> impl Widget<AppState> {
+        let ok = IconButton::from_label(
+            Label::new("OK").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
+
+        Flex::column()
+            .with_child(Label::new(message).with_text_color(crate::theme::BASIC_TEXT_COLOR))
+            .with_default_spacer()
+            .with_child(ok)
+            .padding(10.0)
+            .background(crate::theme::TOOL_WINDOW_COLOR)
+    }
+
+    fn open_path(&mut self, ctx: &mut DelegateCtx, state: &mut AppState, path: &Path) {
+        if path.is_dir() {
+            state.set_dir(path.to_owned());
+            state.add_recent(path);
+            if let Some(path) = state.project_file() {
+                self.load_file(path);
+            }
+            ctx.submit_command(print_command::OPEN);
+            return;
+        }
+
+        if !path.is_file() {
+            log::info!("can not open: {:?}", path);
+            return;
+        }
+
+        // binary files are turned down by `load_file`
+        if let Some(parent) = path.parent() {
+            state.set_dir(Some(parent.to_owned()));
+        }
+        self.load_file(path.to_owned());
+        ctx.submit_command(print_command::OPEN);
+    }
+
+    fn save_file(ctx: &mut DelegateCtx, data: &mut AppState) -> Handled {
+        let buf = match &data.current_file {
+            // an untitled buffer, ask where it goes
+            None => {
+                ctx.submit_command(commands::SHOW_SAVE_PANEL.with(FileDialogOptions::new()));
+                return Handled::Yes;
+            }
+            Some(path) => path.to_path_buf(),
+        };
+
+        if data.workspace.read_only {
+            log::info!("not saving {:?}, it is read-only", buf);
+            return Handled::Yes;
+        }
         if data.workspace.input_text == data.workspace.origin_text {
+            data.workspace.dirty = false;
             return Handled::Yes;
         }
 
-        let mut ifile = OpenOptions::new()
-            .read(true)
+        Delegate::tidy_before_save(ctx, data);
+        match Delegate::write_file(&buf, &data.workspace) {
+            Ok(_) => {
+                data.workspace.mark_saved();
+                log::info!("save file: {:?}", buf)
+            }
+            Err(e) => Delegate::save_failed(ctx, &buf, e),
+        }
+
+        return Handled::Yes;
+    }
+
+    /// Save without any prompt or alert, the watcher ignores the write as the
+    /// disk hash is updated with it.
+    fn auto_save(data: &mut AppState) {
+        let path = match &data.current_file {
+            None => return,
+            Some(path) => path.to_path_buf(),
+        };
+        if data.workspace.read_only {
+            return;
+        }
+
+        match Delegate::write_file(&path, &data.workspace) {
+            Ok(_) => {
+                data.workspace.mark_saved();
+                log::info!("auto save file: {:?}", path)
+            }
+            Err(e) => log::error!("Failed to auto save: {}", e),
+        }
+    }
+
+    fn save_file_as(&mut self, ctx: &mut DelegateCtx, data: &mut AppState, path: &Path) {
+        Delegate::tidy_before_save(ctx, data);
+        match Delegate::write_file(path, &data.workspace) {
+            Ok(_) => {
+                data.workspace.mark_saved();
+                // the copy was just written, so it can be edited
+                data.workspace.read_only = false;
+                data.set_current_path(path.to_path_buf());
+                self.watch_file(data);
+                log::info!("save file as: {:?}", path);
+                if self.resume_after_save {
+                    self.after_unsaved_prompt(ctx, data);
+                }
+            }
+            Err(e) => Delegate::save_failed(ctx, path, e),
+        }
+    }
+
+    /// Strip trailing whitespace and fix the final newline in the buffer,
+    /// as configured, keeping the selection on the same text. Auto-save
+    /// skips this, it would eat a space just typed at the end of a line.
+    fn tidy_before_save(ctx: &mut DelegateCtx, data: &mut AppState) {
+        let trim = data.trim_trailing_whitespace();
+        let final_newline = data.ensure_final_newline();
+        if !trim && !final_newline {
+            return;
+        }
+
+        let workspace = &mut data.workspace;
+        let tidy = Tidy {
+            trim_lines: trim,
+            keep_hard_breaks: language::has_hard_line_breaks(&workspace.language),
+            final_newline,
+        };
+        let (text, removed) = tidy.apply(&workspace.input_text);
+        if text == workspace.input_text {
+            return;
+        }
+
+        let anchor = whitespace::shift_offset(workspace.selection_anchor, &removed);
+        let active = whitespace::shift_offset(workspace.selection_active, &removed);
+        workspace.input_text = text;
+        workspace.selection_anchor = anchor;
+        workspace.selection_active = active;
+        ctx.submit_command(CustomTextComponent::SET_SELECTION.with(Selection::new(anchor, active)));
+    }
+
+    /// Write the buffer in the encoding of its file. Text that encoding
+    /// can't hold fails with `encoding::Unmappable`, before the file is
+    /// touched.
+    fn write_file(path: &Path, workspace: &Workspace) -> io::Result<()> {
+        let content = encoding::encode(&workspace.input_text, &workspace.encoding, workspace.bom)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let mut file = OpenOptions::new()
             .write(true)
             .create(true)
-            .open(&buf)
-            .expect("unable to open file");
-
-        let result = ifile.write_all(data.text().as_bytes());
+            .truncate(true)
+            .open(path)?;
+        file.write_all(&content)
+    }
 
-        match result {
-            Ok(_) => log::info!("save file: {:?}", buf),
-            Err(e) => log::info!("Failed to write data: {}", { e }),
+    /// Tell why the save failed, offering to save as UTF-8 when the
+    /// encoding of the file can't hold the text.
+    fn save_failed(ctx: &mut DelegateCtx, path: &Path, e: io::Error) {
+        log::error!("Failed to write data: {}", e);
+        let message = format!("Unable to save {}: {}", path.display(), e);
+        let unmappable = e
+            .get_ref()
+            .map_or(false, |inner| inner.is::<encoding::Unmappable>());
+        if !unmappable {
+            ctx.submit_command(ModalHost::<AppState>::make_modal_command(move || {
+                Delegate::alert(message.clone())
+            }));
+            return;
         }
 
-        return Handled::Yes;
+        let path = path.to_path_buf();
+        ctx.submit_command(ModalHost::<AppState>::make_modal_command(move || {
+            let path = path.clone();
+            let save = IconButton::from_label(
+                Label::new("Save as UTF-8").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+            )
+            .on_click(move |ctx, _data: &mut AppState, _env| {
+                ctx.submit_command(print_command::SAVE_AS_UTF8.with(path.clone()));
+                ctx.submit_command(ModalHost::DISMISS_MODAL);
+            });
+
+            let cancel = IconButton::from_label(
+                Label::new("Cancel").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+            )
+            .on_click(|ctx, _data: &mut AppState, _env| {
+                ctx.submit_command(ModalHost::DISMISS_MODAL);
+            });
+
+            Flex::column()
+                .with_child(
+                    Label::new(message.clone()).with_text_color(crate::theme::BASIC_TEXT_COLOR),
+                )
+                .with_default_spacer()
+                .with_child(
+                    Flex::row()
+                        .with_child(save)
+                        .with_default_spacer()
+                        .with_child(cancel),
+                )
+                .padding(10.0)
+                .background(crate::theme::TOOL_WINDOW_COLOR)
+        }));
     }
 
-    fn paint_preferences() -> impl Widget<Workspace> {
-        let flex = Flex::column()
-            .with_child(Label::new("preferences").with_text_color(crate::theme::BASIC_TEXT_COLOR))
-            .with_default_spacer();
+    fn preferences() -> impl Widget<AppState> {
+        fn row(label: &str, control: impl Widget<Params> + 'static) -> impl Widget<Params> {
+            Flex::row()
+                .with_child(
+                    Label::new(label)
+                        .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+                        .fix_width(100.0),
+                )
+                .with_child(control)
+        }
+
+        fn stepper(min: f64, max: f64, step: f64) -> impl Widget<f64> {
+            Flex::row()
+                .with_child(
+                    Label::new(|value: &f64, _env: &Env| format!("{}", value))
+                        .with_text_color(crate::theme::BASIC_TEXT_COLOR),
+                )
+                .with_child(Stepper::new().with_range(min, max).with_step(step))
+        }
+
+        let auto_save_secs = lens::Map::new(
+            |secs: &u64| *secs as f64,
+            |secs: &mut u64, value: f64| *secs = value as u64,
+        );
+        let large_file_mb = lens::Map::new(
+            |mb: &u64| *mb as f64,
+            |mb: &mut u64, value: f64| *mb = value as u64,
+        );
+        let tab_size = lens::Map::new(
+            |indentation: &Indentation| indentation.width as f64,
+            |indentation: &mut Indentation, value: f64| indentation.width = value as usize,
+        );
+
+        // picking a mode by hand stops following the OS, as the menu does
+        let dark_mode = lens::Map::new(
+            |params: &Params| params.dark_mode,
+            |params: &mut Params, dark: bool| {
+                // the lens writes back on every event, not only on a change
+                if params.dark_mode != dark {
+                    params.dark_mode = dark;
+                    params.follow_system_theme = false;
+                }
+            },
+        );
+
+        let settings = Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(row("Font", TextBox::new().lens(Params::font_family)))
+            .with_default_spacer()
+            .with_child(row(
+                "Font size",
+                stepper(MIN_FONT_SIZE, MAX_FONT_SIZE, 1.0).lens(Params::font_size),
+            ))
+            .with_default_spacer()
+            .with_child(row(
+                "Tab size",
+                stepper(1.0, 16.0, 1.0).lens(Params::indentation.then(tab_size)),
+            ))
+            .with_default_spacer()
+            .with_child(row("Word wrap", Checkbox::new("").lens(Params::word_wrap)))
+            .with_default_spacer()
+            .with_child(row(
+                "Max text width (0 for none)",
+                stepper(0.0, 4000.0, 20.0).lens(Params::max_content_width),
+            ))
+            .with_default_spacer()
+            .with_child(row(
+                "Show whitespace",
+                Checkbox::new("").lens(Params::show_whitespace),
+            ))
+            .with_default_spacer()
+            .with_child(row(
+                "Trim trailing whitespace on save",
+                Checkbox::new("").lens(Params::trim_trailing_whitespace),
+            ))
+            .with_default_spacer()
+            .with_child(row(
+                "Final newline on save",
+                Checkbox::new("").lens(Params::ensure_final_newline),
+            ))
+            .with_default_spacer()
+            .with_child(row(
+                "Follow system appearance",
+                Checkbox::new("").lens(Params::follow_system_theme),
+            ))
+            .with_default_spacer()
+            .with_child(row("Dark mode", Checkbox::new("").lens(dark_mode)))
+            .with_default_spacer()
+            .with_child(row("Auto save", Checkbox::new("").lens(Params::auto_save)))
+            .with_default_spacer()
+            .with_child(row(
+                "Every (secs)",
+                stepper(1.0, 600.0, 1.0).lens(Params::auto_save_secs.then(auto_save_secs)),
+            ))
+            .with_default_spacer()
+            .with_child(row(
+                "Large file (MB, 0 for none)",
+                stepper(0.0, 1024.0, 1.0).lens(Params::large_file_mb.then(large_file_mb)),
+            ))
+            .lens(AppState::params);
+
+        // drawn with the editor font, so it follows the settings above as they change
+        let preview = Label::new("The quick brown fox jumps over the lazy dog. 0O 1lI {}")
+            .with_font(crate::theme::WRITING_FONT)
+            .with_text_color(crate::theme::BASIC_TEXT_COLOR);
+        let missing_font = Label::new(|data: &AppState, _env: &Env| {
+            if data.missing_font {
+                format!(
+                    "{} is not installed, a monospace font is used.",
+                    data.params.font_family
+                )
+            } else {
+                "".to_string()
+            }
+        })
+        .with_text_color(crate::theme::BASIC_TEXT_COLOR);
+
+        let close = IconButton::from_label(
+            Label::new("Close").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .on_click(|ctx, _data: &mut AppState, _env| {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+        });
 
-        return flex;
+        Flex::column()
+            .cross_axis_alignment(CrossAxisAlignment::Start)
+            .with_child(Label::new("Preferences").with_text_color(crate::theme::BASIC_TEXT_COLOR))
+            .with_default_spacer()
+            .with_child(settings)
+            .with_default_spacer()
+            .with_child(preview)
+            .with_child(missing_font)
+            .with_default_spacer()
+            .with_child(close)
+            .padding(10.0)
+            .background(crate::theme::TOOL_WINDOW_COLOR)
+            .controller(SavePreferences)
     }
 }
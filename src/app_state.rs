@@ -1,18 +1,42 @@
 use std::fmt::Debug;
-use std::fs::File;
-use std::io::Read;
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc};
+use std::sync::Arc;
 
 use druid::{Data, Lens};
 use serde::{Deserialize, Serialize};
 
 use crate::model::file_tree::FileEntry;
+use crate::model::outline::OutlineEntry;
+use crate::model::search::SearchState;
+use crate::support::appearance;
 use crate::support::directory;
-use std::collections::HashMap;
+use crate::support::encoding::DecodedText;
+use crate::support::git::{GitStatus, LineChange};
+use crate::support::indentation::{self, Indentation};
+use crate::support::language;
+use crate::support::snippet;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// how many entries the recent list keeps.
+const MAX_RECENT: usize = 10;
+/// how many closed files Reopen Closed File can go back.
+const MAX_CLOSED: usize = 20;
+
+/// bump when a change to `AppState` can't be read by older serde defaults.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// the range the editor font size is kept in.
+pub const MIN_FONT_SIZE: f64 = 6.0;
+pub const MAX_FONT_SIZE: f64 = 72.0;
 
 #[derive(Serialize, Deserialize, Clone, Data, Lens, Debug)]
 pub struct AppState {
+    #[serde(default)]
+    pub config_version: u32,
+
     pub title: String,
     #[serde(skip_serializing, skip_deserializing)]
     pub workspace: Workspace,
@@ -29,6 +53,103 @@ pub struct AppState {
 
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_dir: Option<Arc<Path>>,
+
+    /// text typed into the input of the currently shown modal.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub modal_input: String,
+
+    /// message to show once the window is up, e.g. when the config was lost.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub startup_alert: Option<String>,
+
+    /// the editor is split into two panes showing the open file.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub split: bool,
+    /// the markdown preview is shown next to the editor.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub preview: bool,
+
+    /// the file last revealed in the project tree, which selects it.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub revealed: Option<Arc<Path>>,
+
+    /// recently opened files and dirs, the latest first.
+    #[serde(default)]
+    pub recent: Arc<Vec<Arc<Path>>>,
+    /// recently opened project dirs, the latest first.
+    #[serde(default)]
+    pub recent_dirs: Arc<Vec<RecentDir>>,
+
+    /// chords by action name overriding `keymap::DEFAULT_KEYMAP`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keymap: Arc<BTreeMap<String, String>>,
+
+    /// where the caret and scroll were left in the recent files, by path.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub positions: Arc<BTreeMap<String, FilePosition>>,
+
+    /// settings from `.print/project.json` of `current_dir`, if it has one.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub project_config: Option<ProjectConfig>,
+
+    /// what the last run of the project command printed so far.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub output: Arc<String>,
+    /// the project command is still running.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub running: bool,
+
+    #[serde(skip_serializing, skip_deserializing)]
+    pub search: SearchState,
+
+    /// the git status of the changed paths below `current_dir`, empty
+    /// outside of a repository.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub git_status: Arc<HashMap<String, GitStatus>>,
+
+    /// `params.font_family` is not installed, the editor falls back to a
+    /// monospace font.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub missing_font: bool,
+
+    /// how many untitled buffers were created, numbers them.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub untitled_count: usize,
+
+    /// the files the editor was switched away from, the latest last.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub closed: Arc<Vec<ClosedFile>>,
+
+    /// whether the OS appearance is dark, looked up while
+    /// `params.follow_system_theme` is on.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub system_dark: Option<bool>,
+}
+
+/// A recently opened project dir and the state its tree was left in.
+#[derive(Serialize, Deserialize, Clone, Data, Lens, Debug)]
+pub struct RecentDir {
+    pub path: Arc<Path>,
+    /// the expanded dirs of the tree, relative to `path`.
+    #[serde(default)]
+    pub expanded: Arc<Vec<String>>,
+}
+
+/// The selection and scroll a file was left with, restored when it is
+/// opened again.
+#[derive(Serialize, Deserialize, Clone, Copy, Data, PartialEq, Debug)]
+pub struct FilePosition {
+    pub anchor: usize,
+    pub active: usize,
+    /// the center of the visible part, as a fraction of the text height.
+    pub scroll: f64,
+}
+
+/// A file that was closed, with where it was left.
+#[derive(Clone, Data, Debug)]
+pub struct ClosedFile {
+    pub path: Arc<Path>,
+    pub position: FilePosition,
 }
 
 #[derive(Serialize, Deserialize, Clone, Data, Lens, Debug)]
@@ -62,6 +183,7 @@ impl Default for ViewCore {
 impl Default for AppState {
     fn default() -> Self {
         Self {
+            config_version: CONFIG_VERSION,
             title: "".to_string(),
             workspace: Default::default(),
             params: Default::default(),
@@ -69,28 +191,509 @@ impl Default for AppState {
             current_file: None,
             current_dir: None,
             last_dir: None,
+            modal_input: "".to_string(),
+            startup_alert: None,
+            split: false,
+            preview: false,
+            revealed: None,
+            recent: Default::default(),
+            recent_dirs: Default::default(),
+            keymap: Default::default(),
+            positions: Default::default(),
+            project_config: None,
+            output: Default::default(),
+            running: false,
+            search: Default::default(),
+            git_status: Default::default(),
+            missing_font: false,
+            untitled_count: 0,
+            closed: Default::default(),
+            system_dark: None,
         }
     }
 }
 
 impl AppState {
-    pub fn open_file(&mut self, path: impl Into<Option<PathBuf>>) {
-        let path: Option<Arc<Path>> = path.into().map(Into::into);
+    /// Show `content`, read from `path` by `Delegate::load_file`, in the editor.
+    pub fn set_file(&mut self, path: PathBuf, content: DecodedText) {
+        if self.current_file.as_deref() != Some(path.as_path()) {
+            self.push_closed();
+        }
+        Arc::make_mut(&mut self.closed).retain(|closed| *closed.path != *path);
+        self.remember_position();
+        let configured = self.indentation();
+        self.workspace.indentation =
+            indentation::detect(&content.text, configured).unwrap_or(configured);
+        self.workspace.large_file = self.is_large_file(&content.text);
+        self.workspace.input_text = content.text;
+        self.workspace.mark_saved();
+        self.workspace.encoding = content.encoding;
+        self.workspace.bom = content.bom;
+        self.workspace.language = if self.workspace.large_file {
+            language::PLAIN_TEXT.to_string()
+        } else {
+            self.language_of(&path)
+        };
+        self.workspace.current_file = Arc::new(path.clone());
+        self.workspace.untitled = 0;
+        self.workspace.line_changes = Default::default();
+        self.workspace.read_only = fs::metadata(&path)
+            .map(|metadata| metadata.permissions().readonly())
+            .unwrap_or(false);
+
+        self.add_recent(&path);
+        self.prune_positions();
+        self.current_file = Some(path.into());
+        self.save_global_config();
+        self.remember_project_file();
+    }
 
-        let mut file_content: Vec<u8> = Vec::new();
-        let mut file = File::open(&path.as_ref().unwrap()).expect("Unable to open file");
-        if let Err(err) = file.read_to_end(&mut file_content) {
-            log::error!("open file error: {:?}", err);
+    /// Keep the selection and scroll of the open file for the next time it
+    /// is opened, the config is saved by the caller.
+    pub fn remember_position(&mut self) {
+        if let Some((path, position)) = self.current_position() {
+            Arc::make_mut(&mut self.positions).insert(format!("{}", path.display()), position);
+        }
+    }
+
+    /// The open file and where its selection and scroll are.
+    fn current_position(&self) -> Option<(Arc<Path>, FilePosition)> {
+        // at startup the buffer is still empty while `current_file` is set
+        let path = match &self.current_file {
+            Some(path) if **path == **self.workspace.current_file => path.clone(),
+            _ => return None,
+        };
+        let (top, bottom) = self.workspace.viewport;
+        let position = FilePosition {
+            anchor: self.workspace.selection_anchor,
+            active: self.workspace.selection_active,
+            scroll: (top + bottom) / 2.0,
+        };
+        Some((path, position))
+    }
+
+    /// Put the open file on the closed stack, before the editor leaves it.
+    fn push_closed(&mut self) {
+        let (path, position) = match self.current_position() {
+            Some(current) => current,
+            None => return,
+        };
+        let closed = Arc::make_mut(&mut self.closed);
+        closed.retain(|closed| closed.path != path);
+        closed.push(ClosedFile { path, position });
+        if closed.len() > MAX_CLOSED {
+            closed.remove(0);
+        }
+    }
+
+    /// Take the latest closed file that still exists off the stack, its
+    /// position is kept for `Delegate::restore_position`.
+    pub fn pop_closed(&mut self) -> Option<Arc<Path>> {
+        let closed = Arc::make_mut(&mut self.closed);
+        while let Some(file) = closed.pop() {
+            if file.path.exists() {
+                let key = format!("{}", file.path.display());
+                Arc::make_mut(&mut self.positions).insert(key, file.position);
+                return Some(file.path);
+            }
+            log::info!("closed file is gone: {:?}", file.path);
+        }
+        None
+    }
+
+    /// The position `path` was left at, if it is still in the recent list.
+    pub fn position_of(&self, path: &Path) -> Option<FilePosition> {
+        self.positions.get(&format!("{}", path.display())).copied()
+    }
+
+    /// Only the recent files keep their position, so the config stays small.
+    fn prune_positions(&mut self) {
+        let recent: HashSet<String> = self
+            .recent
+            .iter()
+            .map(|path| format!("{}", path.display()))
+            .collect();
+        if self.positions.keys().any(|path| !recent.contains(path)) {
+            Arc::make_mut(&mut self.positions).retain(|path, _| recent.contains(path));
+        }
+    }
+
+    /// Expand the project tree down to the open file and select it.
+    pub fn reveal_current_file(&mut self) {
+        let path = match &self.current_file {
+            Some(path) => path.clone(),
+            None => return,
+        };
+        self.reveal_path(&path);
+    }
+
+    /// Expand the project tree down to `path` and select it, a dir is
+    /// expanded as well to show what is in it.
+    pub fn reveal_path(&mut self, path: &Path) {
+        if !self.entry.expand_to(path) {
+            return;
+        }
+        if let Some(entry) = self.entry.find_mut(path).filter(|entry| entry.is_dir) {
+            entry.expanded = true;
+        }
+        self.revealed = Some(path.to_path_buf().into());
+    }
+
+    /// Move `path` to the top of the recent list, the config is saved by the caller.
+    pub fn add_recent(&mut self, path: &Path) {
+        let recent = Arc::make_mut(&mut self.recent);
+        recent.retain(|item| &**item != path);
+        recent.insert(0, path.into());
+        recent.truncate(MAX_RECENT);
+    }
+
+    fn add_recent_dir(&mut self, dir: &Path) {
+        let recent = Arc::make_mut(&mut self.recent_dirs);
+        let previous = recent.iter().position(|item| &*item.path == dir);
+        let item = match previous {
+            Some(index) => recent.remove(index),
+            None => RecentDir {
+                path: dir.into(),
+                expanded: Default::default(),
+            },
+        };
+        recent.insert(0, item);
+        recent.truncate(MAX_RECENT);
+    }
+
+    /// Forget the recent dirs that were moved or deleted since.
+    pub fn prune_recent_dirs(&mut self) {
+        if self.recent_dirs.iter().any(|item| !item.path.is_dir()) {
+            Arc::make_mut(&mut self.recent_dirs).retain(|item| item.path.is_dir());
+        }
+    }
+
+    /// Keep the expanded dirs of the tree with the open dir in `recent_dirs`,
+    /// for `saved_expanded` once it is opened again.
+    pub fn remember_expanded(&mut self) {
+        let dir = match &self.current_dir {
+            Some(dir) => dir.clone(),
+            None => return,
+        };
+        // the tree is still being scanned, there is nothing to keep yet
+        if self.entry.path.is_empty() {
+            return;
+        }
+
+        let mut expanded: Vec<String> = self
+            .entry
+            .expanded_paths()
+            .iter()
+            .filter_map(|path| Path::new(path).strip_prefix(&dir).ok())
+            .filter(|relative| !relative.as_os_str().is_empty())
+            .map(|relative| relative.to_string_lossy().to_string())
+            .collect();
+        expanded.sort();
+
+        let recent = Arc::make_mut(&mut self.recent_dirs);
+        if let Some(item) = recent.iter_mut().find(|item| item.path == dir) {
+            item.expanded = Arc::new(expanded);
+        }
+    }
+
+    /// The expanded dirs `remember_expanded` kept for the open dir, as the
+    /// paths of the tree.
+    pub fn saved_expanded(&self) -> HashSet<String> {
+        let dir = match &self.current_dir {
+            Some(dir) => dir,
+            None => return HashSet::new(),
+        };
+        self.recent_dirs
+            .iter()
+            .filter(|item| &item.path == dir)
+            .flat_map(|item| item.expanded.iter())
+            .map(|relative| dir.join(relative).display().to_string())
+            .collect()
+    }
+
+    pub fn clear_recent(&mut self) {
+        self.recent = Default::default();
+        self.save_global_config();
+    }
+
+    /// The file the project was left at, unless a file of it is already open.
+    pub fn project_file(&self) -> Option<PathBuf> {
+        let dir = self.current_dir.as_ref()?;
+        let file = dir.join(self.project_config.as_ref()?.current_file.as_ref()?);
+
+        let is_open = match &self.current_file {
+            Some(current) => current.starts_with(dir),
+            None => false,
+        };
+        if !is_open && file.is_file() {
+            Some(file)
+        } else {
+            None
+        }
+    }
+
+    fn remember_project_file(&mut self) {
+        let (dir, file) = match (&self.current_dir, &self.current_file) {
+            (Some(dir), Some(file)) => (dir.clone(), file.clone()),
+            _ => return,
+        };
+
+        if let Some(config) = &mut self.project_config {
+            if let Ok(relative) = file.strip_prefix(&dir) {
+                config.current_file = Some(relative.to_path_buf().into());
+                directory::save_project_config(&dir, config);
+            }
+        }
+    }
+
+    /// the language picked for `path` in the project config, else the one
+    /// of its extension.
+    fn language_of(&self, path: &Path) -> String {
+        let picked = match (&self.current_dir, &self.project_config) {
+            (Some(dir), Some(config)) => path
+                .strip_prefix(dir)
+                .ok()
+                .and_then(|relative| config.languages.get(&*relative.to_string_lossy()))
+                .cloned(),
+            _ => None,
+        };
+
+        picked.unwrap_or_else(|| language::language_for(path).to_string())
+    }
+
+    /// Override the language of the open file, kept in the project config.
+    pub fn set_language(&mut self, name: String) {
+        self.workspace.language = name.clone();
+
+        let (dir, file) = match (&self.current_dir, &self.current_file) {
+            (Some(dir), Some(file)) => (dir.clone(), file.clone()),
+            _ => return,
+        };
+        let relative = match file.strip_prefix(&dir) {
+            Ok(relative) => relative.to_string_lossy().to_string(),
+            Err(_) => return,
+        };
+
+        let config = self.project_config.get_or_insert_with(Default::default);
+        Arc::make_mut(&mut config.languages).insert(relative, name);
+        directory::save_project_config(&dir, config);
+    }
+
+    /// the project setting when there is one, else the OS appearance when
+    /// following it, else the global one.
+    pub fn dark_mode(&self) -> bool {
+        let global = match self.system_dark {
+            Some(dark) if self.params.follow_system_theme => dark,
+            _ => self.params.dark_mode,
+        };
+        self.project_config
+            .as_ref()
+            .and_then(|config| config.dark_mode)
+            .unwrap_or(global)
+    }
+
+    /// Whether `text` is over the size the editor stays responsive with.
+    pub fn is_large_file(&self, text: &str) -> bool {
+        let limit = self.params.large_file_mb * 1024 * 1024;
+        limit > 0 && text.len() as u64 > limit
+    }
+
+    /// the project setting when there is one, else the global one.
+    pub fn indentation(&self) -> Indentation {
+        self.project_config
+            .as_ref()
+            .and_then(|config| config.indentation)
+            .unwrap_or(self.params.indentation)
+    }
+
+    /// Switch the open file to `indentation` and keep it for the project,
+    /// or globally when no dir is open.
+    pub fn set_indentation(&mut self, indentation: Indentation) {
+        self.workspace.indentation = indentation;
+        if let Some(dir) = &self.current_dir {
+            let config = self.project_config.get_or_insert_with(Default::default);
+            config.indentation = Some(indentation);
+            directory::save_project_config(dir, config);
             return;
+        }
+
+        self.params.indentation = indentation;
+        self.save_global_config();
+    }
+
+    /// the project setting when there is one, else the global one.
+    pub fn trim_trailing_whitespace(&self) -> bool {
+        self.project_config
+            .as_ref()
+            .and_then(|config| config.trim_trailing_whitespace)
+            .unwrap_or(self.params.trim_trailing_whitespace)
+    }
+
+    /// the project setting when there is one, else the global one.
+    pub fn ensure_final_newline(&self) -> bool {
+        self.project_config
+            .as_ref()
+            .and_then(|config| config.ensure_final_newline)
+            .unwrap_or(self.params.ensure_final_newline)
+    }
+
+    /// the project setting when there is one, else the global one.
+    pub fn word_wrap(&self) -> bool {
+        self.project_config
+            .as_ref()
+            .and_then(|config| config.word_wrap)
+            .unwrap_or(self.params.word_wrap)
+    }
+
+    /// Flip word wrap where it is set, in the project config when it has
+    /// it and globally otherwise.
+    pub fn toggle_word_wrap(&mut self) {
+        let enabled = !self.word_wrap();
+        if let (Some(dir), Some(config)) = (&self.current_dir, &mut self.project_config) {
+            if config.word_wrap.is_some() {
+                config.word_wrap = Some(enabled);
+                directory::save_project_config(dir, config);
+                return;
+            }
+        }
+
+        self.params.word_wrap = enabled;
+        self.save_global_config();
+    }
+
+    pub fn toggle_dark_mode(&mut self) {
+        let enabled = !self.dark_mode();
+        if let (Some(dir), Some(config)) = (&self.current_dir, &mut self.project_config) {
+            if config.dark_mode.is_some() {
+                config.dark_mode = Some(enabled);
+                directory::save_project_config(dir, config);
+                return;
+            }
+        }
+
+        // picking a mode by hand stops following the OS until turned on again
+        self.params.dark_mode = enabled;
+        self.params.follow_system_theme = false;
+        self.save_global_config();
+    }
+
+    /// Grow or shrink the editor font by `step` points.
+    pub fn zoom(&mut self, step: f64) {
+        self.set_font_size(self.params.font_size + step);
+    }
+
+    pub fn reset_zoom(&mut self) {
+        self.set_font_size(default_font_size());
+    }
+
+    fn set_font_size(&mut self, size: f64) {
+        let size = size.max(MIN_FONT_SIZE).min(MAX_FONT_SIZE);
+        if size != self.params.font_size {
+            self.params.font_size = size;
+            self.save_global_config();
+        }
+    }
+
+    /// Nothing is open, no file, folder or untitled buffer, the welcome
+    /// screen shows instead of the editor.
+    pub fn is_empty_session(&self) -> bool {
+        self.current_file.is_none() && self.current_dir.is_none() && self.workspace.untitled == 0
+    }
+
+    /// Show an empty buffer without a file, numbered apart from the earlier
+    /// untitled ones.
+    pub fn new_untitled(&mut self) {
+        self.push_closed();
+        self.remember_position();
+        self.untitled_count += 1;
+        self.workspace.input_text = "".to_string();
+        self.workspace.mark_saved();
+        self.workspace.encoding = "UTF-8".to_string();
+        self.workspace.bom = false;
+        self.workspace.language = language::PLAIN_TEXT.to_string();
+        self.workspace.indentation = self.indentation();
+        self.workspace.current_file = Default::default();
+        self.workspace.untitled = self.untitled_count;
+        self.workspace.line_changes = Default::default();
+        self.workspace.read_only = false;
+        self.workspace.large_file = false;
+        self.workspace.selection_anchor = 0;
+        self.workspace.selection_active = 0;
+
+        self.current_file = None;
+        self.save_global_config();
+    }
+
+    /// Make `path` the open file, e.g. after the buffer was saved there.
+    pub fn set_current_path(&mut self, path: PathBuf) {
+        if !self.workspace.large_file {
+            self.workspace.language = self.language_of(&path);
+        }
+        self.workspace.current_file = Arc::new(path.clone());
+        self.workspace.untitled = 0;
+        self.current_file = Some(path.into());
+        self.save_global_config();
+        self.remember_project_file();
+    }
+
+    /// Point the open file at its new location after it, or one of its
+    /// parent dirs, was renamed.
+    pub fn move_current_file(&mut self, from: &Path, to: &Path) {
+        let current = match &self.current_file {
+            None => return,
+            Some(path) => path.to_path_buf(),
         };
 
-        let out = String::from_utf8_lossy(&*file_content);
+        if let Ok(rest) = current.strip_prefix(from) {
+            let moved = if rest.as_os_str().is_empty() {
+                to.to_path_buf()
+            } else {
+                to.join(rest)
+            };
+            self.workspace.current_file = Arc::new(moved.clone());
+            self.current_file = Some(moved.into());
+            self.save_global_config();
+        }
+    }
 
-        self.workspace.input_text = out.to_string();
-        let buf = path.clone().unwrap().to_path_buf();
-        self.workspace.current_file = Arc::new(buf.clone());
+    /// Close the open file if it is, or lives under, `path`. Unsaved changes
+    /// stay open, saving them writes the file again.
+    pub fn close_file_under(&mut self, path: &Path) {
+        let is_affected = match &self.current_file {
+            None => false,
+            Some(current) => current.starts_with(path) && !self.workspace.dirty,
+        };
 
-        self.current_file = path;
+        if is_affected {
+            self.push_closed();
+            self.workspace.input_text = "".to_string();
+            self.workspace.mark_saved();
+            self.workspace.current_file = Default::default();
+            self.workspace.read_only = false;
+            self.workspace.large_file = false;
+            self.current_file = None;
+            self.save_global_config();
+        }
+    }
+
+    /// Close the project dir, with the file open from it unless that has
+    /// unsaved changes. It can be reopened through `last_dir`.
+    pub fn close_dir(&mut self) {
+        self.remember_expanded();
+        let dir = match self.current_dir.take() {
+            Some(dir) => dir,
+            None => return,
+        };
+        self.close_file_under(&dir);
+
+        self.last_dir = Some(dir);
+        self.entry = FileEntry::new("".to_string());
+        self.project_config = None;
+        self.revealed = None;
+        self.search.results = Default::default();
+        self.git_status = Default::default();
+        self.workspace.project = "".to_string();
+        self.workspace.dir = Default::default();
         self.save_global_config();
     }
 
@@ -109,7 +712,14 @@ impl AppState {
                 self.workspace.dir = Arc::new(dir.clone().to_path_buf());
             }
 
-            self.entry = FileEntry::from_dir(self.workspace.project.clone(), &dir);
+            // the tree itself is scanned in the background, see `Delegate::load_dir`,
+            // keep the old one for a reload so its expanded dirs can be restored.
+            if self.current_dir.as_ref() != Some(&dir) {
+                self.remember_expanded();
+                self.entry = FileEntry::new(self.workspace.project.clone());
+            }
+            self.add_recent_dir(&dir);
+            self.project_config = directory::read_project_config(&dir);
             log::info!("open dir: {:?}", dir);
         }
 
@@ -119,25 +729,40 @@ impl AppState {
         self.save_global_config();
     }
 
+    /// `path` relative to `current_dir` with `/` between the names, as
+    /// written in docs and imports. Paths outside of it stay absolute.
+    pub fn relative_to_dir(&self, path: &Path) -> String {
+        let relative = match self.current_dir.as_ref().map(|dir| path.strip_prefix(dir)) {
+            Some(Ok(relative)) => relative,
+            _ => return format!("{}", path.display()),
+        };
+        let names: Vec<_> = relative.iter().map(|name| name.to_string_lossy()).collect();
+        names.join("/")
+    }
+
     pub fn text(&mut self) -> String {
         return self.workspace.input_text.clone();
     }
 
-    // todo: add save project config
     pub fn save_global_config(&mut self) {
         let mut current_state = self.clone();
 
+        current_state.config_version = CONFIG_VERSION;
         current_state.workspace = Default::default();
         current_state.entry = Default::default();
+        current_state.project_config = None;
 
         directory::save_config(&current_state);
     }
 
     pub fn setup_workspace(&mut self) {
         info!("init state: {:?}", self);
-        if let Some(path) = self.current_file.clone() {
-            &self.open_file(path.to_path_buf());
+        // looked up before the window shows, so it doesn't flash the other palette
+        if self.params.follow_system_theme {
+            self.system_dark = appearance::system_dark();
         }
+        self.prune_recent_dirs();
+        // the file itself is loaded once the window is up, see `Delegate::window_added`
         if let Some(path) = self.current_dir.clone() {
             &self.set_dir(path.to_path_buf());
         }
@@ -156,24 +781,187 @@ pub struct Workspace {
 
     #[serde(default)]
     current_file: Arc<PathBuf>,
+
+    /// hash of the content last read from or written to `current_file`,
+    /// used to tell our own saves apart from external changes.
+    #[serde(default)]
+    pub disk_hash: u64,
+
+    /// byte offsets of the editor selection, equal when it is a caret.
+    #[serde(default)]
+    pub selection_anchor: usize,
+    #[serde(default)]
+    pub selection_active: usize,
+
+    /// the encoding `current_file` is read and saved with.
+    #[serde(default)]
+    pub encoding: String,
+    /// whether `current_file` starts with a byte order mark.
+    #[serde(default)]
+    pub bom: bool,
+
+    /// the buffer has edits that are not saved yet.
+    #[serde(default)]
+    pub dirty: bool,
+
+    /// the language of `current_file`, see `language::language_for`.
+    #[serde(default)]
+    pub language: String,
+
+    /// the text the markdown preview shows, lags `input_text` while typing.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub preview_text: Arc<String>,
+
+    /// the number of an untitled buffer, 0 once it has a file.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub untitled: usize,
+
+    /// the indentation of the open file, detected when it was loaded.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub indentation: Indentation,
+
+    /// edits and saves are refused, set for files the user can't write and
+    /// toggled from the menu.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub read_only: bool,
+
+    /// the file is over `Params::large_file_mb`, the counts, git changes,
+    /// outline, minimap and whitespace marks are left out to keep up.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub large_file: bool,
+
+    /// the visible part of the text as fractions of its height, for the minimap.
+    #[serde(skip_serializing, skip_deserializing, default = "default_viewport")]
+    pub viewport: (f64, f64),
+
+    /// the lines of the buffer that differ from the file in git HEAD.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub line_changes: Arc<Vec<LineChange>>,
+
+    /// the headings of the buffer, lags `input_text` while typing.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub outline: OutlineEntry,
 }
 
 impl Workspace {
-    pub fn relative_path(&self) -> String {
-        match self.current_file.strip_prefix(&*self.dir) {
-            Ok(path) => {
-                let mut paths: Vec<String> = vec![];
-                for sub in path.iter() {
-                    paths.push(sub.to_str().unwrap().to_string())
-                }
-                if paths.len() == 0 {
-                    return self.project.to_string();
-                }
-                format!("{} > {}", self.project, paths.join(" > "))
-            }
-            Err(_) => self.project.to_string(),
+    /// Remember the buffer as what is on disk now, after a load or save.
+    pub fn mark_saved(&mut self) {
+        self.origin_text = self.input_text.clone();
+        self.disk_hash = content_hash(&self.input_text);
+        self.dirty = false;
+    }
+
+    /// the file name, `Untitled` for a buffer that was never saved.
+    pub fn file_name(&self) -> String {
+        match self.current_file.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None if self.untitled > 0 => format!("Untitled-{}", self.untitled),
+            None => "Untitled".to_string(),
         }
     }
+
+    /// the file name, prefixed with `•` when it has unsaved changes.
+    pub fn file_title(&self) -> String {
+        let name = self.file_name();
+        if self.dirty {
+            format!("• {}", name)
+        } else {
+            name
+        }
+    }
+
+    /// The caret position as `Ln N, Col M`, plus the count of selected
+    /// characters when the selection is not empty.
+    pub fn caret_position(&self) -> String {
+        let before = match self.input_text.get(..self.selection_active) {
+            Some(text) => text,
+            None => return "".to_string(),
+        };
+        let line = before.matches('\n').count() + 1;
+        let column = match before.rfind('\n') {
+            Some(index) => before[index + 1..].chars().count() + 1,
+            None => before.chars().count() + 1,
+        };
+
+        let start = self.selection_anchor.min(self.selection_active);
+        let end = self.selection_anchor.max(self.selection_active);
+        match self.input_text.get(start..end) {
+            Some(selected) if !selected.is_empty() => format!(
+                "Ln {}, Col {} ({} selected)",
+                line,
+                column,
+                selected.chars().count()
+            ),
+            _ => format!("Ln {}, Col {}", line, column),
+        }
+    }
+
+    /// whether the selection covers more than one line.
+    pub fn selection_spans_lines(&self) -> bool {
+        let start = self.selection_anchor.min(self.selection_active);
+        let end = self.selection_anchor.max(self.selection_active);
+        match self.input_text.get(start..end) {
+            Some(selected) => selected.contains('\n'),
+            None => false,
+        }
+    }
+
+    /// The byte offset for a go to line input: `line`, `line:col`, or
+    /// `+N`/`-N` lines from the caret. Line and column are clamped to the
+    /// text, `None` when the input can't be parsed.
+    pub fn go_to_line_offset(&self, input: &str) -> Option<usize> {
+        let mut parts = input.trim().splitn(2, ':');
+        let line_part = parts.next()?.trim();
+        let column = match parts.next() {
+            Some(column) => column.trim().parse::<usize>().ok()?,
+            None => 1,
+        };
+
+        let current = self.input_text.get(..self.selection_active).unwrap_or("");
+        let current_line = current.matches('\n').count() as i64 + 1;
+        let line = if let Some(delta) = line_part.strip_prefix('+') {
+            current_line + delta.parse::<i64>().ok()?
+        } else if let Some(delta) = line_part.strip_prefix('-') {
+            current_line - delta.parse::<i64>().ok()?
+        } else {
+            line_part.parse::<i64>().ok()?
+        };
+
+        let line_count = self.input_text.matches('\n').count() as i64 + 1;
+        let line = line.max(1).min(line_count) as usize;
+
+        let line_start = match line {
+            1 => 0,
+            _ => self.input_text.match_indices('\n').nth(line - 2)?.0 + 1,
+        };
+        let text = &self.input_text[line_start..];
+        let line_text = text.split('\n').next().unwrap_or("");
+        let offset = line_text
+            .char_indices()
+            .nth(column.max(1) - 1)
+            .map(|(index, _)| index)
+            .unwrap_or_else(|| line_text.len());
+
+        Some(line_start + offset)
+    }
+
+    /// The project and every dir down to the open file, then the file
+    /// itself, each with its path. Only the title outside of a project.
+    pub fn breadcrumbs(&self) -> Vec<(String, Option<PathBuf>)> {
+        let relative = match self.current_file.strip_prefix(&*self.dir) {
+            Ok(relative) if !self.project.is_empty() => relative,
+            _ => return vec![(self.file_title(), None)],
+        };
+
+        let dirty = if self.dirty { "• " } else { "" };
+        let mut path = self.dir.to_path_buf();
+        let mut crumbs = vec![(format!("{}{}", dirty, self.project), Some(path.clone()))];
+        for name in relative.iter() {
+            path.push(name);
+            crumbs.push((name.to_string_lossy().into_owned(), Some(path.clone())));
+        }
+        crumbs
+    }
 }
 
 impl Default for Workspace {
@@ -185,19 +973,185 @@ impl Default for Workspace {
             char_count: 0,
             dir: Default::default(),
             current_file: Default::default(),
+            disk_hash: 0,
+            selection_anchor: 0,
+            selection_active: 0,
+            encoding: "UTF-8".to_string(),
+            bom: false,
+            dirty: false,
+            language: language::PLAIN_TEXT.to_string(),
+            preview_text: Default::default(),
+            untitled: 0,
+            indentation: Default::default(),
+            read_only: false,
+            large_file: false,
+            viewport: default_viewport(),
+            line_changes: Default::default(),
+            outline: Default::default(),
         }
     }
 }
 
+fn default_viewport() -> (f64, f64) {
+    (0.0, 1.0)
+}
+
+pub fn content_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
 #[derive(Serialize, Deserialize, Clone, Data, Lens, Debug)]
 pub struct Params {
     pub debug_layout: bool,
+    #[serde(default)]
+    pub dark_mode: bool,
+    /// use the light or dark appearance of the OS instead of `dark_mode`.
+    #[serde(default)]
+    pub follow_system_theme: bool,
+    #[serde(default)]
+    pub auto_save: bool,
+    #[serde(default = "default_auto_save_secs")]
+    pub auto_save_secs: u64,
+    #[serde(default = "default_font_family")]
+    pub font_family: String,
+    #[serde(default = "default_font_size")]
+    pub font_size: f64,
+    #[serde(default = "default_word_wrap")]
+    pub word_wrap: bool,
+    /// reveal every opened file in the project tree.
+    #[serde(default)]
+    pub auto_reveal: bool,
+    /// the output panel under the Run button is open.
+    #[serde(default = "default_show_output")]
+    pub show_output: bool,
+    /// show an overview of the text next to the editor.
+    #[serde(default)]
+    pub minimap: bool,
+    /// show the headings of the open file next to the editor.
+    #[serde(default)]
+    pub show_outline: bool,
+    /// only the editor is shown, centered, for writing without distraction.
+    #[serde(default)]
+    pub zen_mode: bool,
+    /// keep the caret line in the middle of the editor.
+    #[serde(default)]
+    pub typewriter_scrolling: bool,
+    /// glide to where the wheel or a jump scrolls to instead of landing there.
+    #[serde(default)]
+    pub smooth_scrolling: bool,
+    /// the widest the text column of the editor gets, centered in a wider
+    /// editor. 0 for no limit.
+    #[serde(default)]
+    pub max_content_width: f64,
+    /// draw spaces and tabs as dots and arrows.
+    #[serde(default)]
+    pub show_whitespace: bool,
+    /// indentation for files that don't show one of their own.
+    #[serde(default)]
+    pub indentation: Indentation,
+    /// strip the spaces and tabs ending each line when saving.
+    #[serde(default)]
+    pub trim_trailing_whitespace: bool,
+    /// end the file in a single line break when saving.
+    #[serde(default)]
+    pub ensure_final_newline: bool,
+    /// the strftime format of Insert Date/Time.
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    /// text templates by name, `$DATE`, `$TIME` and `$FILENAME` are filled in.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub snippets: Arc<BTreeMap<String, String>>,
+    /// files bigger than this many megabytes open in large file mode, 0
+    /// for never.
+    #[serde(default = "default_large_file_mb")]
+    pub large_file_mb: u64,
+}
+
+fn default_auto_save_secs() -> u64 {
+    30
+}
+
+fn default_large_file_mb() -> u64 {
+    5
+}
+
+fn default_font_family() -> String {
+    "Microsoft Yahei".to_string()
+}
+
+fn default_font_size() -> f64 {
+    15.0
+}
+
+fn default_word_wrap() -> bool {
+    true
+}
+
+fn default_show_output() -> bool {
+    true
+}
+
+fn default_date_format() -> String {
+    snippet::DEFAULT_DATE_TIME_FORMAT.to_string()
 }
 
 impl Default for Params {
     fn default() -> Self {
         Self {
             debug_layout: false,
+            dark_mode: false,
+            follow_system_theme: false,
+            auto_save: false,
+            auto_save_secs: default_auto_save_secs(),
+            font_family: default_font_family(),
+            font_size: default_font_size(),
+            word_wrap: default_word_wrap(),
+            auto_reveal: false,
+            show_output: default_show_output(),
+            minimap: false,
+            show_outline: false,
+            zen_mode: false,
+            typewriter_scrolling: false,
+            smooth_scrolling: false,
+            max_content_width: 0.0,
+            show_whitespace: false,
+            indentation: Default::default(),
+            trim_trailing_whitespace: false,
+            ensure_final_newline: false,
+            date_format: default_date_format(),
+            snippets: Default::default(),
+            large_file_mb: default_large_file_mb(),
         }
     }
 }
+
+/// per-project settings layered over the global `Params`, unset fields
+/// fall back to the global ones.
+#[derive(Serialize, Deserialize, Clone, Data, Lens, Debug, Default)]
+pub struct ProjectConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dark_mode: Option<bool>,
+
+    /// the last open file, relative to the project dir.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_file: Option<Arc<Path>>,
+
+    /// languages picked by the user, by file path relative to the project dir.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub languages: Arc<BTreeMap<String, String>>,
+
+    /// the shell command the Run button runs in the project dir.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_command: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub indentation: Option<Indentation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trim_trailing_whitespace: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ensure_final_newline: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub word_wrap: Option<bool>,
+}
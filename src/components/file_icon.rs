@@ -0,0 +1,31 @@
+//! Glyphs used as icons for the nodes of the project tree.
+
+pub const FOLDER_CLOSED: &str = "📁";
+pub const FOLDER_OPEN: &str = "📂";
+pub const FILE: &str = "📄";
+
+pub fn folder_icon(expanded: bool) -> &'static str {
+    if expanded {
+        FOLDER_OPEN
+    } else {
+        FOLDER_CLOSED
+    }
+}
+
+/// Pick an icon by file extension, falling back to a generic file icon.
+pub fn file_icon(ext: &str) -> &'static str {
+    match ext.to_lowercase().as_str() {
+        "rs" => "🦀",
+        "md" | "markdown" | "txt" => "📝",
+        "js" | "ts" | "jsx" | "tsx" | "py" | "rb" | "go" | "java" | "kt" | "c" | "h" | "cpp" => {
+            "📜"
+        }
+        "json" | "toml" | "yaml" | "yml" | "xml" | "ini" | "lock" => "⚙",
+        "html" | "htm" | "css" | "scss" => "🌐",
+        "png" | "jpg" | "jpeg" | "gif" | "svg" | "bmp" | "ico" => "🖼",
+        "sh" | "bash" | "zsh" | "bat" | "ps1" => "💲",
+        "zip" | "tar" | "gz" | "rar" | "7z" => "📦",
+        "ftl" => "🌍",
+        _ => FILE,
+    }
+}
@@ -18,16 +18,21 @@ use crate::theme;
 use druid::widget::prelude::*;
 use druid::widget::{Click, ControllerHost, Label, LabelText};
 use druid::{Affine, Data, Insets};
+use std::rc::Rc;
 
 // the minimum padding added to a button.
 // NOTE: these values are chosen to match the existing look of TextBox; these
 // should be reevaluated at some point.
 const LABEL_INSETS: Insets = Insets::uniform_xy(8., 2.);
 
+type DisabledPredicate<T> = Rc<dyn Fn(&T, &Env) -> bool>;
+
 /// A button with a text label.
 pub struct IconButton<T> {
     label: Label<T>,
     label_size: Size,
+    /// when set and true for the data, the button is dimmed and ignores clicks.
+    disabled_if: Option<DisabledPredicate<T>>,
 }
 
 impl<T: Data> IconButton<T> {
@@ -73,6 +78,7 @@ impl<T: Data> IconButton<T> {
         IconButton {
             label,
             label_size: Size::ZERO,
+            disabled_if: None,
         }
     }
 
@@ -101,17 +107,42 @@ impl<T: Data> IconButton<T> {
         IconButton::new(text)
     }
 
+    /// Builder-style method to disable the button while `disabled_if`
+    /// returns true for the data. Call it before [`.on_click`].
+    ///
+    /// [`.on_click`]: #method.on_click
+    pub fn disabled_if(mut self, disabled_if: impl Fn(&T, &Env) -> bool + 'static) -> Self {
+        self.disabled_if = Some(Rc::new(disabled_if));
+        self
+    }
+
     /// Provide a closure to be called when this button is clicked.
     pub fn on_click(
         self,
         f: impl Fn(&mut EventCtx, &mut T, &Env) + 'static,
     ) -> ControllerHost<Self, Click<T>> {
-        ControllerHost::new(self, Click::new(f))
+        let disabled_if = self.disabled_if.clone();
+        ControllerHost::new(
+            self,
+            Click::new(move |ctx, data, env| {
+                let disabled = disabled_if.as_ref().map_or(false, |f| f(data, env));
+                if !disabled {
+                    f(ctx, data, env);
+                }
+            }),
+        )
+    }
+
+    fn is_disabled(&self, data: &T, env: &Env) -> bool {
+        self.disabled_if.as_ref().map_or(false, |f| f(data, env))
     }
 }
 
 impl<T: Data> Widget<T> for IconButton<T> {
-    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut T, _env: &Env) {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        if self.is_disabled(data, env) {
+            return;
+        }
         match event {
             Event::MouseDown(_) => {
                 ctx.set_active(true);
@@ -135,6 +166,9 @@ impl<T: Data> Widget<T> for IconButton<T> {
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if self.disabled_if.is_some() && !old_data.same(data) {
+            ctx.request_paint();
+        }
         self.label.update(ctx, old_data, data, env)
     }
 
@@ -163,9 +197,11 @@ impl<T: Data> Widget<T> for IconButton<T> {
 
         let rounded_rect = size.to_rect().inset(-stroke_width / 2.0);
 
-        if is_hot || is_active {
-            let bg = env.get(theme::BUTTON_DARK);
-            ctx.fill(rounded_rect, &bg);
+        let disabled = self.is_disabled(data, env);
+        if !disabled && is_active {
+            ctx.fill(rounded_rect, &env.get(theme::BUTTON_DARK));
+        } else if !disabled && is_hot {
+            ctx.fill(rounded_rect, &env.get(theme::BUTTON_LIGHT));
         }
 
         let label_offset = (size.to_vec2() - self.label_size.to_vec2()) / 2.0;
@@ -174,5 +210,11 @@ impl<T: Data> Widget<T> for IconButton<T> {
             ctx.transform(Affine::translate(label_offset));
             self.label.paint(ctx, data, env);
         });
+
+        // dim the label by washing it out with the background
+        if disabled {
+            let veil = env.get(theme::TOOL_WINDOW_COLOR).with_alpha(0.6);
+            ctx.fill(size.to_rect(), &veil);
+        }
     }
 }
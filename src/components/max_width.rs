@@ -0,0 +1,64 @@
+use druid::widget::prelude::*;
+use druid::{Point, WidgetPod};
+
+type WidthFn<T> = Box<dyn Fn(&T) -> Option<f64>>;
+
+/// Centers its child in the width it gets, no wider than what `width`
+/// returns for the data. `None` lets the child take the whole width.
+pub struct MaxWidth<T> {
+    inner: WidgetPod<T, Box<dyn Widget<T>>>,
+    width: WidthFn<T>,
+}
+
+impl<T: Data> MaxWidth<T> {
+    pub fn new(
+        inner: impl Widget<T> + 'static,
+        width: impl Fn(&T) -> Option<f64> + 'static,
+    ) -> MaxWidth<T> {
+        MaxWidth {
+            inner: WidgetPod::new(Box::new(inner)),
+            width: Box::new(width),
+        }
+    }
+}
+
+impl<T: Data> Widget<T> for MaxWidth<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        self.inner.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        self.inner.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, env: &Env) {
+        if (self.width)(old_data) != (self.width)(data) {
+            ctx.request_layout();
+        }
+        self.inner.update(ctx, data, env)
+    }
+
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, env: &Env) -> Size {
+        let child_bc = match (self.width)(data) {
+            Some(width) if width < bc.max().width => BoxConstraints::new(
+                Size::new(bc.min().width.min(width), bc.min().height),
+                Size::new(width, bc.max().height),
+            ),
+            _ => *bc,
+        };
+        let child = self.inner.layout(ctx, &child_bc, data, env);
+        let size = if bc.max().width.is_finite() {
+            Size::new(bc.max().width, child.height)
+        } else {
+            child
+        };
+        let size = bc.constrain(size);
+        let x = ((size.width - child.width) / 2.0).max(0.0);
+        self.inner.set_origin(ctx, data, env, Point::new(x, 0.0));
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        self.inner.paint(ctx, data, env)
+    }
+}
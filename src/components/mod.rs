@@ -1,3 +1,5 @@
+pub mod file_icon;
 pub mod icon_button;
+pub mod max_width;
 pub mod modal_host;
 pub mod tree;
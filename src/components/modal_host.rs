@@ -2,7 +2,7 @@
 
 use druid::kurbo::Vec2;
 use druid::widget::prelude::*;
-use druid::{Color, Command, Data, Rect, Selector, SingleUse, WidgetExt, WidgetPod};
+use druid::{Color, Command, Data, KbKey, Rect, Selector, SingleUse, WidgetExt, WidgetPod};
 
 /// A wrapper around a closure for constructing a widget.
 pub struct ModalBuilder<T>(Box<dyn FnOnce() -> Box<dyn Widget<T>>>);
@@ -20,9 +20,14 @@ impl<T: Data> ModalBuilder<T> {
 
 /// A widget that has a child, and can optionally show a modal dialog
 /// that obscures the child.
+///
+/// The modal is centered over a dimmed backdrop. Escape dismisses it unless
+/// the modal handles the key itself, and so does a click on the backdrop
+/// unless that is turned off.
 pub struct ModalHost<T> {
     child: WidgetPod<T, Box<dyn Widget<T>>>,
     modal: Option<WidgetPod<T, Box<dyn Widget<T>>>>,
+    dismiss_on_click_outside: bool,
 }
 
 // this impl block has () type so that you can use this const without knowing `T`.
@@ -49,8 +54,23 @@ impl<T: Data> ModalHost<T> {
         ModalHost {
             child: WidgetPod::new(widget.boxed()),
             modal: None,
+            dismiss_on_click_outside: true,
         }
     }
+
+    /// Builder-style method to set whether a click on the backdrop
+    /// dismisses the modal.
+    pub fn with_dismiss_on_click_outside(mut self, dismiss: bool) -> Self {
+        self.dismiss_on_click_outside = dismiss;
+        self
+    }
+
+    fn dismiss(&mut self, ctx: &mut EventCtx) {
+        if self.modal.take().is_some() {
+            ctx.children_changed();
+        }
+        ctx.set_handled();
+    }
 }
 
 impl<T: Data> Widget<T> for ModalHost<T> {
@@ -66,16 +86,28 @@ impl<T: Data> Widget<T> for ModalHost<T> {
                     }
                     ctx.set_handled();
                 } else if cmd.is(ModalHost::DISMISS_MODAL) {
-                    if self.modal.is_some() {
-                        self.modal = None;
-                        ctx.children_changed();
-                    } else {
+                    if self.modal.is_none() {
                         log::warn!("cannot dismiss modal; no modal shown");
                     }
-                    ctx.set_handled();
+                    self.dismiss(ctx);
                 }
             }
 
+            Event::MouseDown(mouse) if self.dismiss_on_click_outside => match self.modal.as_mut() {
+                Some(modal) if !modal.layout_rect().contains(mouse.pos) => self.dismiss(ctx),
+                Some(modal) => modal.event(ctx, event, data, env),
+                None => self.child.event(ctx, event, data, env),
+            },
+            Event::KeyDown(key) if key.key == KbKey::Escape => match self.modal.as_mut() {
+                Some(modal) => {
+                    modal.event(ctx, event, data, env);
+                    if !ctx.is_handled() {
+                        self.dismiss(ctx);
+                    }
+                }
+                None => self.child.event(ctx, event, data, env),
+            },
+
             // user input only gets delivered to modal, if modal is present
             e if is_user_input(e) => match self.modal.as_mut() {
                 Some(modal) => modal.event(ctx, event, data, env),
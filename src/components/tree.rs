@@ -23,8 +23,8 @@ use druid::piet::{LineCap, LineJoin, RenderContext, StrokeStyle};
 use druid::theme;
 use druid::widget::Label;
 use druid::{
-    BoxConstraints, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx,
-    Point, UpdateCtx, Widget, WidgetPod,
+    BoxConstraints, Data, Env, Event, EventCtx, KbKey, KeyEvent, LayoutCtx, LifeCycle,
+    LifeCycleCtx, PaintCtx, Point, Rect, UpdateCtx, Widget, WidgetPod,
 };
 
 /// A tree widget for a collection of items organized in a hierachical way.
@@ -34,8 +34,31 @@ where
 {
     /// The root node of this tree
     root_node: TreeNodeWidget<T>,
+
+    /// The selected node, as the path of child indices from the root
+    selected: Option<Vec<usize>>,
+
+    /// Called when Enter is pressed on the selected node
+    on_activate: Option<ActivateCallback<T>>,
+
+    /// Called with the dragged node and the node it was dropped on
+    on_drop: Option<DropCallback<T>>,
+
+    /// The node the mouse went down on, and where
+    pressed: Option<(Vec<usize>, Point)>,
+
+    /// The node a drag would drop on, while dragging
+    drop_target: Option<Vec<usize>>,
+
+    dragging: bool,
 }
 
+type ActivateCallback<T> = Box<dyn Fn(&mut EventCtx, &mut T, &Env)>;
+type DropCallback<T> = Box<dyn Fn(&mut EventCtx, &T, &T, &Env)>;
+
+/// how far the mouse moves before a press turns into a drag.
+const DRAG_THRESHOLD: f64 = 4.0;
+
 /// A tree node, with methods providing its own label and its children.
 /// This is the data expected by the tree widget.
 pub trait TreeNode {
@@ -51,6 +74,20 @@ pub trait TreeNode {
     fn get_child_mut(&mut self, index: usize) -> &mut Self
     where
         Self: Sized;
+
+    /// Called when the node is expanded or collapsed by the user, so that the
+    /// data can keep track of it.
+    fn set_expanded(&mut self, _expanded: bool) {}
+
+    /// Whether the node should start expanded when its widget is created.
+    fn is_expanded(&self) -> bool {
+        false
+    }
+
+    /// Whether other nodes can be dropped on this one, see `Tree::with_on_drop`.
+    fn accepts_drop(&self) -> bool {
+        false
+    }
 }
 
 /// Wedge is an arbitrary name for the arrow-like icon marking whether a node is expanded or collapsed.
@@ -166,13 +203,17 @@ impl<T: TreeNode + Data + Default> TreeNodeWidget<T> {
 
     /// Create a TreeNodeWidget from a TreeNode.
     fn from_node(node: &T, make_widget: WidgetFactoryCallback<T>) -> Self {
-        TreeNodeWidget {
+        let mut widget = TreeNodeWidget {
             wedge: WidgetPod::new(Wedge::new()),
             widget: WidgetPod::new(Box::new((make_widget)(node))),
             expanded: false,
             children: BTreeMap::new(),
             make_widget,
+        };
+        if node.is_expanded() {
+            widget.expand(node, true);
         }
+        widget
     }
 
     /// Expand or collapse the node.
@@ -199,6 +240,67 @@ impl<T: TreeNode + Data + Default> TreeNodeWidget<T> {
     fn make_widget(&mut self, data: &T) {
         self.widget = WidgetPod::new((self.make_widget)(data));
     }
+
+    /// Expand or collapse the node on behalf of the user, keeping the data
+    /// and the node widget in sync. The caller must call `children_changed`.
+    fn set_expanded_by_user(&mut self, data: &mut T, expanded: bool) {
+        self.expanded = expanded;
+        self.expand(data, expanded);
+        data.set_expanded(expanded);
+        // The node widget may depend on the expanded state (e.g. an
+        // open folder icon), so it is rebuilt along with new children.
+        self.make_widget(data);
+    }
+
+    /// Collect the paths of this node and its visible descendants, in the
+    /// order they are displayed.
+    fn visible_nodes(&self, data: &T, path: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+        out.push(path.clone());
+        if self.expanded {
+            for (index, child_widget_node) in self.children.iter() {
+                if *index >= data.children_count() {
+                    continue;
+                }
+                path.push(*index);
+                child_widget_node
+                    .widget()
+                    .visible_nodes(data.get_child(*index), path, out);
+                path.pop();
+            }
+        }
+    }
+
+    /// The node widget at the given path below this node
+    fn node_mut(&mut self, path: &[usize]) -> Option<&mut TreeNodeWidget<T>> {
+        match path.split_first() {
+            None => Some(self),
+            Some((index, rest)) => self.children.get_mut(index)?.widget_mut().node_mut(rest),
+        }
+    }
+}
+
+/// The data node at the given path below `data`
+fn data_at<'a, T: TreeNode>(data: &'a T, path: &[usize]) -> Option<&'a T> {
+    let mut node = data;
+    for index in path {
+        if *index >= node.children_count() {
+            return None;
+        }
+        node = node.get_child(*index);
+    }
+    Some(node)
+}
+
+/// The data node at the given path below `data`
+fn data_at_mut<'a, T: TreeNode>(data: &'a mut T, path: &[usize]) -> Option<&'a mut T> {
+    let mut node = data;
+    for index in path {
+        if *index >= node.children_count() {
+            return None;
+        }
+        node = node.get_child_mut(*index);
+    }
+    Some(node)
 }
 
 impl<T: TreeNode + Data + Default> Widget<T> for TreeNodeWidget<T> {
@@ -221,11 +323,8 @@ impl<T: TreeNode + Data + Default> Widget<T> for TreeNodeWidget<T> {
                 // The wedge widget has decided to change the expanded/collapsed state of the node,
                 // handle it by expanding/collapsing children nodes as required.
                 ctx.request_layout();
-                self.expanded = wegde_expanded;
-                if self.expand(data, wegde_expanded) {
-                    // New children were created, inform the context.
-                    ctx.children_changed();
-                }
+                self.set_expanded_by_user(data, wegde_expanded);
+                ctx.children_changed();
             }
         }
     }
@@ -333,8 +432,194 @@ impl<T: TreeNode + Data + Default> Tree<T> {
             Arc::new(Box::new(move |n: &T| Box::new(make_widget(n))));
         Tree {
             root_node: TreeNodeWidget::default(boxed_closure),
+            selected: None,
+            on_activate: None,
+            on_drop: None,
+            pressed: None,
+            drop_target: None,
+            dragging: false,
         }
     }
+
+    /// Builder-style method to let nodes be dragged onto the nodes that
+    /// `accepts_drop`. The closure gets the dragged node and the one it was
+    /// dropped on; a node can't be dropped on itself, its parent or below it.
+    pub fn with_on_drop(mut self, f: impl Fn(&mut EventCtx, &T, &T, &Env) + 'static) -> Self {
+        self.on_drop = Some(Box::new(f));
+        self
+    }
+
+    /// Builder-style method to provide a closure called when Enter is pressed
+    /// on the selected node.
+    pub fn with_on_activate(mut self, f: impl Fn(&mut EventCtx, &mut T, &Env) + 'static) -> Self {
+        self.on_activate = Some(Box::new(f));
+        self
+    }
+
+    /// Builder-style method to set the selected node, as the path of child
+    /// indices from the root.
+    pub fn with_selected(mut self, selected: Option<Vec<usize>>) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    /// The selected node, as the path of child indices from the root.
+    pub fn selected(&self) -> Option<&Vec<usize>> {
+        self.selected.as_ref()
+    }
+
+    fn visible_nodes(&self, data: &T) -> Vec<Vec<usize>> {
+        let mut nodes = Vec::new();
+        self.root_node
+            .visible_nodes(data, &mut Vec::new(), &mut nodes);
+        nodes
+    }
+
+    /// the node in the row at `pos`, every node takes one row.
+    fn node_at(&self, data: &T, pos: Point, env: &Env) -> Option<Vec<usize>> {
+        let row_height = env.get(theme::BASIC_WIDGET_HEIGHT);
+        let row = (pos.y / row_height).floor().max(0.0) as usize;
+        self.visible_nodes(data).get(row).cloned()
+    }
+
+    /// Where the dragged node at `from` would drop with the mouse at `pos`:
+    /// the node under the mouse if it takes drops, or else its parent.
+    fn drop_target_at(
+        &self,
+        data: &T,
+        from: &[usize],
+        pos: Point,
+        env: &Env,
+    ) -> Option<Vec<usize>> {
+        let mut target = self.node_at(data, pos, env)?;
+        while !data_at(data, &target)?.accepts_drop() {
+            target.pop()?;
+        }
+
+        let is_parent = from.len() == target.len() + 1 && from.starts_with(&target);
+        if target.starts_with(from) || is_parent {
+            return None;
+        }
+        Some(target)
+    }
+
+    /// Follow the mouse while a node is pressed, returns whether it is dragged.
+    fn handle_drag(&mut self, ctx: &mut EventCtx, event: &Event, data: &T, env: &Env) -> bool {
+        if self.on_drop.is_none() {
+            return false;
+        }
+
+        match event {
+            Event::MouseMove(mouse) => {
+                // the button went up outside of the tree
+                if !mouse.buttons.has_left() {
+                    self.pressed = None;
+                    self.dragging = false;
+                    if self.drop_target.take().is_some() {
+                        ctx.request_paint();
+                    }
+                    return false;
+                }
+                let from = match &self.pressed {
+                    Some((from, start))
+                        if self.dragging || (mouse.pos - *start).hypot() > DRAG_THRESHOLD =>
+                    {
+                        from.clone()
+                    }
+                    _ => return false,
+                };
+                self.dragging = true;
+                let target = self.drop_target_at(data, &from, mouse.pos, env);
+                if target != self.drop_target {
+                    self.drop_target = target;
+                    ctx.request_paint();
+                }
+                true
+            }
+            Event::MouseUp(_) => {
+                let pressed = self.pressed.take();
+                let target = self.drop_target.take();
+                if !std::mem::replace(&mut self.dragging, false) {
+                    return false;
+                }
+                ctx.request_paint();
+
+                if let (Some((from, _)), Some(target), Some(on_drop)) =
+                    (pressed, target, &self.on_drop)
+                {
+                    if let (Some(dragged), Some(target)) =
+                        (data_at(data, &from), data_at(data, &target))
+                    {
+                        on_drop(ctx, dragged, target, env);
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Handle the navigation keys, returns whether the key was used.
+    fn handle_key(&mut self, ctx: &mut EventCtx, key: &KeyEvent, data: &mut T, env: &Env) -> bool {
+        let nodes = self.visible_nodes(data);
+        let current = self
+            .selected
+            .as_ref()
+            .and_then(|selected| nodes.iter().position(|path| path == selected));
+
+        match &key.key {
+            KbKey::ArrowDown => {
+                let next = current.map(|index| index + 1).unwrap_or(0);
+                if let Some(path) = nodes.get(next) {
+                    self.selected = Some(path.clone());
+                }
+            }
+            KbKey::ArrowUp => {
+                let prev = current.map(|index| index.saturating_sub(1)).unwrap_or(0);
+                if let Some(path) = nodes.get(prev) {
+                    self.selected = Some(path.clone());
+                }
+            }
+            KbKey::ArrowRight | KbKey::ArrowLeft => {
+                let mut path = match &self.selected {
+                    Some(path) => path.clone(),
+                    None => return false,
+                };
+                let (node, node_data) =
+                    match (self.root_node.node_mut(&path), data_at_mut(data, &path)) {
+                        (Some(node), Some(node_data)) => (node, node_data),
+                        _ => return false,
+                    };
+
+                let has_children = node_data.children_count() > 0;
+                if key.key == KbKey::ArrowRight {
+                    if has_children && !node.expanded {
+                        node.set_expanded_by_user(node_data, true);
+                        ctx.children_changed();
+                    } else if has_children {
+                        path.push(0);
+                        self.selected = Some(path);
+                    }
+                } else if has_children && node.expanded {
+                    node.set_expanded_by_user(node_data, false);
+                    ctx.children_changed();
+                } else if path.pop().is_some() {
+                    self.selected = Some(path);
+                }
+            }
+            KbKey::Enter => {
+                if let (Some(path), Some(on_activate)) = (&self.selected, &self.on_activate) {
+                    if let Some(node_data) = data_at_mut(data, path) {
+                        on_activate(ctx, node_data, env);
+                    }
+                }
+            }
+            _ => return false,
+        }
+
+        ctx.request_paint();
+        true
+    }
 }
 
 /// Default tree implementation, supplying Label if the nodes implement the Display trait
@@ -345,6 +630,12 @@ impl<T: TreeNode + Data + Default + Display> Default for Tree<T> {
         }));
         Tree {
             root_node: TreeNodeWidget::default(boxed_closure),
+            selected: None,
+            on_activate: None,
+            on_drop: None,
+            pressed: None,
+            drop_target: None,
+            dragging: false,
         }
     }
 }
@@ -352,10 +643,37 @@ impl<T: TreeNode + Data + Default + Display> Default for Tree<T> {
 // Implement the Widget trait for Tree
 impl<T: TreeNode + Data + Default> Widget<T> for Tree<T> {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut T, env: &Env) {
+        match event {
+            Event::MouseDown(mouse) => {
+                // every node takes one row, so the row under the mouse
+                // tells which node gets selected
+                ctx.request_focus();
+                self.selected = self.node_at(data, mouse.pos, env);
+                self.pressed = self.selected.clone().map(|path| (path, mouse.pos));
+                ctx.request_paint();
+            }
+            Event::MouseMove(_) | Event::MouseUp(_) => {
+                // the node widgets still see the event, so their pressed
+                // state is cleared, but a click on them won't fire away
+                // from where it started
+                self.handle_drag(ctx, event, data, env);
+            }
+            Event::KeyDown(key) if ctx.has_focus() => {
+                if self.handle_key(ctx, key, data, env) {
+                    ctx.request_layout();
+                    ctx.set_handled();
+                    return;
+                }
+            }
+            _ => {}
+        }
         self.root_node.event(ctx, event, data, env);
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &T, env: &Env) {
+        if let LifeCycle::BuildFocusChain = event {
+            ctx.register_for_focus();
+        }
         if let LifeCycle::WidgetAdded = event {
             self.root_node.make_widget(data);
             // Always expand the first level
@@ -378,6 +696,41 @@ impl<T: TreeNode + Data + Default> Widget<T> for Tree<T> {
         let background_color = env.get(crate::theme::SIDEBAR_BACKGROUND);
         let clip_rect = ctx.size().to_rect();
         ctx.fill(clip_rect, &background_color);
+
+        if let Some(selected) = &self.selected {
+            if let Some(row) = self
+                .visible_nodes(data)
+                .iter()
+                .position(|path| path == selected)
+            {
+                let row_height = env.get(theme::BASIC_WIDGET_HEIGHT);
+                let rect = Rect::new(
+                    0.0,
+                    row as f64 * row_height,
+                    clip_rect.width(),
+                    (row + 1) as f64 * row_height,
+                );
+                ctx.fill(rect, &env.get(crate::theme::TREE_SELECTED_COLOR));
+            }
+        }
+
+        if let Some(target) = &self.drop_target {
+            if let Some(row) = self
+                .visible_nodes(data)
+                .iter()
+                .position(|path| path == target)
+            {
+                let row_height = env.get(theme::BASIC_WIDGET_HEIGHT);
+                let rect = Rect::new(
+                    0.5,
+                    row as f64 * row_height + 0.5,
+                    clip_rect.width() - 0.5,
+                    (row + 1) as f64 * row_height - 0.5,
+                );
+                ctx.stroke(rect, &env.get(crate::theme::FOREGROUND_DARK), 1.0);
+            }
+        }
+
         self.root_node.paint(ctx, data, env);
     }
 }
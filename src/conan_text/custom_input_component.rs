@@ -18,17 +18,20 @@ use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::ops::Range;
 use std::sync::{Arc, Weak};
 
-use druid::{Cursor, Env, Modifiers, Selector, text, TextAlignment, theme, UpdateCtx};
-use druid::kurbo::{Line, Point, Rect, Vec2};
+use druid::kurbo::{BezPath, Circle, Insets, Line, Point, Rect, Vec2};
 use druid::piet::TextLayout as _;
 use druid::text::{
     EditableText, ImeHandlerRef, ImeInvalidation, InputHandler, Movement, Selection, TextAction,
     TextLayout, TextStorage,
 };
 use druid::widget::prelude::*;
+use druid::{text, theme, Cursor, Env, Modifiers, Selector, TextAlignment, UpdateCtx};
 use tracing::instrument;
 
-use crate::{conan_text};
+use crate::conan_text;
+use crate::support::git::{ChangeKind, LineChange};
+use crate::support::indentation::Indentation;
+use crate::support::{bracket, whitespace};
 
 /// A widget that accepts text input.
 ///
@@ -96,11 +99,30 @@ pub struct EditSession<T> {
     /// notification when the user cancels editing.
     pub send_notification_on_cancel: bool,
     selection: Selection,
+    /// the ranges of a box selection or of the carets added with Cmd+click
+    /// and Cmd+D, from top to bottom. The first one is also `selection`.
+    /// Empty with a single selection.
+    block: Vec<Range<usize>>,
+    /// where the Alt+drag making the box selection started.
+    block_origin: Option<Point>,
     accepts_newlines: bool,
     accepts_tabs: bool,
+    /// what Tab inserts, a tab or spaces up to the next stop.
+    indentation: Indentation,
+    /// the caret moves and text can be selected, but edits are thrown away.
+    read_only: bool,
+    /// draw spaces and tabs as dots and arrows.
+    show_whitespace: bool,
+    /// the lines that differ from git HEAD, marked in the left inset.
+    line_changes: Arc<Vec<LineChange>>,
     alignment: TextAlignment,
     /// The y-position of the text when it does not fill our width.
     alignment_offset: f64,
+    /// the width lines are wrapped at when we are wider, `None` for our width.
+    max_width: Option<f64>,
+    /// the space left of the text when it is narrower than we are, which
+    /// centers it.
+    margin: f64,
     /// The portion of the text that is currently marked by the IME.
     composition_range: Option<Range<usize>>,
     drag_granularity: DragGranularity,
@@ -210,6 +232,47 @@ impl CustomTextComponent<()> {
     /// An ancestor can handle this event in order to do things like request
     /// a focus change.
     pub const BACKTAB: Selector = Selector::new("druid-builtin.textbox-backtab");
+
+    /// A notification sent by the textbox when the selection (or the caret)
+    /// has moved.
+    ///
+    /// An ancestor can handle this event to show the caret position.
+    pub const SELECTION_CHANGED: Selector<Selection> =
+        Selector::new("conan.textbox-selection-changed");
+
+    /// A command that moves the selection of the textbox, and scrolls it
+    /// into view.
+    pub const SET_SELECTION: Selector<Selection> = Selector::new("conan.textbox-set-selection");
+
+    /// A notification sent by the textbox when it scrolled, with the top
+    /// and bottom of the visible part as fractions of the text height.
+    pub const VIEWPORT_CHANGED: Selector<(f64, f64)> =
+        Selector::new("conan.textbox-viewport-changed");
+
+    /// A command that scrolls the textbox to center the given fraction of
+    /// the text height.
+    pub const SCROLL_TO_FRACTION: Selector<f64> = Selector::new("conan.textbox-scroll-to-fraction");
+
+    /// A command that replaces the lines marked as changed since git HEAD.
+    pub const SET_LINE_CHANGES: Selector<Arc<Vec<LineChange>>> =
+        Selector::new("conan.textbox-set-line-changes");
+
+    /// A notification sent by the component when it refused an edit, as
+    /// the text is read-only. As a command it shows the same flash for an
+    /// edit refused outside of the textbox.
+    pub const EDIT_REFUSED: Selector = Selector::new("conan.textbox-edit-refused");
+
+    /// A command that makes the text read-only, or editable again.
+    pub const SET_READ_ONLY: Selector<bool> = Selector::new("conan.textbox-set-read-only");
+
+    /// A command that sets what the Tab key inserts.
+    pub const SET_INDENTATION: Selector<Indentation> =
+        Selector::new("conan.textbox-set-indentation");
+
+    /// A command that selects the word at the caret, or adds the next match
+    /// of the selected text as another selection.
+    pub const SELECT_NEXT_OCCURRENCE: Selector =
+        Selector::new("conan.textbox-select-next-occurrence");
 }
 
 impl<T> CustomTextComponent<T> {
@@ -263,6 +326,111 @@ impl<T: EditableText + TextStorage> CustomTextComponent<T> {
             lock: self.lock.clone(),
         }
     }
+
+    /// Mark trailing whitespace, and with `show_whitespace` every space and
+    /// tab, within the painted region. The marks are drawn over the blank
+    /// glyphs, so the text keeps its metrics.
+    fn paint_whitespace(&self, ctx: &mut PaintCtx, data: &T, env: &Env, text_offset: Vec2) {
+        let session = self.borrow();
+        let text = data.as_str();
+        let region = ctx.region().bounding_box() - text_offset;
+        let start = session
+            .layout
+            .text_position_for_point(Point::new(0.0, region.y0));
+        let end = session
+            .layout
+            .text_position_for_point(Point::new(session.layout.size().width, region.y1));
+
+        let trailing_color = env.get(crate::theme::TRAILING_WHITESPACE);
+        for range in whitespace::trailing_whitespace(text, start..end) {
+            for region in session.layout.rects_for_range(range) {
+                ctx.fill(region + text_offset, &trailing_color);
+            }
+        }
+
+        if !session.show_whitespace {
+            return;
+        }
+        let color = env.get(crate::theme::WHITESPACE);
+        for (offset, ch) in whitespace::whitespace(text, start..end) {
+            for region in session.layout.rects_for_range(offset..offset + 1) {
+                let region = region + text_offset;
+                let center = region.center();
+                if ch == '\t' {
+                    let (x0, x1) = (region.x0 + 2.0, (region.x1 - 2.0).max(region.x0 + 4.0));
+                    ctx.stroke(Line::new((x0, center.y), (x1, center.y)), &color, 1.0);
+                    ctx.stroke(
+                        Line::new((x1 - 3.0, center.y - 3.0), (x1, center.y)),
+                        &color,
+                        1.0,
+                    );
+                    ctx.stroke(
+                        Line::new((x1 - 3.0, center.y + 3.0), (x1, center.y)),
+                        &color,
+                        1.0,
+                    );
+                } else {
+                    ctx.fill(Circle::new(center, 1.0), &color);
+                }
+            }
+        }
+    }
+
+    /// Mark the lines changed since git HEAD with a bar in the inset left of
+    /// the text, and deleted lines with a wedge between their neighbours.
+    /// Changes past the end of the text are from before an edit and skipped
+    /// until the next diff comes in.
+    fn paint_line_changes(&self, ctx: &mut PaintCtx, data: &T, env: &Env) {
+        let session = self.borrow();
+        if session.line_changes.is_empty() {
+            return;
+        }
+
+        let text = data.as_str();
+        let starts: Vec<usize> = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+        // the caret position at the end of a line, before its line break
+        let line_end = |line: usize| match starts.get(line + 1) {
+            Some(next) if text[..next - 1].ends_with('\r') => next - 2,
+            Some(next) => next - 1,
+            None => text.len(),
+        };
+
+        let gutter = env.get(theme::TEXTBOX_INSETS).x0;
+        let (x0, x1) = (session.margin - gutter, session.margin - 1.0);
+        for change in session.line_changes.iter() {
+            let color = match change.kind {
+                ChangeKind::Added => env.get(crate::theme::GIT_ADDED),
+                ChangeKind::Modified => env.get(crate::theme::GIT_MODIFIED),
+                ChangeKind::Deleted => env.get(crate::theme::GIT_DELETED),
+            };
+
+            if change.lines.is_empty() {
+                let y = match starts.get(change.lines.start) {
+                    Some(start) => session.layout.cursor_line_for_text_position(*start).p0.y,
+                    None => session.layout.size().height,
+                };
+                let mut wedge = BezPath::new();
+                wedge.move_to((x0, y - 4.0));
+                wedge.line_to((x1, y));
+                wedge.line_to((x0, y + 4.0));
+                wedge.close_path();
+                ctx.fill(wedge, &color);
+                continue;
+            }
+
+            let first = match starts.get(change.lines.start) {
+                Some(start) => *start,
+                None => continue,
+            };
+            let last = line_end((change.lines.end - 1).min(starts.len() - 1));
+            let top = session.layout.cursor_line_for_text_position(first);
+            let bottom = session.layout.cursor_line_for_text_position(last);
+            let rect = Rect::new(x0, top.p0.y.min(top.p1.y), x1, bottom.p0.y.max(bottom.p1.y));
+            ctx.fill(rect, &color);
+        }
+    }
 }
 
 impl<T: TextStorage + EditableText> Widget<T> for CustomTextComponent<T> {
@@ -331,7 +499,9 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextComponent<T> {
                         TextAction::InsertNewLine { .. } => {
                             ctx.submit_notification(CustomTextComponent::RETURN)
                         }
-                        TextAction::InsertTab { .. } => ctx.submit_notification(CustomTextComponent::TAB),
+                        TextAction::InsertTab { .. } => {
+                            ctx.submit_notification(CustomTextComponent::TAB)
+                        }
                         TextAction::InsertBacktab => {
                             ctx.submit_notification(CustomTextComponent::BACKTAB)
                         }
@@ -340,12 +510,23 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextComponent<T> {
                 }
                 let text = self.borrow_mut().take_external_text_change();
                 let selection = self.borrow_mut().take_external_selection_change();
-                if let Some(text) = text {
-                    self.borrow_mut().layout.set_text(text.clone());
-                    *data = text;
-                }
-                if let Some(selection) = selection {
-                    self.borrow_mut().selection = selection;
+                if text.is_some() && self.borrow().read_only {
+                    // the edit and the caret after it are dropped, the platform
+                    // is told to start over from the text as it was
+                    let mut session = self.borrow_mut();
+                    session.block.clear();
+                    session.composition_range = None;
+                    drop(session);
+                    ctx.invalidate_text_input(ImeInvalidation::Reset);
+                    ctx.submit_notification(CustomTextComponent::EDIT_REFUSED);
+                } else {
+                    if let Some(text) = text {
+                        self.borrow_mut().layout.set_text(text.clone());
+                        *data = text;
+                    }
+                    if let Some(selection) = selection {
+                        self.borrow_mut().selection = selection;
+                    }
                 }
                 ctx.request_update();
             }
@@ -407,7 +588,18 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextComponent<T> {
             return Size::ZERO;
         }
 
-        self.borrow_mut().layout.set_wrap_width(bc.max().width);
+        // a narrower text column is centered, wrapped at its own width
+        let max = bc.max().width;
+        let wrap_width = match self.borrow().max_width {
+            Some(width) if max.is_finite() && max > width => width,
+            _ => max,
+        };
+        self.borrow_mut().margin = if max.is_finite() {
+            (max - wrap_width) / 2.0
+        } else {
+            0.0
+        };
+        self.borrow_mut().layout.set_wrap_width(wrap_width);
         self.borrow_mut().layout.rebuild_if_needed(ctx.text(), env);
         let metrics = self.borrow().layout.layout_metrics();
         let width = if bc.max().width.is_infinite() || bc.max().width < f64::MAX {
@@ -424,11 +616,14 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextComponent<T> {
         self.borrow_mut().update_alignment_offset(extra_width);
         let baseline_off = metrics.size.height - metrics.first_baseline;
         ctx.set_baseline_offset(baseline_off);
+        // the change markers are drawn into the inset on our left
+        let gutter = env.get(theme::TEXTBOX_INSETS).x0;
+        ctx.set_paint_insets(Insets::new(gutter, 0.0, 0.0, 0.0));
         size
     }
 
-    #[instrument(name = "InputComponent", level = "trace", skip(self, ctx, _data, env))]
-    fn paint(&mut self, ctx: &mut PaintCtx, _data: &T, env: &Env) {
+    #[instrument(name = "InputComponent", level = "trace", skip(self, ctx, data, env))]
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, env: &Env) {
         if !self.can_read() {
             tracing::warn!("Text paint called with IME lock held.");
         }
@@ -440,11 +635,16 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextComponent<T> {
         };
 
         let cursor_color = env.get(theme::CURSOR_COLOR);
-        let text_offset = Vec2::new(self.borrow().alignment_offset, 0.0);
+        let text_offset = self.borrow().text_offset();
 
         let selection = self.borrow().selection();
         let composition = self.borrow().composition_range();
-        let sel_rects = self.borrow().layout.rects_for_range(selection.range());
+        let sel_rects: Vec<Rect> = self
+            .borrow()
+            .selected_ranges()
+            .into_iter()
+            .flat_map(|range| self.borrow().layout.rects_for_range(range))
+            .collect();
         if let Some(composition) = composition {
             // I believe selection should always be contained in composition range while composing?
             assert!(composition.start <= selection.anchor && composition.end >= selection.active);
@@ -464,7 +664,22 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextComponent<T> {
                 let rounded = (region + text_offset).to_rounded_rect(1.0);
                 ctx.fill(rounded, &selection_color);
             }
+
+            if selection.is_caret() {
+                let text = data.as_str();
+                if let Some((bracket, other)) = bracket::matching_bracket(text, selection.active) {
+                    let match_color = env.get(crate::theme::MATCH_BRACKET);
+                    for offset in [bracket, other].iter().copied() {
+                        let range = offset..offset + 1;
+                        for region in self.borrow().layout.rects_for_range(range) {
+                            ctx.fill(region + text_offset, &match_color);
+                        }
+                    }
+                }
+            }
         }
+        self.paint_whitespace(ctx, data, env, text_offset);
+        self.paint_line_changes(ctx, data, env);
         self.borrow().layout.draw(ctx, text_offset.to_point());
     }
 }
@@ -475,6 +690,55 @@ impl<T> EditSession<T> {
         self.selection
     }
 
+    /// The selected ranges, one per line of a box selection or per caret.
+    pub fn selected_ranges(&self) -> Vec<Range<usize>> {
+        if self.block.is_empty() {
+            vec![self.selection.range()]
+        } else {
+            self.block.clone()
+        }
+    }
+
+    /// Make `ranges` the selection, as several selections when there is
+    /// more than one.
+    fn set_ranges(&mut self, mut ranges: Vec<Range<usize>>) {
+        ranges.sort_by_key(|range| range.start);
+        if let Some(first) = ranges.first() {
+            self.selection = Selection::new(first.start, first.end);
+        }
+        if ranges.len() > 1 {
+            self.block = ranges;
+        } else {
+            self.block.clear();
+        }
+    }
+
+    /// Add a caret at `pos`, or take away the selection under it while
+    /// another one is left.
+    fn toggle_caret(&mut self, pos: usize) {
+        let mut ranges = self.selected_ranges();
+        match ranges
+            .iter()
+            .position(|range| range.start <= pos && pos <= range.end)
+        {
+            Some(index) if ranges.len() > 1 => {
+                ranges.remove(index);
+            }
+            Some(_) => return,
+            None => ranges.push(pos..pos),
+        }
+        self.set_ranges(ranges);
+    }
+
+    /// Where a caret is drawn, at the end of every range of a box selection.
+    pub fn carets(&self) -> Vec<usize> {
+        if self.block.is_empty() {
+            vec![self.selection.active]
+        } else {
+            self.block.iter().map(|range| range.end).collect()
+        }
+    }
+
     /// Manually set the selection.
     ///
     /// If the new selection is different from the current selection, this
@@ -483,6 +747,7 @@ impl<T> EditSession<T> {
     /// [`EventCtx::invalidate_text_input`].
     #[must_use]
     pub fn set_selection(&mut self, selection: Selection) -> Option<ImeInvalidation> {
+        self.block.clear();
         if selection != self.selection {
             self.selection = selection;
             self.update_pending_invalidation(ImeInvalidation::SelectionChanged);
@@ -502,6 +767,47 @@ impl<T> EditSession<T> {
         self.accepts_newlines = accepts_newlines;
     }
 
+    /// Sets whether or not the tab key inserts a tab, instead of moving focus.
+    pub fn set_accepts_tabs(&mut self, accepts_tabs: bool) {
+        self.accepts_tabs = accepts_tabs;
+    }
+
+    /// Sets whether spaces and tabs are drawn as dots and arrows.
+    pub fn set_show_whitespace(&mut self, show_whitespace: bool) {
+        self.show_whitespace = show_whitespace;
+    }
+
+    /// Sets the width the text is wrapped at when there is more room, the
+    /// column is then centered.
+    pub fn set_max_width(&mut self, max_width: Option<f64>) {
+        self.max_width = max_width;
+    }
+
+    /// Where the text is drawn, relative to our origin.
+    fn text_offset(&self) -> Vec2 {
+        Vec2::new(self.alignment_offset + self.margin, 0.0)
+    }
+
+    /// Sets what the Tab key inserts.
+    pub fn set_indentation(&mut self, indentation: Indentation) {
+        self.indentation = indentation;
+    }
+
+    /// Sets whether edits are refused.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Whether edits are refused.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Sets the lines marked as changed since git HEAD.
+    pub fn set_line_changes(&mut self, line_changes: Arc<Vec<LineChange>>) {
+        self.line_changes = line_changes;
+    }
+
     /// Set the text alignment.
     ///
     /// This is only meaningful for single-line text that does not fill
@@ -566,6 +872,22 @@ impl<T: TextStorage + EditableText> EditSession<T> {
     /// text state, by calling [`EventCtx::invalidate_text_input`].
     #[must_use]
     pub fn insert_text(&mut self, data: &mut T, new_text: &str) -> ImeInvalidation {
+        if !self.block.is_empty() {
+            // a paste with a line per range of the box fills it line by line
+            let lines: Vec<&str> = new_text.lines().collect();
+            let by_line = lines.len() > 1 && lines.len() == self.block.len();
+            self.edit_block(data, |_, index, range| {
+                (
+                    range,
+                    if by_line { lines[index] } else { new_text }.to_string(),
+                )
+            });
+            self.layout.set_text(data.clone());
+            if let Some(selection) = self.external_selection_change.take() {
+                self.selection = selection;
+            }
+            return ImeInvalidation::Reset;
+        }
         let new_cursor_pos = self.selection.min() + new_text.len();
         data.edit(self.selection.range(), new_text);
         self.selection = Selection::caret(new_cursor_pos);
@@ -573,11 +895,68 @@ impl<T: TextStorage + EditableText> EditSession<T> {
         ImeInvalidation::Reset
     }
 
+    /// Select the word at a caret, or add the next match of the selected
+    /// text after the last selection, wrapping around at the end of the
+    /// text. Returns whether the selection changed.
+    pub fn select_next_occurrence(&mut self, buffer: &T) -> bool {
+        let text = buffer.as_str();
+        let ranges = self.selected_ranges();
+        if ranges.len() == 1 && ranges[0].is_empty() {
+            let word = conan_text::movement::word_range_for_pos(text, self.selection.active);
+            if word.is_empty() {
+                return false;
+            }
+            self.set_ranges(vec![word]);
+            return true;
+        }
+
+        let needle = match text.get(ranges[0].clone()) {
+            Some(needle) if !needle.is_empty() => needle,
+            _ => return false,
+        };
+        let after = ranges.iter().map(|range| range.end).max().unwrap_or(0);
+        let next = text[after..]
+            .match_indices(needle)
+            .map(|(index, _)| after + index)
+            .chain(text.match_indices(needle).map(|(index, _)| index))
+            .map(|start| start..start + needle.len())
+            .find(|found| {
+                !ranges
+                    .iter()
+                    .any(|range| range.start < found.end && found.start < range.end)
+            });
+
+        match next {
+            Some(next) => {
+                let mut ranges = ranges;
+                ranges.push(next);
+                self.set_ranges(ranges);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Sets the clipboard to the contents of the current selection.
     ///
     /// Returns `true` if the clipboard was set, and `false` if not (indicating)
     /// that the selection was empty.)
     pub fn set_clipboard(&self) -> bool {
+        if !self.block.is_empty() {
+            let text = match self.layout.text() {
+                Some(text) if self.block.iter().any(|range| !range.is_empty()) => text,
+                _ => return false,
+            };
+            let lines: Vec<String> = self
+                .block
+                .iter()
+                .filter_map(|range| text.slice(range.clone()).map(|line| line.into_owned()))
+                .collect();
+            druid::Application::global()
+                .clipboard()
+                .put_string(lines.join("\n"));
+            return true;
+        }
         if let Some(text) = self
             .layout
             .text()
@@ -596,6 +975,13 @@ impl<T: TextStorage + EditableText> EditSession<T> {
     }
 
     fn do_action(&mut self, buffer: &mut T, action: TextAction) {
+        if !self.block.is_empty() {
+            if self.do_block_action(buffer, &action) {
+                return;
+            }
+            // anything else leaves the box selection
+            self.block.clear();
+        }
         match action {
             TextAction::Move(movement) => {
                 let sel = text::movement(movement, self.selection, &self.layout, false);
@@ -613,8 +999,10 @@ impl<T: TextStorage + EditableText> EditSession<T> {
             }
             TextAction::SelectWord => {
                 if self.selection.is_caret() {
-                    let range =
-                        conan_text::movement::word_range_for_pos(buffer.as_str(), self.selection.active);
+                    let range = conan_text::movement::word_range_for_pos(
+                        buffer.as_str(),
+                        self.selection.active,
+                    );
                     self.external_selection_change = Some(Selection::new(range.start, range.end));
                 }
 
@@ -663,7 +1051,8 @@ impl<T: TextStorage + EditableText> EditSession<T> {
             }
             TextAction::InsertTab { ignore_hotkey } => {
                 if ignore_hotkey || self.accepts_tabs {
-                    self.ime_insert_text(buffer, "\t");
+                    let tab = tab_text(self.indentation, buffer, self.selection.min());
+                    self.ime_insert_text(buffer, &tab);
                 } else if !ignore_hotkey {
                     self.external_action = Some(action);
                 }
@@ -682,6 +1071,122 @@ impl<T: TextStorage + EditableText> EditSession<T> {
         }
     }
 
+    /// The edits that apply to every line of a box selection, returns
+    /// whether `action` was one of them.
+    fn do_block_action(&mut self, buffer: &mut T, action: &TextAction) -> bool {
+        match action {
+            TextAction::Delete(Movement::Grapheme(druid_shell::text::Direction::Upstream))
+            | TextAction::DecomposingBackspace => {
+                // an empty range takes the grapheme before it, but not the line break
+                self.edit_block(buffer, |text, _, range| {
+                    let line_start = text.preceding_line_break(range.start);
+                    if !range.is_empty() || range.start == line_start {
+                        return (range, "".to_string());
+                    }
+                    let start = text.prev_grapheme_offset(range.start).unwrap_or(line_start);
+                    (start.max(line_start)..range.end, "".to_string())
+                });
+            }
+            TextAction::Delete(Movement::Grapheme(druid_shell::text::Direction::Downstream)) => {
+                self.edit_block(buffer, |text, _, range| {
+                    let at_line_end = text.slice(range.end..text.len()).map_or(true, |rest| {
+                        rest.is_empty() || rest.starts_with(&['\n', '\r'][..])
+                    });
+                    if !range.is_empty() || at_line_end {
+                        return (range, "".to_string());
+                    }
+                    let end = text.next_grapheme_offset(range.end).unwrap_or(range.end);
+                    (range.start..end, "".to_string())
+                });
+            }
+            TextAction::InsertTab { .. } if self.accepts_tabs => {
+                let indentation = self.indentation;
+                self.edit_block(buffer, |text, _, range| {
+                    let tab = tab_text(indentation, text, range.start);
+                    (range, tab)
+                });
+            }
+            TextAction::Cancel => {
+                self.block.clear();
+                self.external_selection_change = Some(Selection::caret(self.selection.active));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Replace every range of the box selection with what `edit` returns
+    /// for it, given the text, the index of the range and the range. The
+    /// box is left as a caret after each replacement.
+    fn edit_block(
+        &mut self,
+        buffer: &mut T,
+        edit: impl Fn(&T, usize, Range<usize>) -> (Range<usize>, String),
+    ) {
+        let edits: Vec<(Range<usize>, String)> = self
+            .block
+            .iter()
+            .enumerate()
+            .map(|(index, range)| edit(buffer, index, range.clone()))
+            .collect();
+        // from the bottom up, so the ranges above stay where they are
+        for (range, text) in edits.iter().rev() {
+            buffer.edit(range.clone(), text);
+        }
+
+        let mut shift = 0isize;
+        self.block = edits
+            .iter()
+            .map(|(range, text)| {
+                let caret = (range.start as isize + shift) as usize + text.len();
+                shift += text.len() as isize - range.len() as isize;
+                caret..caret
+            })
+            .collect();
+        if let Some(first) = self.block.first() {
+            self.external_selection_change = Some(Selection::caret(first.start));
+        }
+        self.scroll_to_selection_end(true);
+    }
+
+    /// The ranges of a box spanned by two points, one per visual line
+    /// between them. Lines shorter than the box get a caret at their end.
+    fn block_ranges(&self, from: Point, to: Point) -> Vec<Range<usize>> {
+        let (text, layout) = match (self.layout.text(), self.layout.layout()) {
+            (Some(text), Some(layout)) => (text, layout),
+            _ => return vec![],
+        };
+        let line_of = |point: Point| {
+            layout
+                .hit_test_text_position(self.layout.text_position_for_point(point))
+                .line
+        };
+        let (first, last) = (
+            line_of(from).min(line_of(to)),
+            line_of(from).max(line_of(to)),
+        );
+        let (x0, x1) = (from.x.min(to.x), from.x.max(to.x));
+
+        (first..=last)
+            .filter_map(|line| {
+                let metric = layout.line_metric(line)?;
+                let content = text.slice(metric.range())?;
+                let content_end =
+                    metric.start_offset + content.trim_end_matches(&['\n', '\r'][..]).len();
+                let y = metric.y_offset + metric.height / 2.0;
+                let start = self
+                    .layout
+                    .text_position_for_point(Point::new(x0, y))
+                    .min(content_end);
+                let end = self
+                    .layout
+                    .text_position_for_point(Point::new(x1, y))
+                    .min(content_end);
+                Some(start..end)
+            })
+            .collect()
+    }
+
     /// Replace the current selection with `text`, and advance the cursor.
     ///
     /// This should only be called from the IME.
@@ -705,9 +1210,26 @@ impl<T: TextStorage + EditableText> EditSession<T> {
     }
 
     fn do_mouse_down(&mut self, point: Point, mods: Modifiers, count: u8) {
-        let point = point + Vec2::new(self.alignment_offset, 0.0);
+        let point = point + Vec2::new(self.alignment_offset - self.margin, 0.0);
         let pos = self.layout.text_position_for_point(point);
-        if mods.shift() {
+        self.block_origin = None;
+        let add_caret = if cfg!(target_os = "macos") {
+            mods.meta()
+        } else {
+            mods.ctrl()
+        };
+        if add_caret && count == 1 && self.accepts_newlines {
+            // Cmd+click adds a caret, typing goes to all of them
+            self.toggle_caret(pos);
+            return;
+        }
+        self.block.clear();
+        if mods.alt() && count == 1 && self.accepts_newlines {
+            // Alt+drag selects a box, see `do_drag`
+            self.block_origin = Some(point);
+            self.selection = Selection::caret(pos);
+            self.drag_granularity = DragGranularity::Grapheme;
+        } else if mods.shift() {
             self.selection.active = pos;
         } else {
             let Range { start, end } = self.sel_region_for_pos(pos, count);
@@ -721,7 +1243,19 @@ impl<T: TextStorage + EditableText> EditSession<T> {
     }
 
     fn do_drag(&mut self, point: Point) {
-        let point = point + Vec2::new(self.alignment_offset, 0.0);
+        let point = point + Vec2::new(self.alignment_offset - self.margin, 0.0);
+        if let Some(origin) = self.block_origin {
+            self.block = self.block_ranges(origin, point);
+            if let Some(first) = self.block.first() {
+                self.selection = Selection::new(first.start, first.end);
+            }
+            self.scroll_to_selection_end(false);
+            return;
+        }
+        if !self.block.is_empty() {
+            // the carets from Cmd+click stay put
+            return;
+        }
         //FIXME: this should behave differently if we were double or triple clicked
         let pos = self.layout.text_position_for_point(point);
         let text = match self.layout.text() {
@@ -757,7 +1291,7 @@ impl<T: TextStorage + EditableText> EditSession<T> {
     /// Returns a line suitable for drawing a standard cursor.
     pub fn cursor_line_for_text_position(&self, pos: usize) -> Line {
         let line = self.layout.cursor_line_for_text_position(pos);
-        line + Vec2::new(self.alignment_offset, 0.0)
+        line + self.text_offset()
     }
 
     fn sel_region_for_pos(&mut self, pos: usize, click_count: u8) -> Range<usize> {
@@ -802,6 +1336,8 @@ impl<T: TextStorage + EditableText> EditSession<T> {
         {
             self.update_pending_invalidation(ImeInvalidation::Reset);
             self.layout.set_text(new_data.clone());
+            // the text changed under the box, its ranges are stale
+            self.block.clear();
         }
         if self.layout.needs_rebuild_after_update(ctx) {
             ctx.request_layout();
@@ -815,6 +1351,20 @@ impl<T: TextStorage + EditableText> EditSession<T> {
     }
 }
 
+/// What Tab inserts at `offset`: a tab, or the spaces up to the next
+/// multiple of the indentation width.
+fn tab_text<T: EditableText>(indentation: Indentation, text: &T, offset: usize) -> String {
+    if indentation.tabs {
+        return "\t".to_string();
+    }
+    let width = indentation.width.max(1);
+    let line_start = text.preceding_line_break(offset);
+    let column = text
+        .slice(line_start..offset)
+        .map_or(0, |before| before.chars().count());
+    " ".repeat(width - column % width)
+}
+
 impl<T: TextStorage> EditSessionHandle<T> {
     fn new(inner: Arc<RefCell<EditSession<T>>>) -> Self {
         let text = inner.borrow().layout.text().cloned().unwrap();
@@ -853,8 +1403,18 @@ impl<T: TextStorage + EditableText> InputHandler for EditSessionHandle<T> {
     }
 
     fn replace_range(&mut self, range: Range<usize>, text: &str) {
-        self.text.edit(range, text);
-        self.inner.borrow_mut().external_text_change = Some(self.text.clone());
+        let mut inner = self.inner.borrow_mut();
+        // typing into a box selection types on every line of it
+        if !inner.block.is_empty()
+            && inner.composition_range.is_none()
+            && range == inner.selection.range()
+        {
+            inner.edit_block(&mut self.text, |_, _, range| (range, text.to_string()));
+        } else {
+            inner.block.clear();
+            self.text.edit(range, text);
+        }
+        inner.external_text_change = Some(self.text.clone());
     }
 
     fn hit_test_point(&self, point: Point) -> druid::piet::HitTestPoint {
@@ -880,7 +1440,7 @@ impl<T: TextStorage + EditableText> InputHandler for EditSessionHandle<T> {
     }
 
     fn slice_bounding_box(&self, range: Range<usize>) -> Option<Rect> {
-        let origin = self.inner.borrow().origin;
+        let origin = self.inner.borrow().origin + self.inner.borrow().text_offset();
         let layout = &self.inner.borrow().layout;
         if range.is_empty() {
             let hit = layout
@@ -920,13 +1480,21 @@ impl<T> Default for CustomTextComponent<T> {
             external_action: None,
             pending_ime_invalidation: None,
             selection: Selection::caret(0),
+            block: vec![],
+            block_origin: None,
             composition_range: None,
             send_notification_on_return: false,
             send_notification_on_cancel: false,
             accepts_newlines: false,
             accepts_tabs: false,
+            indentation: Default::default(),
+            read_only: false,
+            show_whitespace: false,
+            line_changes: Default::default(),
             alignment: TextAlignment::Start,
             alignment_offset: 0.0,
+            max_width: None,
+            margin: 0.0,
             drag_granularity: DragGranularity::Grapheme,
             origin: Point::ZERO,
         };
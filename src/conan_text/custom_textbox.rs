@@ -17,18 +17,16 @@
 use std::time::Duration;
 use tracing::{instrument, trace};
 
+use crate::conan_text::custom_input_component::CustomTextComponent;
 use druid::kurbo::Insets;
 use druid::piet::TextLayout as _;
-use druid::text::{
-    EditableText, ImeInvalidation, Selection, TextLayout, TextStorage,
-};
+use druid::text::{EditableText, ImeInvalidation, Selection, TextLayout, TextStorage};
 use druid::widget::prelude::*;
 use druid::widget::{Padding, Scroll, WidgetWrapper};
 use druid::{
     theme, Color, Command, FontDescriptor, HotKey, KeyEvent, KeyOrValue, Point, Rect, SysMods,
     TextAlignment, TimerToken, Vec2,
 };
-use crate::conan_text::custom_input_component::CustomTextComponent;
 
 const CURSOR_BLINK_DURATION: Duration = Duration::from_millis(500);
 const MAC_OR_LINUX: bool = cfg!(any(target_os = "macos", target_os = "linux"));
@@ -36,6 +34,20 @@ const MAC_OR_LINUX: bool = cfg!(any(target_os = "macos", target_os = "linux"));
 /// When we scroll after editing or movement, we show a little extra of the document.
 const SCROLL_TO_INSETS: Insets = Insets::uniform_xy(40.0, 0.0);
 
+/// How long the text flashes when an edit of read-only text is refused.
+const REFUSED_FLASH_DURATION: Duration = Duration::from_millis(120);
+
+/// How long a smooth scroll takes to reach its target.
+const SMOOTH_SCROLL_DURATION: Duration = Duration::from_millis(150);
+
+/// A smooth scroll on its way from one offset to another.
+struct ScrollAnimation {
+    from: Vec2,
+    to: Vec2,
+    /// nanoseconds since it started.
+    elapsed: u64,
+}
+
 /// A widget that allows user text input.
 ///
 /// # Editing values
@@ -66,6 +78,17 @@ pub struct CustomTextBox<T> {
     /// behaviour.
     pub handles_tab_notifications: bool,
     text_pos: Point,
+    /// the part of the text last reported by `VIEWPORT_CHANGED`.
+    viewport: (f64, f64),
+    /// long lines run past the edge and scroll sideways.
+    scrolls_horizontally: bool,
+    /// keep the caret line in the middle of the view, see `scroll_to_selection_end`.
+    typewriter: bool,
+    /// glide to the target of a wheel or a jump instead of landing on it.
+    smooth: bool,
+    scroll_animation: Option<ScrollAnimation>,
+    /// running while the flash of a refused edit shows.
+    flash_timer: TimerToken,
 }
 
 impl<T: EditableText + TextStorage> CustomTextBox<T> {
@@ -89,6 +112,12 @@ impl<T: EditableText + TextStorage> CustomTextBox<T> {
             cursor_timer: TimerToken::INVALID,
             handles_tab_notifications: true,
             text_pos: Point::ZERO,
+            viewport: (0.0, 1.0),
+            scrolls_horizontally: false,
+            typewriter: false,
+            smooth: false,
+            scroll_animation: None,
+            flash_timer: TimerToken::INVALID,
         }
     }
 
@@ -98,6 +127,7 @@ impl<T: EditableText + TextStorage> CustomTextBox<T> {
         this.inner
             .set_enabled_scrollbars(druid::scroll_component::ScrollbarsEnabled::Both);
         this.text_mut().borrow_mut().set_accepts_newlines(true);
+        this.text_mut().borrow_mut().set_accepts_tabs(true);
         this.inner.set_horizontal_scroll_enabled(false);
         this.multiline = true;
         this
@@ -112,6 +142,36 @@ impl<T: EditableText + TextStorage> CustomTextBox<T> {
     /// [`multiline`]: TextBox::multiline
     pub fn with_line_wrapping(mut self, wrap_lines: bool) -> Self {
         self.inner.set_horizontal_scroll_enabled(!wrap_lines);
+        self.scrolls_horizontally = !wrap_lines;
+        self
+    }
+
+    /// Builder-style method to keep the caret line vertically centered as
+    /// the caret moves, like the paper in a typewriter.
+    pub fn with_typewriter_scrolling(mut self, typewriter: bool) -> Self {
+        self.typewriter = typewriter;
+        self
+    }
+
+    /// Builder-style method to animate scrolling to the target of a wheel,
+    /// a page or a jump, easing out over a few frames.
+    pub fn with_smooth_scrolling(mut self, smooth: bool) -> Self {
+        self.smooth = smooth;
+        self
+    }
+
+    /// Builder-style method to wrap lines at no more than `max_width`,
+    /// centering the text column when the box is wider.
+    pub fn with_max_content_width(mut self, max_width: Option<f64>) -> Self {
+        self.text_mut().borrow_mut().set_max_width(max_width);
+        self
+    }
+
+    /// Builder-style method to draw spaces and tabs as dots and arrows.
+    pub fn with_show_whitespace(mut self, show_whitespace: bool) -> Self {
+        self.text_mut()
+            .borrow_mut()
+            .set_show_whitespace(show_whitespace);
         self
     }
 }
@@ -321,16 +381,96 @@ impl<T: TextStorage + EditableText> CustomTextBox<T> {
         Rect::new(x, y0, x, y1)
     }
 
-    fn scroll_to_selection_end(&mut self) {
+    /// Tell the ancestors which part of the text is visible, when it moved.
+    fn notify_viewport(&mut self, ctx: &mut EventCtx) {
+        let height = self.inner.child_size().height;
+        if height <= 0.0 {
+            return;
+        }
+
+        // while gliding, the ancestors only hear about where it will stop
+        let view = match &self.scroll_animation {
+            Some(animation) => {
+                Rect::from_origin_size(animation.to.to_point(), self.inner.viewport_rect().size())
+            }
+            None => self.inner.viewport_rect(),
+        };
+        let viewport = (view.y0 / height, (view.y1 / height).min(1.0));
+        if viewport != self.viewport {
+            self.viewport = viewport;
+            ctx.submit_notification(CustomTextComponent::VIEWPORT_CHANGED.with(viewport));
+        }
+    }
+
+    /// Bring the caret into view, gliding there when `animate` and smooth
+    /// scrolling is on. Edits don't animate, the caret would lag behind typing.
+    fn scroll_to_selection_end(&mut self, animate: bool) {
         let rect = self.rect_for_selection_end();
         let view_rect = self.inner.viewport_rect();
+        if self.typewriter {
+            // a rect as tall as the view around the caret puts it in the middle,
+            // the scroll stops at the ends so the first and last lines stay reachable
+            let (y, half) = (rect.center().y, view_rect.height() / 2.0);
+            let centered = Rect::new(rect.x0, y - half, rect.x1, y + half);
+            self.scroll_to(centered + SCROLL_TO_INSETS, animate);
+            return;
+        }
         let is_visible =
             view_rect.contains(rect.origin()) && view_rect.contains(Point::new(rect.x1, rect.y1));
         if !is_visible {
-            self.inner.scroll_to(rect + SCROLL_TO_INSETS);
+            self.scroll_to(rect + SCROLL_TO_INSETS, animate);
         }
     }
 
+    /// Scroll so `region` is visible, see `scroll_to_selection_end` for `animate`.
+    fn scroll_to(&mut self, region: Rect, animate: bool) {
+        if !(self.smooth && animate) {
+            self.scroll_animation = None;
+            self.inner.scroll_to(region);
+            return;
+        }
+        // let the scroll work out where it would stop, then go back and glide there
+        let from = self.inner.offset();
+        self.inner.scroll_to(region);
+        let to = self.inner.offset();
+        self.inner.scroll_by(from - to);
+        self.glide(from, to);
+    }
+
+    fn glide(&mut self, from: Vec2, to: Vec2) {
+        if from != to {
+            self.scroll_animation = Some(ScrollAnimation {
+                from,
+                to,
+                elapsed: 0,
+            });
+        }
+    }
+
+    /// Briefly tint the text, to show an edit of read-only text was refused.
+    fn flash_refused(&mut self, ctx: &mut EventCtx) {
+        self.flash_timer = ctx.request_timer(REFUSED_FLASH_DURATION);
+        ctx.request_paint();
+    }
+
+    /// Move a smooth scroll on by `interval` nanoseconds, easing out.
+    fn advance_scroll(&mut self, interval: u64) {
+        let animation = match &mut self.scroll_animation {
+            Some(animation) => animation,
+            None => return,
+        };
+        animation.elapsed += interval;
+        let t = (animation.elapsed as f64 / SMOOTH_SCROLL_DURATION.as_nanos() as f64).min(1.0);
+        let offset = if t >= 1.0 {
+            let to = animation.to;
+            self.scroll_animation = None;
+            to
+        } else {
+            animation.from.lerp(animation.to, 1.0 - (1.0 - t).powi(3))
+        };
+        self.inner.scroll_by(offset - self.inner.offset());
+    }
+
     /// These commands may be supplied by menus; but if they aren't, we
     /// inject them again, here.
     fn fallback_do_builtin_command(
@@ -372,7 +512,7 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextBox<T> {
                         ctx.request_layout();
                         self.scroll_to_selection_after_layout = true;
                     } else {
-                        self.scroll_to_selection_end();
+                        self.scroll_to_selection_end(true);
                     }
                     ctx.set_handled();
                     ctx.request_paint();
@@ -387,6 +527,10 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextBox<T> {
                     ctx.request_paint();
                     ctx.set_handled();
                 }
+                cmd if cmd.is(CustomTextComponent::EDIT_REFUSED) => {
+                    self.flash_refused(ctx);
+                    ctx.set_handled();
+                }
                 cmd if cmd.is(CustomTextComponent::CANCEL) => {
                     ctx.resign_focus();
                     ctx.request_paint();
@@ -414,6 +558,9 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextBox<T> {
                     self.cursor_on = !self.cursor_on;
                     ctx.request_paint();
                     self.cursor_timer = ctx.request_timer(CURSOR_BLINK_DURATION);
+                } else if *id == self.flash_timer {
+                    self.flash_timer = TimerToken::INVALID;
+                    ctx.request_paint();
                 }
             }
             Event::ImeStateChange => {
@@ -432,12 +579,116 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextBox<T> {
                     && ctx.is_focused()
                     && cmd.is(druid::commands::CUT) =>
             {
-                if self.text().borrow().set_clipboard() {
+                if self.text().borrow().is_read_only() {
+                    self.flash_refused(ctx);
+                } else if self.text().borrow().set_clipboard() {
                     let inval = self.text_mut().borrow_mut().insert_text(data, "");
                     ctx.invalidate_text_input(inval);
                 }
                 ctx.set_handled();
             }
+            Event::Command(cmd)
+                if self.text().can_write() && cmd.is(CustomTextComponent::SET_SELECTION) =>
+            {
+                let selection = *cmd.get_unchecked(CustomTextComponent::SET_SELECTION);
+                if let Some(inval) = self.text_mut().borrow_mut().set_selection(selection) {
+                    ctx.invalidate_text_input(inval);
+                }
+                ctx.request_focus();
+                self.scroll_to_selection_end(true);
+                if self.scroll_animation.is_some() {
+                    ctx.request_anim_frame();
+                }
+                ctx.submit_notification(CustomTextComponent::SELECTION_CHANGED.with(selection));
+                ctx.request_paint();
+                ctx.set_handled();
+                return;
+            }
+            // Shift turns a vertical wheel sideways, for mice without a horizontal one
+            Event::Wheel(wheel)
+                if self.scrolls_horizontally
+                    && wheel.mods.shift()
+                    && wheel.wheel_delta.x == 0.0 =>
+            {
+                self.inner.scroll_by(Vec2::new(wheel.wheel_delta.y, 0.0));
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            // wheels keep adding to where a glide is headed, so quick turns add up
+            Event::Wheel(wheel) if self.smooth && self.multiline => {
+                let from = self.inner.offset();
+                let start = self
+                    .scroll_animation
+                    .as_ref()
+                    .map_or(from, |animation| animation.to);
+                let (content, view) = (self.inner.child_size(), self.inner.viewport_rect().size());
+                let dx = if self.scrolls_horizontally {
+                    wheel.wheel_delta.x
+                } else {
+                    0.0
+                };
+                let to = Vec2::new(
+                    (start.x + dx)
+                        .max(0.0)
+                        .min((content.width - view.width).max(0.0)),
+                    (start.y + wheel.wheel_delta.y)
+                        .max(0.0)
+                        .min((content.height - view.height).max(0.0)),
+                );
+                self.glide(from, to);
+                ctx.set_handled();
+            }
+            Event::AnimFrame(interval) => {
+                self.advance_scroll(*interval);
+                ctx.request_paint();
+            }
+            Event::Command(cmd)
+                if self.text().can_write() && cmd.is(CustomTextComponent::EDIT_REFUSED) =>
+            {
+                self.flash_refused(ctx);
+            }
+            Event::Command(cmd)
+                if self.text().can_write() && cmd.is(CustomTextComponent::SET_LINE_CHANGES) =>
+            {
+                let changes = cmd
+                    .get_unchecked(CustomTextComponent::SET_LINE_CHANGES)
+                    .clone();
+                self.text_mut().borrow_mut().set_line_changes(changes);
+                ctx.request_paint();
+            }
+            Event::Command(cmd)
+                if self.text().can_write() && cmd.is(CustomTextComponent::SET_READ_ONLY) =>
+            {
+                let read_only = *cmd.get_unchecked(CustomTextComponent::SET_READ_ONLY);
+                self.text_mut().borrow_mut().set_read_only(read_only);
+            }
+            Event::Command(cmd)
+                if self.text().can_write() && cmd.is(CustomTextComponent::SET_INDENTATION) =>
+            {
+                let indentation = *cmd.get_unchecked(CustomTextComponent::SET_INDENTATION);
+                self.text_mut().borrow_mut().set_indentation(indentation);
+            }
+            Event::Command(cmd)
+                if ctx.is_focused() && cmd.is(CustomTextComponent::SELECT_NEXT_OCCURRENCE) =>
+            {
+                if self.text_mut().borrow_mut().select_next_occurrence(data) {
+                    ctx.invalidate_text_input(ImeInvalidation::SelectionChanged);
+                    let selection = self.text().borrow().selection();
+                    ctx.submit_notification(CustomTextComponent::SELECTION_CHANGED.with(selection));
+                    ctx.request_paint();
+                }
+                ctx.set_handled();
+            }
+            Event::Command(cmd) if cmd.is(CustomTextComponent::SCROLL_TO_FRACTION) => {
+                let fraction = *cmd.get_unchecked(CustomTextComponent::SCROLL_TO_FRACTION);
+                let y = self.inner.child_size().height * fraction.max(0.0).min(1.0);
+                let half = self.inner.viewport_rect().height() / 2.0;
+                self.scroll_to(Rect::new(0.0, y - half, 1.0, y + half), true);
+                ctx.request_paint();
+            }
+            Event::Paste(_) if self.text().can_write() && self.text().borrow().is_read_only() => {
+                self.flash_refused(ctx);
+            }
             Event::Paste(ref item) if self.text().can_write() => {
                 if let Some(string) = item.get_string() {
                     let text = if self.multiline {
@@ -453,7 +704,25 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextBox<T> {
             }
             _ => (),
         }
-        self.inner.event(ctx, event, data, env)
+
+        let pre_selection = if self.text().can_read() {
+            Some(self.text().borrow().selection())
+        } else {
+            None
+        };
+        self.inner.event(ctx, event, data, env);
+        if !matches!(event, Event::Notification(_)) && self.text().can_read() {
+            let selection = self.text().borrow().selection();
+            if Some(selection) != pre_selection {
+                ctx.submit_notification(CustomTextComponent::SELECTION_CHANGED.with(selection));
+            }
+        }
+        if !matches!(event, Event::Notification(_)) {
+            self.notify_viewport(ctx);
+        }
+        if self.scroll_animation.is_some() {
+            ctx.request_anim_frame();
+        }
     }
 
     #[instrument(name = "TextBox", level = "trace", skip(self, ctx, event, data, env))]
@@ -537,7 +806,7 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextBox<T> {
             + textbox_insets.y1;
         ctx.set_baseline_offset(baseline_off);
         if self.scroll_to_selection_after_layout {
-            self.scroll_to_selection_end();
+            self.scroll_to_selection_end(false);
             self.scroll_to_selection_after_layout = false;
         }
 
@@ -576,6 +845,9 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextBox<T> {
             .to_rounded_rect(env.get(theme::TEXTBOX_BORDER_RADIUS));
 
         ctx.fill(clip_rect, &background_color);
+        if self.flash_timer != TimerToken::INVALID {
+            ctx.fill(clip_rect, &env.get(theme::PRIMARY_LIGHT).with_alpha(0.15));
+        }
 
         if !data.is_empty() {
             self.inner.paint(ctx, data, env);
@@ -592,24 +864,26 @@ impl<T: TextStorage + EditableText> Widget<T> for CustomTextBox<T> {
         if is_focused && self.should_draw_cursor() {
             // if there's no data, we always draw the cursor based on
             // our alignment.
-            let cursor_pos = self.text().borrow().selection().active;
-            let cursor_line = self
-                .text()
-                .borrow()
-                .cursor_line_for_text_position(cursor_pos);
-
+            // a box selection has a caret on every line
+            let carets = self.text().borrow().carets();
             let padding_offset = Vec2::new(textbox_insets.x0, textbox_insets.y0);
+            for cursor_pos in carets {
+                let cursor_line = self
+                    .text()
+                    .borrow()
+                    .cursor_line_for_text_position(cursor_pos);
+
+                let cursor = if data.is_empty() {
+                    cursor_line + padding_offset
+                } else {
+                    cursor_line + padding_offset - self.inner.offset()
+                };
 
-            let cursor = if data.is_empty() {
-                cursor_line + padding_offset
-            } else {
-                cursor_line + padding_offset - self.inner.offset()
-            };
-
-            ctx.with_save(|ctx| {
-                ctx.clip(clip_rect);
-                ctx.stroke(cursor, &cursor_color, 1.);
-            })
+                ctx.with_save(|ctx| {
+                    ctx.clip(clip_rect);
+                    ctx.stroke(cursor, &cursor_color, 1.);
+                })
+            }
         }
 
         // Paint the border
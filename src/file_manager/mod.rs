@@ -1 +1,212 @@
+//! File system operations behind the project tree's context menu.
 
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+/// what the entry showing a path in the platform file manager is called.
+#[cfg(target_os = "macos")]
+pub const REVEAL_TITLE: &str = "Reveal in Finder";
+#[cfg(target_os = "windows")]
+pub const REVEAL_TITLE: &str = "Reveal in Explorer";
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub const REVEAL_TITLE: &str = "Open Containing Folder";
+
+#[derive(Clone, Debug)]
+pub enum FileOperation {
+    /// create a file inside the given dir
+    NewFile(PathBuf),
+    /// create a folder inside the given dir
+    NewFolder(PathBuf),
+    Rename(PathBuf),
+    Delete(PathBuf),
+    /// move a file or folder into the given dir, replacing one of the same name
+    Move(PathBuf, PathBuf),
+}
+
+impl FileOperation {
+    pub fn title(&self) -> &'static str {
+        match self {
+            FileOperation::NewFile(_) => "New File",
+            FileOperation::NewFolder(_) => "New Folder",
+            FileOperation::Rename(_) => "Rename",
+            FileOperation::Delete(_) => "Delete",
+            FileOperation::Move(_, _) => "Move",
+        }
+    }
+
+    /// the label of the button confirming the operation.
+    pub fn confirm_label(&self) -> &'static str {
+        match self {
+            FileOperation::Move(_, _) => "Replace",
+            _ => self.title(),
+        }
+    }
+
+    /// whether the operation asks the user for a file name.
+    pub fn needs_input(&self) -> bool {
+        !matches!(self, FileOperation::Delete(_) | FileOperation::Move(_, _))
+    }
+
+    /// the text the name input starts with.
+    pub fn initial_input(&self) -> String {
+        match self {
+            FileOperation::Rename(path) => file_name(path),
+            _ => "".to_string(),
+        }
+    }
+
+    /// the dirs whose listing changes after the operation.
+    pub fn affected_dirs(&self) -> Vec<&Path> {
+        match self {
+            FileOperation::NewFile(dir) | FileOperation::NewFolder(dir) => vec![dir],
+            FileOperation::Rename(path) | FileOperation::Delete(path) => {
+                path.parent().into_iter().collect()
+            }
+            FileOperation::Move(path, dir) => path
+                .parent()
+                .into_iter()
+                .chain(Some(dir.as_path()))
+                .collect(),
+        }
+    }
+
+    /// where a `Move` puts the file, `None` for the other operations.
+    pub fn move_target(&self) -> Option<PathBuf> {
+        match self {
+            FileOperation::Move(path, dir) => Some(dir.join(path.file_name()?)),
+            _ => None,
+        }
+    }
+
+    /// Run the operation, `input` is the name typed by the user. Returns the
+    /// path that was created or renamed to.
+    pub fn apply(&self, input: &str) -> io::Result<Option<PathBuf>> {
+        let name = input.trim();
+        if self.needs_input() && !is_plain_name(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid file name: {:?}", input),
+            ));
+        }
+
+        match self {
+            FileOperation::NewFile(dir) => {
+                let path = dir.join(name);
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)?;
+                Ok(Some(path))
+            }
+            FileOperation::NewFolder(dir) => {
+                let path = dir.join(name);
+                fs::create_dir(&path)?;
+                Ok(Some(path))
+            }
+            FileOperation::Rename(path) => {
+                let target = path.with_file_name(name);
+                if target.exists() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{:?} already exists", target),
+                    ));
+                }
+                fs::rename(path, &target)?;
+                Ok(Some(target))
+            }
+            FileOperation::Delete(path) => {
+                to_trash(path)?;
+                Ok(None)
+            }
+            FileOperation::Move(path, _) => {
+                let target = self.move_target().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("can not move {:?}", path),
+                    )
+                })?;
+                // into its own dir, or a dir into itself
+                if target.starts_with(path) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("can not move {:?} into itself", path),
+                    ));
+                }
+                if target.exists() {
+                    to_trash(&target)?;
+                }
+                fs::rename(path, &target)?;
+                Ok(Some(target))
+            }
+        }
+    }
+}
+
+/// Show `path` in the platform file manager, selected where it can be.
+pub fn reveal(path: &Path) -> io::Result<()> {
+    reveal_command(path).spawn().map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+fn reveal_command(path: &Path) -> Command {
+    let mut command = Command::new("open");
+    command.arg("-R").arg(path);
+    command
+}
+
+#[cfg(target_os = "windows")]
+fn reveal_command(path: &Path) -> Command {
+    let mut command = Command::new("explorer");
+    command.arg(format!("/select,{}", path.display()));
+    command
+}
+
+// xdg-open can't select a file, so it opens the dir the file is in
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn reveal_command(path: &Path) -> Command {
+    let dir = if path.is_dir() {
+        path
+    } else {
+        path.parent().unwrap_or(path)
+    };
+    let mut command = Command::new("xdg-open");
+    command.arg(dir);
+    command
+}
+
+/// Move `path` to the trash, the delete or replace was confirmed, so
+/// without a trash it is removed for good.
+fn to_trash(path: &Path) -> io::Result<()> {
+    if let Err(err) = trash::delete(path) {
+        log::warn!("can not move {:?} to the trash, removing it: {}", path, err);
+        remove(path)?;
+    }
+    Ok(())
+}
+
+fn remove(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+/// a name that stays inside the dir it is joined to: no separators, and
+/// not `.` or `..`.
+fn is_plain_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    )
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("")
+        .to_string()
+}
@@ -7,58 +7,135 @@ extern crate notify;
 #[macro_use]
 extern crate serde_json;
 
-use druid::{AppLauncher, Color, UnitPoint, WindowDesc};
-use druid::widget::{Flex, Label, WidgetExt};
 use druid::widget::prelude::*;
+use druid::widget::{Either, EnvScope, Flex, Label, SizedBox, Split, WidgetExt};
+use druid::{AppLauncher, UnitPoint, WindowDesc};
 
 use app_state::AppState;
 use print::menu;
 use print::text_edit_view::TextEditView;
 pub use support::line;
 
+use crate::app_command::print_command;
 use crate::app_delegate::Delegate;
 use crate::app_state::Workspace;
 use crate::components::icon_button::IconButton;
+use crate::components::max_width::MaxWidth;
+use crate::components::modal_host::ModalHost;
+use crate::print::auto_save::AutoSave;
+use crate::print::find_in_files::find_in_files_panel;
+use crate::print::font_check::FontCheck;
+use crate::print::git_diff::GitDiff;
+use crate::print::markdown_preview::MarkdownPreview;
+use crate::print::navigation_bar::NavigationBar;
+use crate::print::outline_panel::OutlinePanel;
+use crate::print::output_panel::output_panel;
+use crate::print::system_theme::SystemTheme;
+use crate::print::welcome::welcome;
 use crate::print::ProjectToolWindow;
 use crate::support::directory;
 
+use self::print::bar_support::indentation_menu::IndentationMenu;
+use self::print::bar_support::language_menu::LanguageMenu;
 use self::print::bar_support::text_count;
 
 pub mod app_command;
 pub mod app_delegate;
 pub mod app_state;
 pub mod components;
+pub mod conan_text;
 pub mod file_manager;
 pub mod model;
 pub mod print;
 pub mod support;
 pub mod theme;
-pub mod conan_text;
 
 fn navigation_bar() -> impl Widget<AppState> {
-    let label = Label::new(|workspace: &Workspace, _env: &Env| workspace.relative_path())
-        .with_text_color(Color::BLACK);
-    Flex::row()
-        .with_child(label)
+    NavigationBar::new()
         .padding(10.0)
         .expand_width()
-        .lens(AppState::workspace)
         .background(line::hline())
         .align_horizontal(UnitPoint::LEFT)
 }
 
 fn status_bar() -> impl Widget<AppState> {
     let label = Label::new(|data: &Workspace, _env: &Env| {
-        return text_count::count(&data.input_text).to_string();
+        // counting runs on every keystroke, too slow for a large file
+        if data.large_file {
+            return "-".to_string();
+        }
+        let count = text_count::count_all(&data.input_text);
+        return format!(
+            "{}    chars: {}    {}",
+            count.words,
+            count.chars,
+            text_count::reading_time_label(count.reading_minutes())
+        );
+    })
+    .with_text_color(crate::theme::BASIC_TEXT_COLOR);
+
+    let caret = Label::new(|data: &Workspace, _env: &Env| data.caret_position())
+        .with_text_color(crate::theme::BASIC_TEXT_COLOR);
+
+    let language = Label::new(|data: &Workspace, _env: &Env| data.language.to_string())
+        .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+        .controller(LanguageMenu);
+
+    let indentation = Label::new(|data: &Workspace, _env: &Env| data.indentation.label())
+        .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+        .controller(IndentationMenu);
+
+    let read_only = Label::new(|data: &Workspace, _env: &Env| {
+        if data.read_only {
+            "\u{1F512} Read Only"
+        } else {
+            ""
+        }
+        .to_string()
+    })
+    .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+    .on_click(|ctx, _data, _env| ctx.submit_command(print_command::TOGGLE_READ_ONLY));
+
+    let large_file = Label::new(|data: &Workspace, _env: &Env| {
+        if data.large_file {
+            "Large File Mode"
+        } else {
+            ""
+        }
+        .to_string()
     })
-        .with_text_color(Color::BLACK);
+    .with_text_color(crate::theme::BASIC_TEXT_COLOR);
+
+    let encoding = Label::new(|data: &Workspace, _env: &Env| {
+        if data.bom {
+            format!("{} with BOM", data.encoding)
+        } else {
+            data.encoding.to_string()
+        }
+    })
+    .with_text_color(crate::theme::BASIC_TEXT_COLOR);
 
     Flex::row()
         .with_default_spacer()
-        .with_flex_child(Label::new("words: ").with_text_color(Color::BLACK), 1.0)
+        .with_flex_child(
+            Label::new("words: ").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+            1.0,
+        )
         .with_default_spacer()
         .with_flex_child(label, 1.0)
         .with_default_spacer()
+        .with_child(caret)
+        .with_default_spacer()
+        .with_child(read_only)
+        .with_default_spacer()
+        .with_child(large_file)
+        .with_default_spacer()
+        .with_child(indentation)
+        .with_default_spacer()
+        .with_child(encoding)
+        .with_default_spacer()
+        .with_child(language)
+        .with_default_spacer()
         .lens(AppState::workspace)
         .padding(5.0)
         .align_horizontal(UnitPoint::LEFT)
@@ -66,52 +143,157 @@ fn status_bar() -> impl Widget<AppState> {
 
 fn bottom_tool_window() -> impl Widget<AppState> {
     let text = "Run";
-    let label = Label::new(text).with_text_color(Color::BLACK);
-    let button = IconButton::from_label(label);
-    Flex::row()
-        .with_default_spacer()
-        .with_flex_child(button, 1.0)
-        .lens(AppState::params)
-        .background(line::hline())
+    let label = Label::new(text).with_text_color(crate::theme::BASIC_TEXT_COLOR);
+    let button = IconButton::from_label(label)
+        .disabled_if(|data: &AppState, _env| data.running || data.current_dir.is_none())
+        .on_click(|ctx, _data, _env| ctx.submit_command(print_command::RUN));
+    let clear =
+        IconButton::from_label(Label::new("Clear").with_text_color(crate::theme::BASIC_TEXT_COLOR))
+            .disabled_if(|data: &AppState, _env| data.output.is_empty())
+            .on_click(|ctx, _data, _env| ctx.submit_command(print_command::CLEAR_OUTPUT));
+    let toggle = IconButton::new(|data: &AppState, _env: &Env| {
+        if data.params.show_output {
+            "Hide Output"
+        } else {
+            "Show Output"
+        }
+        .to_string()
+    })
+    .on_click(|ctx, _data, _env| ctx.submit_command(print_command::TOGGLE_OUTPUT));
+    let output = Either::new(
+        |data: &AppState, _env| data.params.show_output,
+        output_panel(),
+        SizedBox::empty(),
+    );
+
+    Flex::column()
+        .with_child(
+            Flex::row()
+                .with_default_spacer()
+                .with_child(button)
+                .with_flex_spacer(1.0)
+                .with_child(clear)
+                .with_child(toggle)
+                .with_default_spacer()
+                .background(line::hline()),
+        )
+        .with_child(output)
+}
+
+/// Split shows the open file twice, side by side, each pane with its own
+/// scroll. Both edit the same buffer, there is no second file slot.
+fn editor() -> impl Widget<AppState> {
+    Either::new(
+        |data: &AppState, _env| data.split,
+        Split::columns(TextEditView::new(), TextEditView::new()).draggable(true),
+        TextEditView::new(),
+    )
 }
 
+/// the width of the editor in zen mode, a comfortable line length for prose.
+const ZEN_MODE_WIDTH: f64 = 800.0;
+
 fn center() -> impl Widget<AppState> {
+    let preview = MarkdownPreview::new()
+        .lens(AppState::workspace)
+        .background(crate::theme::TOOL_WINDOW_COLOR);
+    let editor = Either::new(
+        |data: &AppState, _env| data.preview,
+        Split::columns(editor(), preview).draggable(true),
+        editor(),
+    );
+    let editor = MaxWidth::new(editor, |data: &AppState| {
+        Some(ZEN_MODE_WIDTH).filter(|_| data.params.zen_mode)
+    });
+    let editor = Either::new(
+        |data: &AppState, _env| data.is_empty_session(),
+        welcome(),
+        editor.center(),
+    );
+    let outline = Either::new(
+        |data: &AppState, _env| data.params.show_outline && !data.params.zen_mode,
+        OutlinePanel::new().lens(AppState::workspace),
+        SizedBox::empty(),
+    );
+    let project = Either::new(
+        |data: &AppState, _env| data.params.zen_mode,
+        SizedBox::empty(),
+        ProjectToolWindow::new(),
+    );
+
     Flex::row()
-        .with_child(ProjectToolWindow::new())
+        .with_child(project)
         .with_default_spacer()
-        .with_flex_child(TextEditView::new().center(), 1.0)
+        .with_flex_child(editor, 1.0)
+        .with_child(outline)
         .padding(1.0)
         .expand_height()
         .expand_width()
         .background(line::hline())
 }
 
+/// `widget`, hidden in zen mode.
+fn unless_zen_mode(widget: impl Widget<AppState> + 'static) -> impl Widget<AppState> {
+    Either::new(
+        |data: &AppState, _env| data.params.zen_mode,
+        SizedBox::empty(),
+        widget,
+    )
+}
+
 fn make_ui() -> impl Widget<AppState> {
-    Flex::column()
-        .with_child(navigation_bar())
+    let layout = Flex::column()
+        .with_child(unless_zen_mode(navigation_bar()))
         .with_flex_child(center(), 1.0)
-        .with_child(bottom_tool_window())
-        .with_child(status_bar())
+        .with_child(Either::new(
+            |data: &AppState, _env| data.search.visible,
+            find_in_files_panel(),
+            SizedBox::empty(),
+        ))
+        .with_child(unless_zen_mode(bottom_tool_window()))
+        .with_child(unless_zen_mode(status_bar()))
         .background(crate::theme::BACKGROUND_COLOR)
-}
+        .controller(AutoSave::new())
+        .controller(GitDiff::new())
+        .controller(FontCheck)
+        .controller(SystemTheme::new());
 
-pub fn main() {
-    setup_log();
+    EnvScope::new(
+        |env, data: &AppState| {
+            theme::apply_dark_mode(env, data.dark_mode());
+            let family = Some(data.params.font_family.as_str()).filter(|_| !data.missing_font);
+            theme::apply_writing_font(env, family, data.params.font_size);
+        },
+        ModalHost::new(layout),
+    )
+}
 
+/// A window on the app state, every window shows the same buffer and project.
+fn main_window() -> WindowDesc<AppState> {
     let title = "Conan";
 
-    let main_window = WindowDesc::new(make_ui())
+    WindowDesc::new(make_ui())
         .window_size((1024., 768.))
         .with_min_size((1024., 768.))
         .menu(menu::make_menu)
-        .title(title);
+        .title(move |data: &AppState, _env: &Env| {
+            format!("{} - {}", data.workspace.file_title(), title)
+        })
+}
 
+pub fn main() {
+    setup_log();
+
+    let main_window = main_window();
 
     let mut init_state = directory::read_config();
     init_state.setup_workspace();
 
-    AppLauncher::with_window(main_window)
-        .delegate(Delegate::default())
+    let launcher = AppLauncher::with_window(main_window);
+    let delegate = Delegate::new(launcher.get_external_handle());
+
+    launcher
+        .delegate(delegate)
         .configure_env(|env, _| theme::configure_env(env))
         .launch(init_state)
         .expect("Failed to launch application");
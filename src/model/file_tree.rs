@@ -1,3 +1,4 @@
+use std::collections::{HashMap, HashSet};
 use std::fs::DirEntry;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -6,7 +7,9 @@ use std::{fmt, fs, io};
 use druid::{Data, Lens};
 use serde::{Deserialize, Serialize};
 
+use crate::components::file_icon;
 use crate::components::tree::TreeNode;
+use crate::support::git::GitStatus;
 
 #[derive(Serialize, Deserialize, Clone, Lens, Debug)]
 pub struct FileEntry {
@@ -15,6 +18,11 @@ pub struct FileEntry {
     pub is_dir: bool,
     pub path: String,
     pub children: Vec<FileEntry>,
+    #[serde(default)]
+    pub expanded: bool,
+    /// set by `apply_git_status`, `None` for unchanged files.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub git_status: Option<GitStatus>,
 }
 
 impl Default for FileEntry {
@@ -25,11 +33,57 @@ impl Default for FileEntry {
             is_dir: false,
             path: "".to_string(),
             children: vec![],
+            expanded: false,
+            git_status: None,
         }
     }
 }
 
 impl FileEntry {
+    pub fn icon(&self) -> &'static str {
+        if self.is_dir {
+            file_icon::folder_icon(self.expanded)
+        } else {
+            file_icon::file_icon(&self.ext)
+        }
+    }
+
+    /// The paths of all expanded dirs, to carry the expansion state over
+    /// to a rebuilt tree with `apply_expanded`.
+    pub fn expanded_paths(&self) -> HashSet<String> {
+        let mut paths = HashSet::new();
+        self.collect_expanded(&mut paths);
+        paths
+    }
+
+    fn collect_expanded(&self, paths: &mut HashSet<String>) {
+        if self.is_dir && self.expanded {
+            paths.insert(self.path.clone());
+        }
+        for child in self.children.iter() {
+            child.collect_expanded(paths);
+        }
+    }
+
+    /// Expand the dirs listed in `paths`; paths that no longer exist in the
+    /// tree are ignored.
+    pub fn apply_expanded(&mut self, paths: &HashSet<String>) {
+        if self.is_dir && paths.contains(&self.path) {
+            self.expanded = true;
+        }
+        for child in self.children.iter_mut() {
+            child.apply_expanded(paths);
+        }
+    }
+
+    /// Mark the entries that have a status in `statuses`, clearing the rest.
+    pub fn apply_git_status(&mut self, statuses: &HashMap<String, GitStatus>) {
+        self.git_status = statuses.get(&self.path).copied();
+        for child in self.children.iter_mut() {
+            child.apply_git_status(statuses);
+        }
+    }
+
     pub fn from_path(path: PathBuf) -> Self {
         let file_name = path.file_name().unwrap();
         let name = match file_name.to_str() {
@@ -49,6 +103,8 @@ impl FileEntry {
             is_dir: false,
             path,
             children: vec![],
+            expanded: false,
+            git_status: None,
         }
     }
     pub fn new(name: String) -> Self {
@@ -58,6 +114,8 @@ impl FileEntry {
             is_dir: false,
             path: "".to_string(),
             children: vec![],
+            expanded: false,
+            git_status: None,
         }
     }
 
@@ -82,8 +140,135 @@ impl FileEntry {
             .unwrap_or(false)
     }
 
+    /// Same rule as `is_hidden`, for paths relative to the project dir that
+    /// may no longer exist on disk (e.g. from a remove event): anything in a
+    /// dot-dir, and `.DS_Store`. Dotfiles like `.gitignore` stay visible.
+    pub fn is_hidden_path(relative: &Path) -> bool {
+        let (last, dirs) = match relative.parent() {
+            Some(parent) => (relative.file_name(), parent),
+            None => return false,
+        };
+        let in_hidden_dir = dirs
+            .iter()
+            .any(|name| name.to_str().map(|s| s.starts_with(".")).unwrap_or(false));
+        in_hidden_dir || last.and_then(|name| name.to_str()) == Some(".DS_Store")
+    }
+
+    /// Rescan the directory at `relative` (relative to `base_dir`) and replace
+    /// its children. Returns `false` when no node exists for that directory.
+    pub fn refresh_dir(&mut self, relative: &Path, base_dir: &Path) -> bool {
+        let relative_name = format!("{}", relative.display());
+        let node = if relative_name.is_empty() {
+            Some(self)
+        } else {
+            self.find_dir_mut(&relative_name)
+        };
+
+        match node {
+            None => false,
+            Some(node) => {
+                let mut fresh = FileEntry::new(node.name.clone());
+                if let Err(err) =
+                    FileEntry::visit_dirs(&base_dir.join(relative), 0, &mut fresh, base_dir)
+                {
+                    log::info!("refresh dir error: {:?}", err);
+                }
+                let expanded = node.expanded_paths();
+                node.children = fresh.children;
+                node.apply_expanded(&expanded);
+                true
+            }
+        }
+    }
+
+    /// the child indices leading from this entry to the one at `target`.
+    pub fn index_path(&self, target: &Path) -> Option<Vec<usize>> {
+        for (index, child) in self.children.iter().enumerate() {
+            if Path::new(&child.path) == target {
+                return Some(vec![index]);
+            }
+            if child.is_dir && target.starts_with(&child.path) {
+                let mut rest = child.index_path(target)?;
+                rest.insert(0, index);
+                return Some(rest);
+            }
+        }
+        None
+    }
+
+    /// the paths of all files below this entry.
+    pub fn file_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![];
+        self.collect_file_paths(&mut paths);
+        paths
+    }
+
+    fn collect_file_paths(&self, paths: &mut Vec<PathBuf>) {
+        for child in self.children.iter() {
+            if child.is_dir {
+                child.collect_file_paths(paths);
+            } else {
+                paths.push(PathBuf::from(&child.path));
+            }
+        }
+    }
+
+    /// Expand the dirs leading to `target`, returns whether it was found.
+    pub fn expand_to(&mut self, target: &Path) -> bool {
+        for child in self.children.iter_mut() {
+            if Path::new(&child.path) == target {
+                return true;
+            }
+            if child.is_dir && target.starts_with(&child.path) && child.expand_to(target) {
+                child.expanded = true;
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The entry at `target`, this entry itself included.
+    pub fn find(&self, target: &Path) -> Option<&FileEntry> {
+        if Path::new(&self.path) == target {
+            return Some(self);
+        }
+        self.children
+            .iter()
+            .filter(|child| target.starts_with(&child.path))
+            .find_map(|child| child.find(target))
+    }
+
+    pub fn find_mut(&mut self, target: &Path) -> Option<&mut FileEntry> {
+        if Path::new(&self.path) == target {
+            return Some(self);
+        }
+        self.children
+            .iter_mut()
+            .filter(|child| target.starts_with(&child.path))
+            .find_map(|child| child.find_mut(target))
+    }
+
+    fn find_dir_mut(&mut self, name: &str) -> Option<&mut FileEntry> {
+        for child in self.children.iter_mut() {
+            if !child.is_dir {
+                continue;
+            }
+            if child.name == name {
+                return Some(child);
+            }
+            if Path::new(name).starts_with(&child.name) {
+                return child.find_dir_mut(name);
+            }
+        }
+        None
+    }
+
     pub fn from_dir(title: String, dir: &Arc<Path>) -> FileEntry {
         let mut root = FileEntry::new(title);
+        root.is_dir = true;
+        root.path = format!("{}", dir.display());
+        // the tree always shows the first level
+        root.expanded = true;
         let _result = FileEntry::visit_dirs(dir, 0, &mut root, dir);
         root
     }
@@ -118,6 +303,7 @@ impl FileEntry {
                     let relative_path = path.strip_prefix(base_dir).unwrap();
                     let entry = &mut FileEntry::new(format!("{}", relative_path.display()));
                     entry.is_dir = true;
+                    entry.path = format!("{}", path.display());
                     FileEntry::visit_dirs(&path, depth, entry, base_dir)?;
                     node.children.push(entry.to_owned());
                 } else {
@@ -133,6 +319,7 @@ impl FileEntry {
 impl Data for FileEntry {
     fn same(&self, other: &Self) -> bool {
         self.name.same(&other.name)
+            && self.git_status == other.git_status
             && self.children.len() == other.children.len()
             && self
                 .children
@@ -154,6 +341,18 @@ impl TreeNode for FileEntry {
     fn get_child_mut(&mut self, index: usize) -> &mut FileEntry {
         &mut self.children[index]
     }
+
+    fn set_expanded(&mut self, expanded: bool) {
+        self.expanded = expanded;
+    }
+
+    fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+
+    fn accepts_drop(&self) -> bool {
+        self.is_dir
+    }
 }
 
 impl fmt::Display for FileEntry {
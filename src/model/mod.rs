@@ -1 +1,3 @@
 pub mod file_tree;
+pub mod outline;
+pub mod search;
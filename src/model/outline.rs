@@ -0,0 +1,134 @@
+use std::fmt;
+
+use druid::Data;
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::components::tree::TreeNode;
+use crate::support::language;
+
+/// A heading of the open file, with the headings below it.
+#[derive(Clone, Debug)]
+pub struct OutlineEntry {
+    pub title: String,
+    /// the line the heading is on, starting at 1.
+    pub line: usize,
+    pub level: u32,
+    pub children: Vec<OutlineEntry>,
+    pub expanded: bool,
+}
+
+impl Default for OutlineEntry {
+    fn default() -> Self {
+        OutlineEntry::new("".to_string(), 1, 0)
+    }
+}
+
+impl OutlineEntry {
+    fn new(title: String, line: usize, level: u32) -> Self {
+        OutlineEntry {
+            title,
+            line,
+            level,
+            children: vec![],
+            expanded: true,
+        }
+    }
+
+    /// The outline of `text` under a root named `title`. Only Markdown has
+    /// one for now, other languages get an empty root.
+    pub fn from_text(title: String, text: &str, language: &str) -> OutlineEntry {
+        let mut root = OutlineEntry::new(title, 1, 0);
+        if language::has_hard_line_breaks(language) {
+            for heading in markdown_headings(text) {
+                root.insert(heading);
+            }
+        }
+        root
+    }
+
+    /// Add `heading` below the last entry of a higher level.
+    fn insert(&mut self, heading: OutlineEntry) {
+        match self.children.last_mut() {
+            Some(last) if last.level < heading.level => last.insert(heading),
+            _ => self.children.push(heading),
+        }
+    }
+
+    /// Keep the entries collapsed in `old` collapsed, matched by title.
+    pub fn keep_collapsed(&mut self, old: &OutlineEntry) {
+        self.expanded = old.expanded;
+        for child in self.children.iter_mut() {
+            if let Some(old) = old.children.iter().find(|o| o.title == child.title) {
+                child.keep_collapsed(old);
+            }
+        }
+    }
+}
+
+/// the headings of a markdown text, in order, without nesting.
+fn markdown_headings(text: &str) -> Vec<OutlineEntry> {
+    let mut headings = vec![];
+    let mut current: Option<OutlineEntry> = None;
+
+    for (event, range) in Parser::new(text).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading(level)) => {
+                let line = text[..range.start].matches('\n').count() + 1;
+                current = Some(OutlineEntry::new("".to_string(), line, level));
+            }
+            Event::Text(part) | Event::Code(part) => {
+                if let Some(heading) = current.as_mut() {
+                    heading.title.push_str(&part);
+                }
+            }
+            Event::End(Tag::Heading(_)) => {
+                if let Some(heading) = current.take() {
+                    headings.push(heading);
+                }
+            }
+            _ => {}
+        }
+    }
+    headings
+}
+
+impl Data for OutlineEntry {
+    fn same(&self, other: &Self) -> bool {
+        self.title == other.title
+            && self.line == other.line
+            && self.children.len() == other.children.len()
+            && self
+                .children
+                .iter()
+                .zip(other.children.iter())
+                .all(|(a, b)| a.same(b))
+    }
+}
+
+impl TreeNode for OutlineEntry {
+    fn children_count(&self) -> usize {
+        self.children.len()
+    }
+
+    fn get_child(&self, index: usize) -> &OutlineEntry {
+        &self.children[index]
+    }
+
+    fn get_child_mut(&mut self, index: usize) -> &mut OutlineEntry {
+        &mut self.children[index]
+    }
+
+    fn set_expanded(&mut self, expanded: bool) {
+        self.expanded = expanded;
+    }
+
+    fn is_expanded(&self) -> bool {
+        self.expanded
+    }
+}
+
+impl fmt::Display for OutlineEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.title)
+    }
+}
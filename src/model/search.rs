@@ -0,0 +1,42 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use druid::{Data, Lens};
+
+/// A line matching a find in files query.
+#[derive(Clone, Data, Lens, Debug)]
+pub struct SearchMatch {
+    pub path: Arc<Path>,
+    /// 1-based, like the status bar shows it.
+    pub line: usize,
+    pub preview: String,
+}
+
+/// The matches within one file.
+#[derive(Clone, Data, Lens, Debug)]
+pub struct SearchFileResult {
+    /// the path relative to the project dir.
+    pub title: String,
+    pub matches: Arc<Vec<SearchMatch>>,
+}
+
+/// The find in files panel: the query, its toggles and what was found.
+#[derive(Clone, Data, Lens, Debug, Default)]
+pub struct SearchState {
+    pub visible: bool,
+    pub query: String,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    pub regex: bool,
+    pub results: Arc<Vec<SearchFileResult>>,
+    /// a search is still walking the project.
+    pub searching: bool,
+    /// set when the query is not a valid regex.
+    pub error: Option<String>,
+}
+
+impl SearchState {
+    pub fn match_count(&self) -> usize {
+        self.results.iter().map(|file| file.matches.len()).sum()
+    }
+}
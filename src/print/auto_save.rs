@@ -0,0 +1,51 @@
+use std::time::Duration;
+
+use druid::widget::Controller;
+use druid::{Env, Event, EventCtx, TimerToken, Widget};
+
+use crate::app_command::print_command;
+use crate::app_state::AppState;
+
+/// Sends `AUTO_SAVE` every `Params::auto_save_secs` while auto-save is on
+/// and the open file has unsaved changes.
+pub struct AutoSave {
+    timer: TimerToken,
+}
+
+impl AutoSave {
+    pub fn new() -> AutoSave {
+        AutoSave {
+            timer: TimerToken::INVALID,
+        }
+    }
+
+    fn schedule(&mut self, ctx: &mut EventCtx, data: &AppState) {
+        let secs = data.params.auto_save_secs.max(1);
+        self.timer = ctx.request_timer(Duration::from_secs(secs));
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for AutoSave {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::WindowConnected => self.schedule(ctx, data),
+            Event::Timer(token) if *token == self.timer => {
+                if data.params.auto_save && data.workspace.dirty && data.current_file.is_some() {
+                    ctx.submit_command(print_command::AUTO_SAVE);
+                }
+                self.schedule(ctx, data);
+                return;
+            }
+            _ => {}
+        }
+
+        child.event(ctx, event, data, env)
+    }
+}
@@ -0,0 +1,73 @@
+use druid::widget::Controller;
+use druid::{Env, Event, EventCtx, LocalizedString, Menu, MenuItem, Widget};
+
+use crate::app_command::print_command;
+use crate::app_state::{AppState, Workspace};
+use crate::support::indentation::{self, Indentation};
+
+/// Shows how to indent, tabs or spaces and the width, when the indentation
+/// in the status bar is clicked.
+pub struct IndentationMenu;
+
+impl IndentationMenu {
+    fn menu(current: Indentation) -> Menu<AppState> {
+        let spaces = Indentation {
+            tabs: false,
+            ..current
+        };
+        let tabs = Indentation {
+            tabs: true,
+            ..current
+        };
+        let mut menu = Menu::empty()
+            .entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-indent-spaces")
+                        .with_placeholder("Indent Using Spaces"),
+                )
+                .command(print_command::SET_INDENTATION.with(spaces))
+                .selected_if(|data: &AppState, _env| !data.workspace.indentation.tabs),
+            )
+            .entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-indent-tabs")
+                        .with_placeholder("Indent Using Tabs"),
+                )
+                .command(print_command::SET_INDENTATION.with(tabs))
+                .selected_if(|data: &AppState, _env| data.workspace.indentation.tabs),
+            )
+            .separator();
+
+        for width in indentation::WIDTHS.iter().copied() {
+            let title = format!("Width: {}", width);
+            menu = menu.entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-indent-width").with_placeholder(title),
+                )
+                .command(print_command::SET_INDENTATION.with(Indentation { width, ..current }))
+                .selected_if(move |data: &AppState, _env| {
+                    data.workspace.indentation.width == width
+                }),
+            );
+        }
+        menu
+    }
+}
+
+impl<W: Widget<Workspace>> Controller<Workspace, W> for IndentationMenu {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut Workspace,
+        env: &Env,
+    ) {
+        if let Event::MouseDown(mouse) = event {
+            ctx.show_context_menu(IndentationMenu::menu(data.indentation), mouse.window_pos);
+            ctx.set_handled();
+            return;
+        }
+        child.event(ctx, event, data, env)
+    }
+}
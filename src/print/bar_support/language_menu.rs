@@ -0,0 +1,42 @@
+use druid::widget::Controller;
+use druid::{Env, Event, EventCtx, LocalizedString, Menu, MenuItem, Widget};
+
+use crate::app_command::print_command;
+use crate::app_state::{AppState, Workspace};
+use crate::support::language;
+
+/// Shows the languages to pick from when the language in the status bar
+/// is clicked.
+pub struct LanguageMenu;
+
+impl LanguageMenu {
+    fn menu() -> Menu<AppState> {
+        let mut menu = Menu::empty();
+        for name in language::LANGUAGES.iter() {
+            menu = menu.entry(
+                MenuItem::new(LocalizedString::new("menu-item-language").with_placeholder(*name))
+                    .command(print_command::SET_LANGUAGE.with(name.to_string()))
+                    .selected_if(move |data: &AppState, _env| data.workspace.language == *name),
+            );
+        }
+        menu
+    }
+}
+
+impl<W: Widget<Workspace>> Controller<Workspace, W> for LanguageMenu {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut Workspace,
+        env: &Env,
+    ) {
+        if let Event::MouseDown(mouse) = event {
+            ctx.show_context_menu(LanguageMenu::menu(), mouse.window_pos);
+            ctx.set_handled();
+            return;
+        }
+        child.event(ctx, event, data, env)
+    }
+}
@@ -1 +1,3 @@
+pub mod indentation_menu;
+pub mod language_menu;
 pub mod text_count;
@@ -24,7 +24,37 @@
 use regex::Regex;
 use unicode_segmentation::UnicodeSegmentation;
 
+/// average silent reading speed used for the reading time estimate.
+pub const WORDS_PER_MINUTE: usize = 200;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TextCount {
+    pub words: usize,
+    pub lines: usize,
+    pub chars: usize,
+    pub blank_lines: usize,
+}
+
+impl TextCount {
+    /// estimated reading time in whole minutes, at least one for any words.
+    pub fn reading_minutes(&self) -> usize {
+        if self.words == 0 {
+            return 0;
+        }
+        (self.words + WORDS_PER_MINUTE - 1) / WORDS_PER_MINUTE
+    }
+}
+
+/// the reading time as shown to users, e.g. "3 min read".
+pub fn reading_time_label(minutes: usize) -> String {
+    format!("{} min read", minutes)
+}
+
 pub fn count(content: &String) -> usize {
+    count_all(content).words
+}
+
+pub fn count_all(content: &String) -> TextCount {
     let mut word_count = 0;
     let mut line_count = 0;
     let mut blank_lines_count = 0;
@@ -45,30 +75,172 @@ pub fn count(content: &String) -> usize {
     let multiple_spaces_re = Regex::new(r"\s+").unwrap();
     // match links and files in grammar "[](...)"
     let link_re = Regex::new(r"]\((.*?)\)").unwrap();
+    // match inline code, "``" spans may contain a single backtick
+    let inline_code_re = Regex::new(r"``.*?``|`[^`]*`").unwrap();
+    // match raw html tags and comments
+    let html_re = Regex::new(r"<!--.*?-->|</?[A-Za-z][^>]*>").unwrap();
+
+    // the opening fence of the code block we are in, an unclosed fence runs
+    // to the end of the document.
+    let mut fence: Option<(char, usize)> = None;
 
     // process document
     for line in content.lines() {
         line_count = line_count + 1;
         let clean_line = String::from(line.trim());
 
+        if let Some((mark, len)) = parse_fence(&clean_line) {
+            match fence {
+                None => fence = Some((mark, len)),
+                // a shorter or different fence is content of the outer block
+                Some((open_mark, open_len)) => {
+                    let is_bare = clean_line.trim_start_matches(mark).trim().is_empty();
+                    if mark == open_mark && len >= open_len && is_bare {
+                        fence = None;
+                    }
+                }
+            }
+            continue;
+        }
+        if fence.is_some() {
+            continue;
+        }
+
         if !clean_line.is_empty() {
+            let clean_line = inline_code_re.replace_all(&clean_line, " ");
+            let clean_line = html_re.replace_all(&clean_line, " ");
             // remove whitespace
             let clean_line = replace_whitespace(&clean_line, "", &whitespace_re);
             let clean_line = multiple_spaces_re.replace_all(&clean_line, " ");
             let clean_line = link_re.replace_all(&clean_line, "]");
 
-            // split words using unicode standards
-            let words: Vec<&str> = clean_line.unicode_words().collect();
-            word_count = word_count + words.len();
+            word_count = word_count + count_words(&clean_line);
         } else {
             blank_lines_count = blank_lines_count + 1;
         }
     }
 
-    word_count
+    TextCount {
+        words: word_count,
+        lines: line_count,
+        chars: content.chars().filter(|c| *c != '\n' && *c != '\r').count(),
+        blank_lines: blank_lines_count,
+    }
+}
+
+// a line opening or closing a code block: three or more "`" or "~"
+fn parse_fence(line: &str) -> Option<(char, usize)> {
+    let mark = line.chars().next()?;
+    if mark != '`' && mark != '~' {
+        return None;
+    }
+
+    let len = line.chars().take_while(|c| *c == mark).count();
+    if len >= 3 {
+        Some((mark, len))
+    } else {
+        None
+    }
+}
+
+// CJK text has no spaces between words, so every ideograph (and kana) counts
+// as one word, the text between them is split using unicode standards.
+// Hangul is left to the unicode split, as Korean separates words by spaces.
+fn count_words(line: &str) -> usize {
+    let mut count = 0;
+    let mut start = 0;
+    for (index, ch) in line.char_indices() {
+        if is_cjk(ch) {
+            count = count + line[start..index].unicode_words().count() + 1;
+            start = index + ch.len_utf8();
+        }
+    }
+
+    count + line[start..].unicode_words().count()
+}
+
+// Han, Hiragana and Katakana script ranges
+fn is_cjk(ch: char) -> bool {
+    match ch as u32 {
+        0x2E80..=0x2FDF // CJK Radicals, Kangxi Radicals
+        | 0x3005..=0x3007 // ideographic iteration / closing mark / number zero
+        | 0x3021..=0x3029 // Hangzhou numerals
+        | 0x3038..=0x303B
+        | 0x3040..=0x309F // Hiragana
+        | 0x30A0..=0x30FF // Katakana
+        | 0x31F0..=0x31FF // Katakana Phonetic Extensions
+        | 0x3400..=0x4DBF // CJK Unified Ideographs Extension A
+        | 0x4E00..=0x9FFF // CJK Unified Ideographs
+        | 0xF900..=0xFAFF // CJK Compatibility Ideographs
+        | 0xFF66..=0xFF9D // Halfwidth Katakana
+        | 0x20000..=0x3134F // CJK Unified Ideographs Extension B - G
+        => true,
+        _ => false,
+    }
 }
 
 // replace whitespace according to regex pattern
 fn replace_whitespace(input: &str, placeholder: &str, re: &Regex) -> String {
     re.replace_all(input, placeholder).into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> usize {
+        count_all(&text.to_string()).words
+    }
+
+    #[test]
+    fn counts_every_han_character() {
+        assert_eq!(words("你好世界"), 4);
+    }
+
+    #[test]
+    fn counts_every_kana() {
+        assert_eq!(words("こんにちは"), 5);
+        assert_eq!(words("カタカナ"), 4);
+    }
+
+    #[test]
+    fn counts_latin_words_between_han() {
+        assert_eq!(words("我爱Rust和Go"), 5);
+        assert_eq!(words("使用 druid 写一个 editor"), 7);
+    }
+
+    #[test]
+    fn splits_hangul_by_spaces() {
+        assert_eq!(words("안녕하세요 세계"), 2);
+    }
+
+    #[test]
+    fn a_longer_fence_keeps_an_inner_block_as_code() {
+        let text = "````\n```\ninner code\n```\n````\nafter words";
+        assert_eq!(words(text), 2);
+    }
+
+    #[test]
+    fn backticks_inside_a_tilde_block_are_code() {
+        let text = "~~~\n```\ninner code\n```\n~~~\nhello";
+        assert_eq!(words(text), 1);
+    }
+
+    #[test]
+    fn an_unclosed_fence_is_code_to_the_end() {
+        let text = "intro text\n```\ncode here\nmore code";
+        assert_eq!(words(text), 2);
+    }
+
+    #[test]
+    fn skips_inline_code() {
+        assert_eq!(words("use `foo bar` here"), 2);
+        assert_eq!(words("``a ` b`` c"), 1);
+    }
+
+    #[test]
+    fn skips_html_tags_and_comments() {
+        assert_eq!(words("<p>hello</p> <!-- a hidden note --> world"), 2);
+        assert_eq!(words("<br/>"), 0);
+    }
+}
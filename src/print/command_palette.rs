@@ -0,0 +1,313 @@
+use std::path::Path;
+
+use druid::piet::{Text, TextLayoutBuilder};
+use druid::widget::prelude::*;
+use druid::widget::TextBox;
+use druid::{
+    commands, Command, FileDialogOptions, FontFamily, KbKey, Point, Rect, Selector, WidgetExt,
+    WidgetPod,
+};
+
+use crate::app_command::print_command;
+use crate::app_state::AppState;
+use crate::components::modal_host::ModalHost;
+use crate::model::file_tree::FileEntry;
+use crate::support::language;
+
+const WIDTH: f64 = 420.0;
+const ROW_HEIGHT: f64 = 24.0;
+const MAX_ROWS: usize = 12;
+
+/// sent to the palette itself once it is added, to focus the input.
+const FOCUS_INPUT: Selector = Selector::new("print.command-palette.focus-input");
+
+/// An action listed in the palette.
+pub struct PaletteItem {
+    pub title: String,
+    pub command: Command,
+}
+
+impl PaletteItem {
+    fn new(title: impl Into<String>, command: impl Into<Command>) -> PaletteItem {
+        PaletteItem {
+            title: title.into(),
+            command: command.into(),
+        }
+    }
+}
+
+/// the actions the palette can run.
+pub fn palette_items() -> Vec<PaletteItem> {
+    let open_file = commands::SHOW_OPEN_PANEL.with(FileDialogOptions::new());
+    let open_folder = commands::SHOW_OPEN_PANEL.with(FileDialogOptions::new().select_directories());
+    let mut items = vec![
+        PaletteItem::new("Open File...", open_file),
+        PaletteItem::new("Open Folder...", open_folder),
+        PaletteItem::new("Save", commands::SAVE_FILE),
+        PaletteItem::new("Save As...", print_command::SAVE_AS),
+        PaletteItem::new("Reopen Closed File", print_command::REOPEN_CLOSED_FILE),
+        PaletteItem::new("Go to Line...", print_command::SHOW_GO_TO_LINE),
+        PaletteItem::new("Find in Files", print_command::SHOW_FIND_IN_FILES),
+        PaletteItem::new("Indent", print_command::INDENT),
+        PaletteItem::new("Outdent", print_command::OUTDENT),
+        PaletteItem::new("Toggle Comment", print_command::TOGGLE_COMMENT),
+        PaletteItem::new("Move Line Up", print_command::MOVE_LINE_UP),
+        PaletteItem::new("Move Line Down", print_command::MOVE_LINE_DOWN),
+        PaletteItem::new("Duplicate Line", print_command::DUPLICATE_LINE),
+        PaletteItem::new(
+            "Select Next Occurrence",
+            print_command::SELECT_NEXT_OCCURRENCE,
+        ),
+        PaletteItem::new("Insert Literal Tab", print_command::INSERT_LITERAL_TAB),
+        PaletteItem::new("Insert Date/Time", print_command::INSERT_DATE_TIME),
+        PaletteItem::new("Reload Directory", print_command::RELOAD_DIR),
+        PaletteItem::new("Reload File", print_command::RELOAD_FILE),
+        PaletteItem::new("Toggle Dark Mode", print_command::TOGGLE_DARK_MODE),
+        PaletteItem::new("Toggle Word Wrap", print_command::TOGGLE_WORD_WRAP),
+        PaletteItem::new("Toggle Zen Mode", print_command::TOGGLE_ZEN_MODE),
+        PaletteItem::new("Toggle Read Only", print_command::TOGGLE_READ_ONLY),
+        PaletteItem::new("Preferences", commands::SHOW_PREFERENCES),
+    ];
+
+    for name in language::LANGUAGES.iter() {
+        let command = print_command::SET_LANGUAGE.with(name.to_string());
+        items.push(PaletteItem::new(format!("Set Language: {}", name), command));
+    }
+    items
+}
+
+/// Score `title` against `query`: every char of the query has to appear in
+/// order, earlier and consecutive matches score better. `None` for no match.
+pub fn fuzzy_score(query: &str, title: &str) -> Option<usize> {
+    let title: Vec<char> = title.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut last_match: Option<usize> = None;
+
+    for ch in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = title[position..].iter().position(|c| *c == ch)? + position;
+        score += found;
+        if let Some(last) = last_match {
+            if found != last + 1 {
+                score += 10;
+            }
+        }
+        last_match = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+/// Like `fuzzy_score`, but a match within the file name ranks above any
+/// match that needs the dirs of `path`.
+pub fn file_score(query: &str, path: &str) -> Option<usize> {
+    let name = Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy());
+    if let Some(score) = name.and_then(|name| fuzzy_score(query, &name)) {
+        return Some(score);
+    }
+    fuzzy_score(query, path).map(|score| score + 1000)
+}
+
+/// every file of the project tree, titled by its path relative to `base`.
+pub fn file_items(entry: &FileEntry, base: &Path) -> Vec<PaletteItem> {
+    let mut items = vec![];
+    collect_files(entry, base, &mut items);
+    items
+}
+
+fn collect_files(entry: &FileEntry, base: &Path, items: &mut Vec<PaletteItem>) {
+    for child in entry.children.iter() {
+        if child.is_dir {
+            collect_files(child, base, items);
+            continue;
+        }
+
+        let path = Path::new(&child.path);
+        let title = path
+            .strip_prefix(base)
+            .unwrap_or(path)
+            .display()
+            .to_string();
+        items.push(PaletteItem::new(
+            title,
+            print_command::SET_FILE.with(child.to_owned()),
+        ));
+    }
+}
+
+/// A modal listing the items matching what is typed into it.
+/// Up/Down move the selection, Enter runs it and Escape dismisses.
+pub struct CommandPalette {
+    input: WidgetPod<AppState, Box<dyn Widget<AppState>>>,
+    items: Vec<PaletteItem>,
+    score: fn(&str, &str) -> Option<usize>,
+    /// indices into `items` that match the input, best first.
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl CommandPalette {
+    /// the palette of `palette_items`.
+    pub fn new() -> CommandPalette {
+        CommandPalette::with_items(palette_items(), "Type a command", fuzzy_score)
+    }
+
+    /// the quick open of the files in the project tree.
+    pub fn go_to_file(entry: &FileEntry, base: &Path) -> CommandPalette {
+        CommandPalette::with_items(file_items(entry, base), "Go to file", file_score)
+    }
+
+    pub fn with_items(
+        items: Vec<PaletteItem>,
+        placeholder: &str,
+        score: fn(&str, &str) -> Option<usize>,
+    ) -> CommandPalette {
+        let input = TextBox::new()
+            .with_placeholder(placeholder.to_string())
+            .fix_width(WIDTH)
+            .lens(AppState::modal_input);
+
+        let matches = (0..items.len()).collect();
+        CommandPalette {
+            input: WidgetPod::new(input.boxed()),
+            items,
+            score,
+            matches,
+            selected: 0,
+        }
+    }
+
+    fn filter(&mut self, query: &str) {
+        let score = self.score;
+        let mut scored: Vec<(usize, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| score(query, &item.title).map(|score| (score, index)))
+            .collect();
+        scored.sort();
+
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.selected = 0;
+    }
+
+    fn run_selected(&self, ctx: &mut EventCtx) {
+        if let Some(index) = self.matches.get(self.selected) {
+            ctx.submit_command(ModalHost::DISMISS_MODAL);
+            ctx.submit_command(self.items[*index].command.clone());
+        }
+    }
+}
+
+impl Widget<AppState> for CommandPalette {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        match event {
+            Event::Command(cmd) if cmd.is(FOCUS_INPUT) => {
+                ctx.set_focus(self.input.id());
+                ctx.set_handled();
+                return;
+            }
+            Event::KeyDown(key) => match &key.key {
+                KbKey::ArrowDown => {
+                    if self.selected + 1 < self.matches.len().min(MAX_ROWS) {
+                        self.selected += 1;
+                    }
+                    ctx.request_paint();
+                    ctx.set_handled();
+                    return;
+                }
+                KbKey::ArrowUp => {
+                    self.selected = self.selected.saturating_sub(1);
+                    ctx.request_paint();
+                    ctx.set_handled();
+                    return;
+                }
+                KbKey::Enter => {
+                    self.run_selected(ctx);
+                    ctx.set_handled();
+                    return;
+                }
+                KbKey::Escape => {
+                    ctx.submit_command(ModalHost::DISMISS_MODAL);
+                    ctx.set_handled();
+                    return;
+                }
+                _ => {}
+            },
+            Event::MouseDown(mouse) => {
+                let row = (mouse.pos.y - self.input.layout_rect().height()) / ROW_HEIGHT;
+                if row >= 0.0 && (row as usize) < self.matches.len().min(MAX_ROWS) {
+                    self.selected = row as usize;
+                    self.run_selected(ctx);
+                    ctx.set_handled();
+                    return;
+                }
+            }
+            _ => {}
+        }
+
+        self.input.event(ctx, event, data, env);
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.filter(&data.modal_input);
+            ctx.submit_command(FOCUS_INPUT.to(ctx.widget_id()));
+        }
+        self.input.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        if old_data.modal_input != data.modal_input {
+            self.filter(&data.modal_input);
+            ctx.request_layout();
+        }
+        self.input.update(ctx, data, env)
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &AppState,
+        env: &Env,
+    ) -> Size {
+        let input_size = self.input.layout(ctx, &bc.loosen(), data, env);
+        self.input.set_origin(ctx, data, env, Point::ORIGIN);
+
+        let rows = self.matches.len().min(MAX_ROWS) as f64;
+        bc.constrain(Size::new(WIDTH, input_size.height + rows * ROW_HEIGHT))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, env: &Env) {
+        let rect = ctx.size().to_rect();
+        ctx.fill(rect, &env.get(crate::theme::TOOL_WINDOW_COLOR));
+        self.input.paint(ctx, data, env);
+
+        let top = self.input.layout_rect().height();
+        let text_color = env.get(crate::theme::BASIC_TEXT_COLOR);
+        for (row, index) in self.matches.iter().take(MAX_ROWS).enumerate() {
+            let y = top + row as f64 * ROW_HEIGHT;
+            if row == self.selected {
+                let highlight = Rect::new(0.0, y, WIDTH, y + ROW_HEIGHT);
+                ctx.fill(highlight, &env.get(crate::theme::TREE_SELECTED_COLOR));
+            }
+
+            let layout = ctx
+                .text()
+                .new_text_layout(self.items[*index].title.clone())
+                .font(
+                    FontFamily::SYSTEM_UI,
+                    env.get(crate::theme::BASIC_TEXT_SIZE),
+                )
+                .text_color(text_color.clone())
+                .build();
+            if let Ok(layout) = layout {
+                ctx.draw_text(&layout, (8.0, y + 4.0));
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use druid::widget::{Checkbox, Controller, Flex, Label, LineBreaking, List, Scroll, TextBox};
+use druid::{Env, Event, EventCtx, ExtEventSink, KbKey, Target, Widget, WidgetExt};
+use regex::{Regex, RegexBuilder};
+
+use crate::app_command::print_command;
+use crate::app_state::AppState;
+use crate::components::icon_button::IconButton;
+use crate::model::file_tree::FileEntry;
+use crate::model::search::{SearchFileResult, SearchMatch, SearchState};
+use crate::support::encoding;
+
+const PANEL_HEIGHT: f64 = 220.0;
+/// files larger than this are skipped, they are rarely what is looked for.
+const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+/// the search stops once this many lines matched.
+const MAX_MATCHES: usize = 2000;
+/// how much of a matching line is shown.
+const PREVIEW_CHARS: usize = 120;
+
+/// Turn the query and toggles of `state` into a regex.
+pub fn build_regex(state: &SearchState) -> Result<Regex, String> {
+    let pattern = if state.regex {
+        state.query.clone()
+    } else {
+        regex::escape(&state.query)
+    };
+    let pattern = if state.whole_word {
+        format!(r"\b(?:{})\b", pattern)
+    } else {
+        pattern
+    };
+
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!state.case_sensitive)
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+/// Search the files of `dir` for `regex` on a background thread. Each file
+/// with matches comes back as `SEARCH_RESULT`, then `SEARCH_DONE`. The
+/// search stops early once `current` moved on to another search `id`.
+pub fn search(id: u64, current: Arc<AtomicU64>, dir: Arc<Path>, regex: Regex, sink: ExtEventSink) {
+    thread::spawn(move || {
+        let entry = FileEntry::from_dir("".to_string(), &dir);
+        let mut found = 0;
+        for path in entry.file_paths() {
+            if current.load(Ordering::SeqCst) != id || found >= MAX_MATCHES {
+                break;
+            }
+
+            let matches = search_file(&path, &regex, MAX_MATCHES - found);
+            if matches.is_empty() {
+                continue;
+            }
+            found += matches.len();
+
+            let title = path
+                .strip_prefix(&dir)
+                .unwrap_or(&path)
+                .display()
+                .to_string();
+            let result = SearchFileResult {
+                title,
+                matches: Arc::new(matches),
+            };
+            if let Err(err) =
+                sink.submit_command(print_command::SEARCH_RESULT, (id, result), Target::Auto)
+            {
+                log::error!("submit search result error: {:?}", err);
+            }
+        }
+
+        if let Err(err) = sink.submit_command(print_command::SEARCH_DONE, id, Target::Auto) {
+            log::error!("submit search done error: {:?}", err);
+        }
+    });
+}
+
+fn search_file(path: &Path, regex: &Regex, limit: usize) -> Vec<SearchMatch> {
+    let too_large = fs::metadata(path).map_or(true, |meta| meta.len() > MAX_FILE_SIZE);
+    if too_large {
+        return vec![];
+    }
+    let content = match fs::read(path) {
+        Ok(content) if !encoding::is_binary(&content) => content,
+        _ => return vec![],
+    };
+
+    let text = encoding::decode(&content).text;
+    let path: Arc<Path> = path.into();
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| regex.is_match(line))
+        .take(limit)
+        .map(|(index, line)| SearchMatch {
+            path: path.clone(),
+            line: index + 1,
+            preview: line.trim().chars().take(PREVIEW_CHARS).collect(),
+        })
+        .collect()
+}
+
+/// The query box, its toggles, and the results grouped by file.
+pub fn find_in_files_panel() -> impl Widget<AppState> {
+    let query = TextBox::new()
+        .with_placeholder("Find in files")
+        .lens(SearchState::query)
+        .controller(SearchOnEnter)
+        .expand_width();
+
+    let search = IconButton::from_label(
+        Label::new("Search").with_text_color(crate::theme::BASIC_TEXT_COLOR),
+    )
+    .disabled_if(|data: &SearchState, _env| data.query.is_empty())
+    .on_click(|ctx, _data, _env| ctx.submit_command(print_command::FIND_IN_FILES));
+
+    let toggles = Flex::row()
+        .with_flex_child(query, 1.0)
+        .with_default_spacer()
+        .with_child(Checkbox::new("Match case").lens(SearchState::case_sensitive))
+        .with_default_spacer()
+        .with_child(Checkbox::new("Whole word").lens(SearchState::whole_word))
+        .with_default_spacer()
+        .with_child(Checkbox::new("Regex").lens(SearchState::regex))
+        .with_default_spacer()
+        .with_child(search);
+
+    let status = Label::new(|data: &SearchState, _env: &Env| match &data.error {
+        Some(error) => error.to_string(),
+        None if data.searching => format!("Searching... {} matches", data.match_count()),
+        None => format!(
+            "{} matches in {} files",
+            data.match_count(),
+            data.results.len()
+        ),
+    })
+    .with_text_color(crate::theme::BASIC_TEXT_COLOR);
+
+    let results = Scroll::new(List::new(file_result).lens(SearchState::results))
+        .vertical()
+        .expand_width();
+
+    Flex::column()
+        .with_child(toggles)
+        .with_default_spacer()
+        .with_child(status)
+        .with_flex_child(results, 1.0)
+        .padding(5.0)
+        .fix_height(PANEL_HEIGHT)
+        .expand_width()
+        .lens(AppState::search)
+        .background(crate::theme::TOOL_WINDOW_COLOR)
+}
+
+fn file_result() -> impl Widget<SearchFileResult> {
+    let title = Label::new(|data: &SearchFileResult, _env: &Env| {
+        format!("{} ({})", data.title, data.matches.len())
+    })
+    .with_text_color(crate::theme::BASIC_TEXT_COLOR);
+
+    let matches = List::new(|| {
+        Label::new(|data: &SearchMatch, _env: &Env| format!("{}: {}", data.line, data.preview))
+            .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+            .with_line_break_mode(LineBreaking::Clip)
+            .padding((20.0, 2.0, 0.0, 2.0))
+            .expand_width()
+            .on_click(|ctx, data: &mut SearchMatch, _env| {
+                let target = (data.path.clone(), data.line);
+                ctx.submit_command(print_command::OPEN_SEARCH_RESULT.with(target));
+            })
+    })
+    .lens(SearchFileResult::matches);
+
+    Flex::column()
+        .cross_axis_alignment(druid::widget::CrossAxisAlignment::Start)
+        .with_child(title)
+        .with_child(matches)
+}
+
+/// Starts the search when Enter is pressed in the query box.
+struct SearchOnEnter;
+
+impl<W: Widget<SearchState>> Controller<SearchState, W> for SearchOnEnter {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut SearchState,
+        env: &Env,
+    ) {
+        if let Event::KeyDown(key) = event {
+            if key.key == KbKey::Enter {
+                ctx.submit_command(print_command::FIND_IN_FILES);
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
@@ -0,0 +1,62 @@
+use druid::piet::Text;
+use druid::widget::Controller;
+use druid::{Env, Event, EventCtx, Selector, UpdateCtx, Widget};
+
+use crate::app_state::AppState;
+
+/// sent to ourselves, the font can only be looked up from an event.
+const CHECK_FONT: Selector = Selector::new("print.check-font");
+
+/// Looks up `Params::font_family` in the installed fonts whenever it changes,
+/// and sets `AppState::missing_font` so that the editor falls back to a
+/// monospace font instead of whatever the platform picks.
+pub struct FontCheck;
+
+impl FontCheck {
+    fn check(ctx: &mut EventCtx, data: &mut AppState) {
+        let missing = ctx.text().font_family(&data.params.font_family).is_none();
+        if missing && !data.missing_font {
+            log::warn!(
+                "font {:?} is not installed, using monospace",
+                data.params.font_family
+            );
+        }
+        data.missing_font = missing;
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for FontCheck {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::WindowConnected => FontCheck::check(ctx, data),
+            Event::Command(cmd) if cmd.is(CHECK_FONT) => {
+                FontCheck::check(ctx, data);
+                ctx.set_handled();
+                return;
+            }
+            _ => {}
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if old_data.params.font_family != data.params.font_family {
+            ctx.submit_command(CHECK_FONT.to(ctx.widget_id()));
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use druid::widget::Controller;
+use druid::{Data, Env, Event, EventCtx, TimerToken, UpdateCtx, Widget};
+
+use crate::app_command::print_command;
+use crate::app_state::AppState;
+
+/// how long typing has to pause before the buffer is diffed again.
+const DIFF_DEBOUNCE_MILLIS: u64 = 500;
+
+/// Sends `DIFF_BUFFER` once the buffer was left alone for a moment after an
+/// edit, or after another file was opened.
+pub struct GitDiff {
+    timer: TimerToken,
+}
+
+impl GitDiff {
+    pub fn new() -> GitDiff {
+        GitDiff {
+            timer: TimerToken::INVALID,
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for GitDiff {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::Timer(token) = event {
+            if *token == self.timer {
+                self.timer = TimerToken::INVALID;
+                ctx.submit_command(print_command::DIFF_BUFFER);
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if old_data.workspace.input_text != data.workspace.input_text
+            || !old_data.current_file.same(&data.current_file)
+        {
+            self.timer = ctx.request_timer(Duration::from_millis(DIFF_DEBOUNCE_MILLIS));
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use druid::text::{AttributesAdder, RichText, RichTextBuilder};
+use druid::widget::prelude::*;
+use druid::widget::{LineBreaking, RawLabel, Scroll};
+use druid::{FontFamily, FontStyle, FontWeight, TimerToken, WidgetExt, WidgetPod};
+use pulldown_cmark::{Event as ParseEvent, Parser, Tag};
+
+use crate::app_state::Workspace;
+
+/// how long typing has to pause before the preview is rendered again.
+const RENDER_DEBOUNCE_MILLIS: u64 = 300;
+
+/// Renders the markdown of `Workspace::input_text` as rich text.
+pub struct MarkdownPreview {
+    label: WidgetPod<RichText, Box<dyn Widget<RichText>>>,
+    rendered: RichText,
+    timer: TimerToken,
+}
+
+impl MarkdownPreview {
+    pub fn new() -> MarkdownPreview {
+        let label = RawLabel::new()
+            .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .padding(10.0);
+
+        MarkdownPreview {
+            label: WidgetPod::new(Scroll::new(label).vertical().boxed()),
+            rendered: render_markdown(""),
+            timer: TimerToken::INVALID,
+        }
+    }
+}
+
+impl Widget<Workspace> for MarkdownPreview {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Workspace, env: &Env) {
+        if let Event::Timer(token) = event {
+            if *token == self.timer {
+                // hand the text over through the data, so `update` re-renders
+                data.preview_text = Arc::new(data.input_text.clone());
+                return;
+            }
+        }
+        self.label.event(ctx, event, &mut self.rendered, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &Workspace,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.rendered = render_markdown(&data.input_text);
+        }
+        self.label.lifecycle(ctx, event, &self.rendered, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &Workspace, data: &Workspace, env: &Env) {
+        if old_data.input_text != data.input_text {
+            self.timer = ctx.request_timer(Duration::from_millis(RENDER_DEBOUNCE_MILLIS));
+        }
+        if !old_data.preview_text.same(&data.preview_text) {
+            self.rendered = render_markdown(&data.preview_text);
+        }
+        self.label.update(ctx, &self.rendered, env)
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &Workspace,
+        env: &Env,
+    ) -> Size {
+        let size = self.label.layout(ctx, bc, &self.rendered, env);
+        self.label
+            .set_origin(ctx, &self.rendered, env, Point::ORIGIN);
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &Workspace, env: &Env) {
+        self.label.paint(ctx, &self.rendered, env)
+    }
+}
+
+/// Turn markdown into rich text: headings, emphasis, lists, quotes, code
+/// and links get their own styling.
+pub fn render_markdown(text: &str) -> RichText {
+    let mut current_pos = 0;
+    let mut builder = RichTextBuilder::new();
+    let mut tag_stack = Vec::new();
+
+    for event in Parser::new(text) {
+        match event {
+            ParseEvent::Start(tag) => {
+                if let Tag::Item = tag {
+                    builder.push("• ");
+                    current_pos += "• ".len();
+                }
+                tag_stack.push((current_pos, tag));
+            }
+            ParseEvent::Text(text) => {
+                builder.push(&text);
+                current_pos += text.len();
+            }
+            ParseEvent::End(_) => {
+                let (start, tag) = tag_stack
+                    .pop()
+                    .expect("parser does not return unbalanced tags");
+                add_attributes_for_tag(&tag, builder.add_attributes_for_range(start..current_pos));
+                if let Some(gap) = gap_after_tag(&tag) {
+                    builder.push(gap);
+                    current_pos += gap.len();
+                }
+            }
+            ParseEvent::Code(text) => {
+                builder.push(&text).font_family(FontFamily::MONOSPACE);
+                current_pos += text.len();
+            }
+            ParseEvent::Html(text) => {
+                builder.push(&text).font_family(FontFamily::MONOSPACE);
+                current_pos += text.len();
+            }
+            ParseEvent::SoftBreak => {
+                builder.push(" ");
+                current_pos += 1;
+            }
+            ParseEvent::HardBreak => {
+                builder.push("\n");
+                current_pos += 1;
+            }
+            _ => {}
+        }
+    }
+
+    builder.build()
+}
+
+// what separates a block from the next one, inline tags need nothing
+fn gap_after_tag(tag: &Tag) -> Option<&'static str> {
+    match tag {
+        Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..) => None,
+        Tag::Item | Tag::List(_) => Some("\n"),
+        _ => Some("\n\n"),
+    }
+}
+
+fn add_attributes_for_tag(tag: &Tag, mut attrs: AttributesAdder) {
+    match tag {
+        Tag::Heading(level) => {
+            let size = match level {
+                1 => 30.,
+                2 => 25.,
+                3 => 21.,
+                4 => 18.,
+                _ => 16.,
+            };
+            attrs.size(size).weight(FontWeight::BOLD);
+        }
+        Tag::BlockQuote => {
+            attrs.style(FontStyle::Italic);
+        }
+        Tag::CodeBlock(_) => {
+            attrs.font_family(FontFamily::MONOSPACE);
+        }
+        Tag::Emphasis => {
+            attrs.style(FontStyle::Italic);
+        }
+        Tag::Strong => {
+            attrs.weight(FontWeight::BOLD);
+        }
+        Tag::Link(..) => {
+            attrs.underline(true);
+        }
+        _ => {}
+    }
+}
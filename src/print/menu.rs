@@ -1,31 +1,485 @@
+use crate::app_command::print_command;
 use crate::app_state::AppState;
+use crate::support::encoding;
+use crate::support::keymap::Keymap;
 use druid::{
     commands, platform_menus, Data, Env, FileDialogOptions, LocalizedString, Menu, MenuItem,
-    SysMods, WindowId,
+    WindowId,
 };
 
 #[allow(unused_assignments)]
-pub fn make_menu(_: Option<WindowId>, _state: &AppState, _: &Env) -> Menu<AppState> {
-    let mut menu = Menu::empty();
+pub fn make_menu(_: Option<WindowId>, state: &AppState, _: &Env) -> Menu<AppState> {
+    let mut menu = Menu::empty().rebuild_on(|old, data, _env| {
+        !old.recent.same(&data.recent)
+            || !old.recent_dirs.same(&data.recent_dirs)
+            || old.last_dir != data.last_dir
+            || !old.keymap.same(&data.keymap)
+            || !old.params.snippets.same(&data.params.snippets)
+    });
     #[cfg(target_os = "macos")]
     {
-        menu = menu.entry(platform_menus::mac::application::default());
+        menu = menu.entry(application_menu());
     }
 
-    menu.entry(file_menu())
+    let keymap = Keymap::load(&state.keymap);
+    menu = menu
+        .entry(file_menu(state, &keymap))
+        .entry(edit_menu(state, &keymap))
+        .entry(view_menu(&keymap));
+    // the macOS app menu already has About
+    #[cfg(not(target_os = "macos"))]
+    {
+        menu = menu.entry(help_menu());
+    }
+    menu
 }
 
-fn file_menu<T: Data>() -> Menu<T> {
-    let open_file = commands::SHOW_OPEN_PANEL.with(FileDialogOptions::new().select_directories());
-    Menu::new(LocalizedString::new("common-menu-file-menu"))
-        .entry(platform_menus::mac::file::new_file())
+/// druid's default app menu, but Quit asks about unsaved changes first.
+#[cfg(target_os = "macos")]
+fn application_menu() -> Menu<AppState> {
+    use platform_menus::mac::application;
+    Menu::new(LocalizedString::new("macos-menu-application-menu"))
+        .entry(application::about())
+        .separator()
+        .entry(application::preferences())
+        .separator()
+        .entry(application::hide())
+        .entry(application::hide_others())
+        .entry(application::show_all())
+        .separator()
+        .entry(
+            MenuItem::new(LocalizedString::new("macos-menu-quit-app"))
+                .command(print_command::QUIT)
+                .hotkey(druid::SysMods::Cmd, "q"),
+        )
+}
+
+#[cfg(not(target_os = "macos"))]
+fn help_menu() -> Menu<AppState> {
+    Menu::new(LocalizedString::new("common-menu-help-menu").with_placeholder("Help"))
+        .entry(platform_menus::mac::application::about())
+}
+
+trait Bound {
+    /// Give the item the chord `keymap` has for `action`, if any.
+    fn bound(self, keymap: &Keymap, action: &str) -> Self;
+}
+
+impl Bound for MenuItem<AppState> {
+    fn bound(self, keymap: &Keymap, action: &str) -> Self {
+        match keymap.chord(action) {
+            Some(chord) => self.hotkey(chord.mods, chord.key.clone()),
+            None => self,
+        }
+    }
+}
+
+fn edit_menu(state: &AppState, keymap: &Keymap) -> Menu<AppState> {
+    let mut snippets = Menu::new(
+        LocalizedString::new("menu-item-insert-snippet").with_placeholder("Insert Snippet"),
+    );
+    for name in state.params.snippets.keys() {
+        snippets = snippets.entry(
+            MenuItem::new(LocalizedString::new("menu-item-snippet").with_placeholder(name.clone()))
+                .command(print_command::INSERT_SNIPPET.with(name.clone())),
+        );
+    }
+    if state.params.snippets.is_empty() {
+        snippets = snippets.entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-no-snippets").with_placeholder("No Snippets"),
+            )
+            .enabled(false),
+        );
+    }
+
+    Menu::new(LocalizedString::new("common-menu-edit-menu").with_placeholder("Edit"))
+        .entry(
+            MenuItem::new(LocalizedString::new("menu-item-indent").with_placeholder("Indent"))
+                .command(print_command::INDENT)
+                .bound(keymap, "indent"),
+        )
+        .entry(
+            MenuItem::new(LocalizedString::new("menu-item-outdent").with_placeholder("Outdent"))
+                .command(print_command::OUTDENT)
+                .bound(keymap, "outdent"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-toggle-comment").with_placeholder("Toggle Comment"),
+            )
+            .command(print_command::TOGGLE_COMMENT)
+            .bound(keymap, "toggle_comment"),
+        )
+        .separator()
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-find-in-files").with_placeholder("Find in Files"),
+            )
+            .command(print_command::SHOW_FIND_IN_FILES)
+            .bound(keymap, "find_in_files"),
+        )
+        .separator()
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-move-line-up").with_placeholder("Move Line Up"),
+            )
+            .command(print_command::MOVE_LINE_UP)
+            .bound(keymap, "move_line_up"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-move-line-down").with_placeholder("Move Line Down"),
+            )
+            .command(print_command::MOVE_LINE_DOWN)
+            .bound(keymap, "move_line_down"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-duplicate-line").with_placeholder("Duplicate Line"),
+            )
+            .command(print_command::DUPLICATE_LINE)
+            .bound(keymap, "duplicate_line"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-select-next-occurrence")
+                    .with_placeholder("Select Next Occurrence"),
+            )
+            .command(print_command::SELECT_NEXT_OCCURRENCE)
+            .bound(keymap, "select_next_occurrence"),
+        )
+        .separator()
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-insert-literal-tab")
+                    .with_placeholder("Insert Literal Tab"),
+            )
+            .command(print_command::INSERT_LITERAL_TAB)
+            .bound(keymap, "insert_literal_tab"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-insert-date-time")
+                    .with_placeholder("Insert Date/Time"),
+            )
+            .command(print_command::INSERT_DATE_TIME),
+        )
+        .entry(snippets)
+}
+
+fn view_menu(keymap: &Keymap) -> Menu<AppState> {
+    Menu::new(LocalizedString::new("common-menu-view-menu").with_placeholder("View"))
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-command-palette")
+                    .with_placeholder("Command Palette"),
+            )
+            .command(print_command::SHOW_COMMAND_PALETTE)
+            .bound(keymap, "command_palette"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-reveal-in-tree")
+                    .with_placeholder("Reveal in File Tree"),
+            )
+            .command(print_command::REVEAL_FILE),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-auto-reveal")
+                    .with_placeholder("Reveal Opened Files"),
+            )
+            .on_activate(|_ctx, data: &mut AppState, _env| {
+                data.params.auto_reveal = !data.params.auto_reveal;
+                data.save_global_config();
+            })
+            .selected_if(|data: &AppState, _env| data.params.auto_reveal),
+        )
+        .separator()
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-split")
+                    .with_placeholder("Split View of Current File"),
+            )
+            .on_activate(|_ctx, data: &mut AppState, _env| data.split = !data.split)
+            .selected_if(|data: &AppState, _env| data.split),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-markdown-preview")
+                    .with_placeholder("Markdown Preview"),
+            )
+            .on_activate(|_ctx, data: &mut AppState, _env| data.preview = !data.preview)
+            .selected_if(|data: &AppState, _env| data.preview),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-dark-mode").with_placeholder("Dark Mode"),
+            )
+            .command(print_command::TOGGLE_DARK_MODE)
+            .selected_if(|data: &AppState, _env| data.dark_mode()),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-follow-system-theme")
+                    .with_placeholder("Follow System Appearance"),
+            )
+            .on_activate(|_ctx, data: &mut AppState, _env| {
+                data.params.follow_system_theme = !data.params.follow_system_theme;
+                data.save_global_config();
+            })
+            .selected_if(|data: &AppState, _env| data.params.follow_system_theme),
+        )
         .entry(
-            MenuItem::new(LocalizedString::new("common-menu-file-open"))
-                .command(open_file)
-                .hotkey(SysMods::Cmd, "o"),
+            MenuItem::new(LocalizedString::new("menu-item-zen-mode").with_placeholder("Zen Mode"))
+                .command(print_command::TOGGLE_ZEN_MODE)
+                .selected_if(|data: &AppState, _env| data.params.zen_mode)
+                .bound(keymap, "zen_mode"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-typewriter-scrolling")
+                    .with_placeholder("Typewriter Scrolling"),
+            )
+            .on_activate(|_ctx, data: &mut AppState, _env| {
+                data.params.typewriter_scrolling = !data.params.typewriter_scrolling;
+                data.save_global_config();
+            })
+            .selected_if(|data: &AppState, _env| data.params.typewriter_scrolling),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-smooth-scrolling")
+                    .with_placeholder("Smooth Scrolling"),
+            )
+            .on_activate(|_ctx, data: &mut AppState, _env| {
+                data.params.smooth_scrolling = !data.params.smooth_scrolling;
+                data.save_global_config();
+            })
+            .selected_if(|data: &AppState, _env| data.params.smooth_scrolling),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-word-wrap").with_placeholder("Word Wrap"),
+            )
+            .command(print_command::TOGGLE_WORD_WRAP)
+            .selected_if(|data: &AppState, _env| data.word_wrap()),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-output").with_placeholder("Output Panel"),
+            )
+            .command(print_command::TOGGLE_OUTPUT)
+            .selected_if(|data: &AppState, _env| data.params.show_output),
+        )
+        .entry(
+            MenuItem::new(LocalizedString::new("menu-item-minimap").with_placeholder("Minimap"))
+                .on_activate(|_ctx, data: &mut AppState, _env| {
+                    data.params.minimap = !data.params.minimap;
+                    data.save_global_config();
+                })
+                .selected_if(|data: &AppState, _env| data.params.minimap),
+        )
+        .entry(
+            MenuItem::new(LocalizedString::new("menu-item-outline").with_placeholder("Outline"))
+                .on_activate(|_ctx, data: &mut AppState, _env| {
+                    data.params.show_outline = !data.params.show_outline;
+                    data.save_global_config();
+                })
+                .selected_if(|data: &AppState, _env| data.params.show_outline),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-show-whitespace")
+                    .with_placeholder("Show Whitespace"),
+            )
+            .on_activate(|_ctx, data: &mut AppState, _env| {
+                data.params.show_whitespace = !data.params.show_whitespace;
+                data.save_global_config();
+            })
+            .selected_if(|data: &AppState, _env| data.params.show_whitespace),
+        )
+        .separator()
+        .entry(
+            MenuItem::new(LocalizedString::new("menu-item-zoom-in").with_placeholder("Zoom In"))
+                .on_activate(|_ctx, data: &mut AppState, _env| data.zoom(1.0))
+                .bound(keymap, "zoom_in"),
+        )
+        .entry(
+            MenuItem::new(LocalizedString::new("menu-item-zoom-out").with_placeholder("Zoom Out"))
+                .on_activate(|_ctx, data: &mut AppState, _env| data.zoom(-1.0))
+                .bound(keymap, "zoom_out"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-reset-zoom").with_placeholder("Reset Zoom"),
+            )
+            .on_activate(|_ctx, data: &mut AppState, _env| data.reset_zoom())
+            .bound(keymap, "reset_zoom"),
         )
-        .entry(platform_menus::mac::file::save())
         .separator()
-        .entry(platform_menus::mac::file::close())
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-auto-save").with_placeholder("Auto Save"),
+            )
+            .on_activate(|_ctx, data: &mut AppState, _env| {
+                data.params.auto_save = !data.params.auto_save;
+                data.save_global_config();
+            })
+            .selected_if(|data: &AppState, _env| data.params.auto_save),
+        )
+}
+
+fn file_menu(state: &AppState, keymap: &Keymap) -> Menu<AppState> {
+    let open_file = commands::SHOW_OPEN_PANEL.with(FileDialogOptions::new());
+    let open_folder = commands::SHOW_OPEN_PANEL.with(FileDialogOptions::new().select_directories());
+    let menu = Menu::new(LocalizedString::new("common-menu-file-menu"))
+        .entry(platform_menus::mac::file::new_file())
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-new-window").with_placeholder("New Window"),
+            )
+            .command(print_command::NEW_WINDOW)
+            .bound(keymap, "new_window"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-open-file").with_placeholder("Open File..."),
+            )
+            .command(open_file)
+            .bound(keymap, "open_file"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-open-folder").with_placeholder("Open Folder..."),
+            )
+            .command(open_folder)
+            .bound(keymap, "open_folder"),
+        )
+        .entry(recent_menu(state))
+        .entry(recent_folders_menu(state))
+        .entry(reopen_previous_folder(state))
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-close-folder").with_placeholder("Close Folder"),
+            )
+            .command(print_command::CLOSE_FOLDER)
+            .enabled_if(|data: &AppState, _env| data.current_dir.is_some()),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-go-to-file").with_placeholder("Go to File"),
+            )
+            .command(print_command::SHOW_GO_TO_FILE)
+            .bound(keymap, "go_to_file"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-go-to-line").with_placeholder("Go to Line"),
+            )
+            .command(print_command::SHOW_GO_TO_LINE)
+            .bound(keymap, "go_to_line"),
+        )
+        .entry(
+            MenuItem::new(LocalizedString::new("common-menu-file-save"))
+                .command(commands::SAVE_FILE)
+                .enabled_if(|data: &AppState, _env| !data.workspace.read_only)
+                .bound(keymap, "save"),
+        )
+        .entry(
+            MenuItem::new(LocalizedString::new("common-menu-file-save-as"))
+                .command(print_command::SAVE_AS)
+                .bound(keymap, "save_as"),
+        )
+        .entry(
+            MenuItem::new(
+                LocalizedString::new("menu-item-read-only").with_placeholder("Read Only"),
+            )
+            .command(print_command::TOGGLE_READ_ONLY)
+            .selected_if(|data: &AppState, _env| data.workspace.read_only),
+        )
+        .entry(encoding_menu())
+        .separator();
+    // the macOS app menu already has Preferences
+    #[cfg(not(target_os = "macos"))]
+    let menu = menu.entry(preferences()).separator();
+    menu.entry(
+        MenuItem::new(LocalizedString::new("common-menu-file-close"))
+            .command(commands::CLOSE_WINDOW)
+            .bound(keymap, "close"),
+    )
+    .entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-reopen-closed").with_placeholder("Reopen Closed File"),
+        )
+        .command(print_command::REOPEN_CLOSED_FILE)
+        .enabled_if(|data: &AppState, _env| !data.closed.is_empty())
+        .bound(keymap, "reopen_closed"),
+    )
 }
 
+#[cfg(not(target_os = "macos"))]
+fn preferences() -> MenuItem<AppState> {
+    platform_menus::mac::application::preferences()
+}
+
+fn recent_menu(state: &AppState) -> Menu<AppState> {
+    let mut menu =
+        Menu::new(LocalizedString::new("menu-item-open-recent").with_placeholder("Open Recent"));
+    for path in state.recent.iter().filter(|path| path.exists()) {
+        let title = path.display().to_string();
+        menu = menu.entry(
+            MenuItem::new(LocalizedString::new("menu-item-recent").with_placeholder(title))
+                .command(print_command::OPEN_RECENT.with(path.clone())),
+        );
+    }
+
+    menu.separator().entry(
+        MenuItem::new(
+            LocalizedString::new("menu-item-clear-recent").with_placeholder("Clear Recent"),
+        )
+        .on_activate(|_ctx, data: &mut AppState, _env| data.clear_recent()),
+    )
+}
+
+/// the recent list, narrowed to the project dirs in it.
+fn recent_folders_menu(state: &AppState) -> Menu<AppState> {
+    let mut menu = Menu::new(
+        LocalizedString::new("menu-item-open-recent-folder").with_placeholder("Open Recent Folder"),
+    );
+    for item in state.recent_dirs.iter().filter(|item| item.path.is_dir()) {
+        let title = item.path.display().to_string();
+        menu = menu.entry(
+            MenuItem::new(LocalizedString::new("menu-item-recent-folder").with_placeholder(title))
+                .command(print_command::OPEN_RECENT.with(item.path.clone())),
+        );
+    }
+    menu
+}
+
+fn reopen_previous_folder(state: &AppState) -> MenuItem<AppState> {
+    let item = MenuItem::new(
+        LocalizedString::new("menu-item-reopen-previous-folder")
+            .with_placeholder("Reopen Previous Folder"),
+    );
+    match state.last_dir.as_ref().filter(|dir| dir.is_dir()) {
+        Some(dir) => item.command(print_command::OPEN_RECENT.with(dir.clone())),
+        None => item.enabled(false),
+    }
+}
+
+fn encoding_menu() -> Menu<AppState> {
+    let mut menu = Menu::new(
+        LocalizedString::new("menu-item-reopen-with-encoding")
+            .with_placeholder("Reopen with Encoding"),
+    );
+    for label in encoding::MENU_ENCODINGS.iter() {
+        menu = menu.entry(
+            MenuItem::new(LocalizedString::new("menu-item-encoding").with_placeholder(*label))
+                .command(print_command::REOPEN_WITH_ENCODING.with(label.to_string()))
+                .selected_if(move |data: &AppState, _env| data.workspace.encoding == *label),
+        );
+    }
+    menu
+}
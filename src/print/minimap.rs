@@ -0,0 +1,108 @@
+use druid::widget::prelude::*;
+use druid::{Rect, Size};
+
+use crate::app_state::Workspace;
+use crate::conan_text::custom_input_component::CustomTextComponent;
+
+const WIDTH: f64 = 80.0;
+/// the height of a line while the whole text fits.
+const LINE_HEIGHT: f64 = 2.0;
+/// how wide a character is drawn.
+const CHAR_WIDTH: f64 = 1.0;
+
+/// A scaled-down overview of the text next to the editor, a block per
+/// line, with the visible part marked. Clicking or dragging scrolls there.
+pub struct Minimap;
+
+impl Minimap {
+    pub fn new() -> Minimap {
+        Minimap
+    }
+
+    // lines shrink once the text no longer fits the height
+    fn line_height(height: f64, lines: usize) -> f64 {
+        LINE_HEIGHT.min(height / lines.max(1) as f64)
+    }
+
+    fn scroll_to(ctx: &mut EventCtx, y: f64, data: &Workspace) {
+        let lines = data.input_text.lines().count().max(1);
+        let line_height = Minimap::line_height(ctx.size().height, lines);
+        let fraction = y / (line_height * lines as f64);
+        ctx.submit_command(CustomTextComponent::SCROLL_TO_FRACTION.with(fraction));
+    }
+}
+
+impl Widget<Workspace> for Minimap {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Workspace, _env: &Env) {
+        match event {
+            Event::MouseDown(mouse) => {
+                ctx.set_active(true);
+                Minimap::scroll_to(ctx, mouse.pos.y, data);
+                ctx.set_handled();
+            }
+            Event::MouseMove(mouse) if ctx.is_active() => {
+                Minimap::scroll_to(ctx, mouse.pos.y, data);
+                ctx.set_handled();
+            }
+            Event::MouseUp(_) if ctx.is_active() => {
+                ctx.set_active(false);
+                ctx.set_handled();
+            }
+            _ => {}
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        _ctx: &mut LifeCycleCtx,
+        _event: &LifeCycle,
+        _data: &Workspace,
+        _env: &Env,
+    ) {
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &Workspace, data: &Workspace, _env: &Env) {
+        if old_data.input_text != data.input_text || old_data.viewport != data.viewport {
+            ctx.request_paint();
+        }
+    }
+
+    fn layout(
+        &mut self,
+        _ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        _data: &Workspace,
+        _env: &Env,
+    ) -> Size {
+        bc.constrain(Size::new(WIDTH, bc.max().height))
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &Workspace, env: &Env) {
+        let size = ctx.size();
+        let lines = data.input_text.lines().count().max(1);
+        let line_height = Minimap::line_height(size.height, lines);
+
+        let text_color = env.get(crate::theme::BASIC_TEXT_COLOR).with_alpha(0.35);
+        for (index, line) in data.input_text.lines().enumerate() {
+            let indent = line.len() - line.trim_start().len();
+            let length = line.trim_end().chars().count().saturating_sub(indent);
+            if length == 0 {
+                continue;
+            }
+
+            let x = 4.0 + indent as f64 * CHAR_WIDTH;
+            let y = index as f64 * line_height;
+            let width = (length as f64 * CHAR_WIDTH).min(size.width - x);
+            let block = Rect::new(x, y, x + width, y + (line_height * 0.8).max(0.5));
+            ctx.fill(block, &text_color);
+        }
+
+        let (top, bottom) = data.viewport;
+        let doc_height = line_height * lines as f64;
+        let view = Rect::new(0.0, top * doc_height, size.width, bottom * doc_height);
+        ctx.fill(
+            view,
+            &env.get(crate::theme::TREE_SELECTED_COLOR).with_alpha(0.5),
+        );
+    }
+}
@@ -7,9 +7,22 @@ pub mod navigation_bar;
 pub mod status_bar;
 pub mod tool_bar;
 
+pub mod auto_save;
 pub mod bar_support;
 pub mod color;
+pub mod command_palette;
+pub mod find_in_files;
+pub mod font_check;
+pub mod git_diff;
+pub mod markdown_preview;
 pub mod menu;
+pub mod minimap;
+pub mod outline_panel;
+pub mod output_panel;
+pub mod runner;
+pub mod save_preferences;
+pub mod system_theme;
 pub mod tabs;
 pub mod text_edit_view;
 pub mod watcher;
+pub mod welcome;
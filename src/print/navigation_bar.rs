@@ -1,7 +1,143 @@
-pub struct NavigationBar {}
+use std::path::{Path, PathBuf};
+
+use druid::widget::{Controller, Flex, Label, SizedBox};
+use druid::{
+    BoxConstraints, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, Menu, MenuItem,
+    PaintCtx, Size, UpdateCtx, Widget, WidgetExt,
+};
+
+use crate::app_command::print_command;
+use crate::app_state::AppState;
+use crate::components::icon_button::IconButton;
+use crate::model::file_tree::FileEntry;
+
+/// The path of the open file as a row of crumbs. Clicking the project or a
+/// dir reveals it in the project tree and lists what is in it, clicking the
+/// file lists the files next to it.
+pub struct NavigationBar {
+    inner: Box<dyn Widget<AppState>>,
+}
 
 impl NavigationBar {
-    pub fn render(_path: &str) {}
+    pub fn new() -> NavigationBar {
+        NavigationBar {
+            inner: SizedBox::empty().boxed(),
+        }
+    }
+
+    fn rebuild_inner(&mut self, data: &AppState) {
+        let mut row = Flex::row();
+        for (index, (name, path)) in data.workspace.breadcrumbs().into_iter().enumerate() {
+            if index > 0 {
+                row.add_child(separator());
+            }
+            let label = Label::new(name).with_text_color(crate::theme::BASIC_TEXT_COLOR);
+            match path {
+                Some(path) => {
+                    row.add_child(IconButton::from_label(label).controller(Crumb { path }))
+                }
+                None => row.add_child(label),
+            }
+        }
+        self.inner = row.boxed();
+    }
+}
+
+fn separator() -> impl Widget<AppState> {
+    Label::new(">")
+        .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+        .padding((4.0, 0.0))
+}
+
+/// Handles the clicks on one crumb of the bar.
+struct Crumb {
+    path: PathBuf,
 }
 
-mod style {}
+impl Crumb {
+    /// the files and dirs of `dir` in the project tree, dirs are revealed
+    /// and files opened.
+    fn menu(dir: &FileEntry) -> Menu<AppState> {
+        let mut menu = Menu::empty();
+        for child in dir.children.iter() {
+            let name = Path::new(&child.path)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| child.name.clone());
+            let item = if child.is_dir {
+                MenuItem::new(format!("{}/", name))
+                    .command(print_command::REVEAL_PATH.with(PathBuf::from(&child.path)))
+            } else {
+                MenuItem::new(name).command(print_command::SET_FILE.with(child.to_owned()))
+            };
+            menu = menu.entry(item);
+        }
+        menu
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for Crumb {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::MouseDown(mouse) = event {
+            let entry = data.entry.find(&self.path);
+            // a file lists its siblings, there is nothing below it
+            let dir = match entry {
+                Some(entry) if entry.is_dir => Some(entry),
+                _ => self
+                    .path
+                    .parent()
+                    .and_then(|parent| data.entry.find(parent)),
+            };
+            if let Some(dir) = dir {
+                ctx.show_context_menu(Crumb::menu(dir), mouse.window_pos);
+            }
+            ctx.submit_command(print_command::REVEAL_PATH.with(self.path.clone()));
+            ctx.set_handled();
+            return;
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+impl Widget<AppState> for NavigationBar {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        self.inner.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.rebuild_inner(data);
+        }
+        self.inner.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
+        if old_data.workspace.breadcrumbs() != data.workspace.breadcrumbs() {
+            self.rebuild_inner(data);
+            ctx.children_changed();
+        } else {
+            self.inner.update(ctx, old_data, data, env);
+        }
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &AppState,
+        env: &Env,
+    ) -> Size {
+        self.inner.layout(ctx, bc, data, env)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, env: &Env) {
+        self.inner.paint(ctx, data, env)
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use druid::widget::{Label, Scroll, SizedBox};
+use druid::{
+    BoxConstraints, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx, Size,
+    TimerToken, UpdateCtx, Widget, WidgetExt,
+};
+
+use crate::app_command::print_command;
+use crate::app_state::Workspace;
+use crate::components::icon_button::IconButton;
+use crate::components::tree::Tree;
+use crate::model::outline::OutlineEntry;
+
+/// how long typing has to pause before the outline is read again.
+const OUTLINE_DEBOUNCE_MILLIS: u64 = 300;
+
+const WIDTH: f64 = 200.0;
+
+/// The headings of the open file as a tree, clicking one moves the caret
+/// to it.
+pub struct OutlinePanel {
+    inner: Box<dyn Widget<Workspace>>,
+    timer: TimerToken,
+}
+
+impl OutlinePanel {
+    pub fn new() -> OutlinePanel {
+        OutlinePanel {
+            inner: SizedBox::empty().boxed(),
+            timer: TimerToken::INVALID,
+        }
+    }
+
+    fn rebuild_inner(&mut self) {
+        let tree = Tree::new(|entry: &OutlineEntry| {
+            IconButton::from_label(
+                Label::new(entry.title.clone())
+                    .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+                    .with_text_size(crate::theme::BASIC_TEXT_SIZE),
+            )
+            .on_click(|ctx, entry: &mut OutlineEntry, _env| {
+                ctx.submit_command(print_command::REVEAL_LINE.with(entry.line));
+            })
+        })
+        .with_on_activate(|ctx, entry: &mut OutlineEntry, _env| {
+            ctx.submit_command(print_command::REVEAL_LINE.with(entry.line));
+        });
+
+        self.inner = Scroll::new(tree)
+            .vertical()
+            .fix_width(WIDTH)
+            .expand_height()
+            .background(crate::theme::SIDEBAR_BACKGROUND)
+            .lens(Workspace::outline)
+            .boxed();
+    }
+}
+
+impl Widget<Workspace> for OutlinePanel {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Workspace, env: &Env) {
+        if let Event::Timer(token) = event {
+            if *token == self.timer {
+                if data.large_file {
+                    data.outline = Default::default();
+                    return;
+                }
+                let mut outline =
+                    OutlineEntry::from_text(data.file_title(), &data.input_text, &data.language);
+                outline.keep_collapsed(&data.outline);
+                data.outline = outline;
+                return;
+            }
+        }
+        self.inner.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &Workspace,
+        env: &Env,
+    ) {
+        if let LifeCycle::WidgetAdded = event {
+            self.rebuild_inner();
+            // the outline is only kept up to date while the panel is shown
+            self.timer = ctx.request_timer(Duration::from_millis(0));
+        }
+        self.inner.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &Workspace, data: &Workspace, env: &Env) {
+        if old_data.input_text != data.input_text || old_data.language != data.language {
+            self.timer = ctx.request_timer(Duration::from_millis(OUTLINE_DEBOUNCE_MILLIS));
+        }
+        if !old_data.outline.same(&data.outline) {
+            self.rebuild_inner();
+            ctx.children_changed();
+        } else {
+            self.inner.update(ctx, old_data, data, env);
+        }
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &Workspace,
+        env: &Env,
+    ) -> Size {
+        self.inner.layout(ctx, bc, data, env)
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &Workspace, env: &Env) {
+        self.inner.paint(ctx, data, env)
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use druid::widget::prelude::*;
+use druid::widget::{Label, LineBreaking, Scroll};
+use druid::{FontDescriptor, FontFamily, Point, Rect, WidgetExt, WidgetPod};
+
+use crate::app_state::AppState;
+
+const PANEL_HEIGHT: f64 = 160.0;
+/// older lines are dropped once the output grows past this.
+pub const MAX_OUTPUT_LINES: usize = 5000;
+
+/// The output of the last run of the project command.
+pub fn output_panel() -> impl Widget<AppState> {
+    OutputView::new()
+        .fix_height(PANEL_HEIGHT)
+        .expand_width()
+        .lens(AppState::output)
+        .background(crate::theme::TOOL_WINDOW_COLOR)
+}
+
+/// Scrolls along with new output while it is scrolled to the end, and
+/// stays put once the user scrolled back.
+struct OutputView {
+    scroll: WidgetPod<Arc<String>, Scroll<Arc<String>, Box<dyn Widget<Arc<String>>>>>,
+    follow: bool,
+}
+
+impl OutputView {
+    fn new() -> OutputView {
+        let output = Label::new(|output: &Arc<String>, _env: &Env| output.to_string())
+            .with_font(FontDescriptor::new(FontFamily::MONOSPACE))
+            .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+            .with_line_break_mode(LineBreaking::WordWrap)
+            .padding(5.0)
+            .expand_width();
+
+        OutputView {
+            scroll: WidgetPod::new(Scroll::new(output.boxed()).vertical()),
+            follow: false,
+        }
+    }
+
+    fn is_at_end(&self) -> bool {
+        let scroll = self.scroll.widget();
+        scroll.viewport_rect().y1 >= scroll.child_size().height - 1.0
+    }
+}
+
+impl Widget<Arc<String>> for OutputView {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut Arc<String>, env: &Env) {
+        self.scroll.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &Arc<String>,
+        env: &Env,
+    ) {
+        self.scroll.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        ctx: &mut UpdateCtx,
+        old_data: &Arc<String>,
+        data: &Arc<String>,
+        env: &Env,
+    ) {
+        if !old_data.same(data) && self.is_at_end() {
+            self.follow = true;
+            ctx.request_layout();
+        }
+        self.scroll.update(ctx, data, env)
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &Arc<String>,
+        env: &Env,
+    ) -> Size {
+        let size = self.scroll.layout(ctx, bc, data, env);
+        self.scroll.set_origin(ctx, data, env, Point::ORIGIN);
+
+        if self.follow {
+            self.follow = false;
+            let height = self.scroll.widget().child_size().height;
+            let end = Rect::new(0.0, (height - 1.0).max(0.0), 1.0, height);
+            self.scroll.widget_mut().scroll_to(end);
+        }
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &Arc<String>, env: &Env) {
+        self.scroll.paint(ctx, data, env)
+    }
+}
+
+/// Append `chunk` to `output` without its ANSI escape sequences, keeping
+/// at most `MAX_OUTPUT_LINES` lines.
+pub fn append_output(output: &mut String, chunk: &str) {
+    output.push_str(&strip_ansi(chunk));
+
+    let lines = output.matches('\n').count();
+    if lines > MAX_OUTPUT_LINES {
+        let dropped = output
+            .match_indices('\n')
+            .nth(lines - MAX_OUTPUT_LINES - 1)
+            .map_or(0, |(index, _)| index + 1);
+        output.drain(..dropped);
+    }
+}
+
+/// Remove ANSI escape sequences, colors and cursor movement, from `text`.
+pub fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch != '\u{1b}' {
+            stripped.push(ch);
+            continue;
+        }
+
+        match chars.peek() {
+            // CSI: parameters up to a final byte in @..~
+            Some('[') => {
+                chars.next();
+                for ch in chars.by_ref() {
+                    if ('@'..='~').contains(&ch) {
+                        break;
+                    }
+                }
+            }
+            // OSC: up to BEL or ESC \
+            Some(']') => {
+                chars.next();
+                let mut escaped = false;
+                for ch in chars.by_ref() {
+                    if ch == '\u{7}' || escaped {
+                        break;
+                    }
+                    escaped = ch == '\u{1b}';
+                }
+            }
+            // two char sequences
+            Some(_) => {
+                chars.next();
+            }
+            None => {}
+        }
+    }
+    stripped
+}
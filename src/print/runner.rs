@@ -0,0 +1,96 @@
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+
+use druid::{ExtEventSink, Target};
+
+use crate::app_command::print_command;
+
+/// Run `command` through the shell in `dir` on a background thread. Its
+/// output comes back line by line as `RUN_OUTPUT`, then `RUN_FINISHED`.
+pub fn run(command: String, dir: PathBuf, sink: ExtEventSink) {
+    thread::spawn(move || {
+        let child = shell(&command)
+            .current_dir(&dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+
+        let mut child = match child {
+            Ok(child) => child,
+            Err(err) => {
+                log::error!("run command error: {:?}, {:?}", command, err);
+                submit_output(&sink, format!("failed to run {}: {}\n", command, err));
+                submit_finished(&sink, None);
+                return;
+            }
+        };
+
+        let mut readers = vec![];
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(forward(stdout, sink.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(forward(stderr, sink.clone()));
+        }
+
+        let code = match child.wait() {
+            Ok(status) => status.code(),
+            Err(err) => {
+                log::error!("wait command error: {:?}, {:?}", command, err);
+                None
+            }
+        };
+        // hand over what is left in the pipes before the exit status
+        for reader in readers {
+            let _ = reader.join();
+        }
+        submit_finished(&sink, code);
+    });
+}
+
+#[cfg(target_os = "windows")]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+#[cfg(not(target_os = "windows"))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+fn forward(pipe: impl Read + Send + 'static, sink: ExtEventSink) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut line = vec![];
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) => break,
+                Ok(_) => submit_output(&sink, String::from_utf8_lossy(&line).to_string()),
+                Err(err) => {
+                    log::error!("read command output error: {:?}", err);
+                    break;
+                }
+            }
+        }
+    })
+}
+
+fn submit_output(sink: &ExtEventSink, output: String) {
+    if let Err(err) = sink.submit_command(print_command::RUN_OUTPUT, output, Target::Auto) {
+        log::error!("submit run output error: {:?}", err);
+    }
+}
+
+fn submit_finished(sink: &ExtEventSink, code: Option<i32>) {
+    if let Err(err) = sink.submit_command(print_command::RUN_FINISHED, code, Target::Auto) {
+        log::error!("submit run finished error: {:?}", err);
+    }
+}
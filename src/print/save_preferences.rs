@@ -0,0 +1,25 @@
+use druid::widget::Controller;
+use druid::{Data, Env, Event, EventCtx, Widget};
+
+use crate::app_state::AppState;
+
+/// Writes the global config as soon as a control changed `AppState::params`,
+/// so the preferences stick however their modal is dismissed.
+pub struct SavePreferences;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for SavePreferences {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        let old_params = data.params.clone();
+        child.event(ctx, event, data, env);
+        if !old_params.same(&data.params) {
+            data.save_global_config();
+        }
+    }
+}
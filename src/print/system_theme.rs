@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+use druid::widget::Controller;
+use druid::{Env, Event, EventCtx, TimerToken, UpdateCtx, Widget};
+
+use crate::app_command::print_command;
+use crate::app_state::AppState;
+
+/// how often the OS appearance is looked up while the editor follows it.
+const POLL_SECS: u64 = 5;
+
+/// Sends `DETECT_SYSTEM_THEME` every few seconds while
+/// `Params::follow_system_theme` is on.
+pub struct SystemTheme {
+    timer: TimerToken,
+}
+
+impl SystemTheme {
+    pub fn new() -> SystemTheme {
+        SystemTheme {
+            timer: TimerToken::INVALID,
+        }
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for SystemTheme {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        match event {
+            Event::WindowConnected if data.params.follow_system_theme => {
+                self.timer = ctx.request_timer(Duration::from_secs(POLL_SECS));
+            }
+            Event::Timer(token) if *token == self.timer => {
+                self.timer = TimerToken::INVALID;
+                if data.params.follow_system_theme {
+                    ctx.submit_command(print_command::DETECT_SYSTEM_THEME);
+                    self.timer = ctx.request_timer(Duration::from_secs(POLL_SECS));
+                }
+                return;
+            }
+            _ => {}
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        if data.params.follow_system_theme && !old_data.params.follow_system_theme {
+            ctx.submit_command(print_command::DETECT_SYSTEM_THEME);
+            self.timer = ctx.request_timer(Duration::from_secs(POLL_SECS));
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
@@ -1,12 +1,15 @@
 use druid::widget::{Flex, SizedBox};
 use druid::{
-    BoxConstraints, Color, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx,
+    BoxConstraints, Command, Data, Env, Event, EventCtx, KbKey, LayoutCtx, LifeCycle, LifeCycleCtx,
     LocalizedString, Menu, MenuItem, MouseEvent, PaintCtx, Size, UpdateCtx, Widget, WidgetExt,
     WidgetId,
 };
 
+use crate::app_command::print_command;
 use crate::app_state::{AppState, Workspace};
+use crate::conan_text::custom_input_component::CustomTextComponent;
 use crate::conan_text::custom_textbox::CustomTextBox;
+use crate::print::minimap::Minimap;
 
 pub struct TextEditView {
     inner: Box<dyn Widget<AppState>>,
@@ -20,17 +23,32 @@ impl TextEditView {
     }
 
     fn rebuild_inner(&mut self, data: &AppState) {
-        let mut flex = Flex::column();
+        let mut flex = Flex::row();
 
         flex.add_flex_child(
             CustomTextBox::multiline()
-                .with_text_color(Color::BLACK)
+                .with_font(crate::theme::WRITING_FONT)
+                .with_line_wrapping(data.word_wrap())
+                .with_show_whitespace(data.params.show_whitespace && !data.workspace.large_file)
+                .with_typewriter_scrolling(data.params.typewriter_scrolling)
+                .with_smooth_scrolling(data.params.smooth_scrolling)
+                .with_max_content_width(
+                    Some(data.params.max_content_width).filter(|width| *width > 0.0),
+                )
+                .with_text_color(crate::theme::BASIC_TEXT_COLOR)
                 .expand_width()
                 .expand_height()
                 .lens(Workspace::input_text)
-                .background(Color::WHITE),
+                .background(druid::theme::BACKGROUND_LIGHT),
             1.0,
         );
+        if data.params.minimap && !data.workspace.large_file {
+            flex.add_child(
+                Minimap::new()
+                    .expand_height()
+                    .background(druid::theme::BACKGROUND_LIGHT),
+            );
+        }
 
         let flex = flex
             .expand_width()
@@ -68,29 +86,104 @@ impl TextEditView {
     }
 }
 
+/// the textbox keeps the git changes in its session, not in the data it edits.
+fn set_line_changes(data: &AppState) -> Command {
+    CustomTextComponent::SET_LINE_CHANGES.with(data.workspace.line_changes.clone())
+}
+
+/// the textbox refuses edits of a read-only file.
+fn set_read_only(data: &AppState) -> Command {
+    CustomTextComponent::SET_READ_ONLY.with(data.workspace.read_only)
+}
+
+/// Tab inserts what the open file indents with.
+fn set_indentation(data: &AppState) -> Command {
+    CustomTextComponent::SET_INDENTATION.with(data.workspace.indentation)
+}
+
 impl Widget<AppState> for TextEditView {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
         match event {
             Event::MouseDown(m) => self.send_mouse(ctx, data, env, m),
+            // Tab indents a selection spanning lines, a plain Tab inserts one level
+            Event::KeyDown(key) if key.key == KbKey::Tab => {
+                if key.mods.shift() {
+                    ctx.submit_command(print_command::OUTDENT);
+                    ctx.set_handled();
+                    return;
+                }
+                if data.workspace.selection_spans_lines() {
+                    ctx.submit_command(print_command::INDENT);
+                    ctx.set_handled();
+                    return;
+                }
+            }
+            Event::Notification(cmd) if cmd.is(CustomTextComponent::SELECTION_CHANGED) => {
+                if let Some(selection) = cmd.get(CustomTextComponent::SELECTION_CHANGED) {
+                    data.workspace.selection_anchor = selection.anchor;
+                    data.workspace.selection_active = selection.active;
+                }
+                ctx.set_handled();
+                return;
+            }
+            Event::Notification(cmd) if cmd.is(CustomTextComponent::VIEWPORT_CHANGED) => {
+                if let Some(viewport) = cmd.get(CustomTextComponent::VIEWPORT_CHANGED) {
+                    data.workspace.viewport = *viewport;
+                }
+                ctx.set_handled();
+                return;
+            }
             _ => {}
         }
-        self.inner.event(ctx, event, data, env)
+
+        // only compare while clean, there is nothing to flag once dirty
+        if data.workspace.dirty {
+            self.inner.event(ctx, event, data, env);
+            return;
+        }
+        let before = data.workspace.input_text.clone();
+        self.inner.event(ctx, event, data, env);
+        if before != data.workspace.input_text {
+            data.workspace.dirty = true;
+        }
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
         if let LifeCycle::WidgetAdded = event {
             self.rebuild_inner(data);
+            ctx.submit_command(set_line_changes(data));
+            ctx.submit_command(set_indentation(data));
+            ctx.submit_command(set_read_only(data));
         }
         self.inner.lifecycle(ctx, event, data, env)
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
-        if !old_data.params.same(&data.params) {
+        if !old_data.params.same(&data.params)
+            || old_data.workspace.large_file != data.workspace.large_file
+            || old_data.word_wrap() != data.word_wrap()
+        {
             self.rebuild_inner(data);
             ctx.children_changed();
+            ctx.submit_command(set_line_changes(data));
+            ctx.submit_command(set_indentation(data));
+            ctx.submit_command(set_read_only(data));
         } else {
             self.inner.update(ctx, old_data, data, env);
         }
+        if !old_data
+            .workspace
+            .line_changes
+            .same(&data.workspace.line_changes)
+        {
+            ctx.submit_command(set_line_changes(data));
+        }
+        if old_data.workspace.indentation != data.workspace.indentation {
+            ctx.submit_command(set_indentation(data));
+        }
+        if old_data.workspace.read_only != data.workspace.read_only {
+            ctx.submit_command(set_read_only(data));
+        }
     }
 
     fn layout(
@@ -1,3 +1 @@
-pub struct Toolbar {
-
-}
\ No newline at end of file
+pub struct Toolbar {}
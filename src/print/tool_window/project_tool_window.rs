@@ -1,44 +1,83 @@
-use druid::widget::{Flex, Label, Scroll, SizedBox};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use druid::widget::{Controller, Flex, Label, Scroll, SizedBox};
 use druid::{
     BoxConstraints, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx,
-    LocalizedString, Menu, MenuItem, MouseEvent, PaintCtx, Size, UpdateCtx, Widget, WidgetExt,
+    LocalizedString, Menu, MenuItem, MouseEvent, PaintCtx, Size, UpdateCtx, Vec2, Widget,
+    WidgetExt,
 };
 
 use crate::app_command::print_command;
 use crate::app_state::AppState;
 use crate::components::icon_button::IconButton;
 use crate::components::tree::Tree;
+use crate::file_manager;
 use crate::model::file_tree::FileEntry;
+use crate::support::git::GitStatus;
 
 pub struct ProjectToolWindow {
     inner: Box<dyn Widget<AppState>>,
+    /// what the tree showed, for the one rebuilt in its place.
+    kept: Rc<RefCell<KeptView>>,
+}
+
+/// The scroll offset and selection of the tree, the tree is built again on
+/// every change of the entries and would jump back to the top.
+#[derive(Default)]
+struct KeptView {
+    offset: Vec2,
+    selected: Option<Vec<usize>>,
 }
 
 impl ProjectToolWindow {
     pub fn new() -> ProjectToolWindow {
         ProjectToolWindow {
             inner: SizedBox::empty().boxed(),
+            kept: Default::default(),
         }
     }
 
-    fn rebuild_inner(&mut self, data: &AppState) {
+    /// Build the tree again, selecting the revealed file when `reveal` is
+    /// set and the node selected before otherwise.
+    fn rebuild_inner(&mut self, data: &AppState, reveal: bool) {
         let mut flex = Flex::column();
 
         if data.current_dir.is_some() {
-            let scroll = Scroll::new(Tree::new(|t: &FileEntry| {
-                // todo: different for dir & file;
-                return IconButton::from_label(
-                    Label::new(t.name.as_str())
-                        .with_text_color(crate::theme::BASIC_TEXT_COLOR)
-                        .with_text_size(crate::theme::BASIC_TEXT_SIZE),
-                )
-                .on_click(|ctx, data: &mut FileEntry, _env| {
+            let selected = if reveal {
+                data.revealed
+                    .as_ref()
+                    .and_then(|path| data.entry.index_path(path))
+            } else {
+                self.kept.borrow().selected.clone()
+            };
+            let scroll = Scroll::new(
+                Tree::new(|t: &FileEntry| {
+                    return IconButton::from_label(entry_label(t))
+                        .on_click(|ctx, data: &mut FileEntry, _env| {
+                            if !data.is_dir {
+                                ctx.submit_command(print_command::SET_FILE.with(data.to_owned()));
+                            }
+                        })
+                        .controller(EntryMenu);
+                })
+                .with_on_activate(|ctx, data: &mut FileEntry, _env| {
                     if !data.is_dir {
                         ctx.submit_command(print_command::SET_FILE.with(data.to_owned()));
                     }
-                });
-            }));
-            flex.add_child(scroll);
+                })
+                .with_on_drop(|ctx, dragged: &FileEntry, dir: &FileEntry, _env| {
+                    ctx.submit_command(
+                        print_command::MOVE_FILE.with((dragged.to_owned(), dir.to_owned())),
+                    );
+                })
+                .with_selected(selected),
+            );
+            flex.add_child(KeepView {
+                inner: scroll,
+                kept: self.kept.clone(),
+                restored: false,
+            });
         }
 
         let flex = flex
@@ -72,26 +111,199 @@ impl ProjectToolWindow {
     }
 }
 
+/// The icon and name of a tree node, colored and badged by its git status.
+fn entry_label(entry: &FileEntry) -> Label<FileEntry> {
+    let (text, color) = match entry.git_status {
+        None => (
+            format!("{} {}", entry.icon(), entry.name),
+            crate::theme::BASIC_TEXT_COLOR,
+        ),
+        Some(status) => {
+            let color = match status {
+                GitStatus::Modified => crate::theme::GIT_MODIFIED,
+                GitStatus::Added | GitStatus::Renamed => crate::theme::GIT_ADDED,
+                GitStatus::Untracked => crate::theme::GIT_UNTRACKED,
+                GitStatus::Deleted => crate::theme::GIT_DELETED,
+                GitStatus::Conflicted => crate::theme::GIT_CONFLICTED,
+            };
+            // dirs are only colored, the badge is for the changed file itself
+            let text = if entry.is_dir {
+                format!("{} {}", entry.icon(), entry.name)
+            } else {
+                format!("{} {}  {}", entry.icon(), entry.name, status.badge())
+            };
+            (text, color)
+        }
+    };
+
+    Label::new(text)
+        .with_text_color(color)
+        .with_text_size(crate::theme::BASIC_TEXT_SIZE)
+}
+
+/// The scroll of the tree, keeping its offset and selection in `kept` and
+/// going back to the kept offset once it is laid out.
+struct KeepView {
+    inner: Scroll<FileEntry, Tree<FileEntry>>,
+    kept: Rc<RefCell<KeptView>>,
+    restored: bool,
+}
+
+impl KeepView {
+    fn keep(&self) {
+        let mut kept = self.kept.borrow_mut();
+        kept.offset = self.inner.offset();
+        kept.selected = self.inner.child().selected().cloned();
+    }
+}
+
+impl Widget<FileEntry> for KeepView {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut FileEntry, env: &Env) {
+        self.inner.event(ctx, event, data, env);
+        if self.restored {
+            self.keep();
+        }
+    }
+
+    fn lifecycle(
+        &mut self,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &FileEntry,
+        env: &Env,
+    ) {
+        self.inner.lifecycle(ctx, event, data, env)
+    }
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &FileEntry, data: &FileEntry, env: &Env) {
+        self.inner.update(ctx, old_data, data, env)
+    }
+
+    fn layout(
+        &mut self,
+        ctx: &mut LayoutCtx,
+        bc: &BoxConstraints,
+        data: &FileEntry,
+        env: &Env,
+    ) -> Size {
+        let size = self.inner.layout(ctx, bc, data, env);
+        // the offset is clamped to the content, which is only known now
+        if !self.restored {
+            self.restored = true;
+            let offset = self.kept.borrow().offset;
+            self.inner.scroll_by(offset);
+            self.keep();
+        }
+        size
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &FileEntry, env: &Env) {
+        self.inner.paint(ctx, data, env)
+    }
+}
+
+/// Shows the file operations for the right-clicked tree node.
+struct EntryMenu;
+
+impl EntryMenu {
+    fn menu(entry: &FileEntry) -> Menu<AppState> {
+        Menu::empty()
+            .entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-new-file").with_placeholder("New File"),
+                )
+                .command(print_command::NEW_FILE.with(entry.to_owned())),
+            )
+            .entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-new-folder").with_placeholder("New Folder"),
+                )
+                .command(print_command::NEW_FOLDER.with(entry.to_owned())),
+            )
+            .separator()
+            .entry(
+                MenuItem::new(LocalizedString::new("menu-item-rename").with_placeholder("Rename"))
+                    .command(print_command::RENAME_FILE.with(entry.to_owned())),
+            )
+            .entry(
+                MenuItem::new(LocalizedString::new("menu-item-delete").with_placeholder("Delete"))
+                    .command(print_command::DELETE_FILE.with(entry.to_owned())),
+            )
+            .separator()
+            .entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-copy-path").with_placeholder("Copy Path"),
+                )
+                .command(print_command::COPY_PATH.with(entry.to_owned())),
+            )
+            .entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-copy-relative-path")
+                        .with_placeholder("Copy Relative Path"),
+                )
+                .command(print_command::COPY_RELATIVE_PATH.with(entry.to_owned())),
+            )
+            .entry(
+                MenuItem::new(
+                    LocalizedString::new("menu-item-reveal")
+                        .with_placeholder(file_manager::REVEAL_TITLE),
+                )
+                .command(print_command::REVEAL_IN_FILE_MANAGER.with(entry.to_owned())),
+            )
+            .separator()
+            .entry(
+                MenuItem::new(LocalizedString::new("menu-item-reload").with_placeholder("Reload"))
+                    .command(print_command::RELOAD_DIR),
+            )
+    }
+}
+
+impl<W: Widget<FileEntry>> Controller<FileEntry, W> for EntryMenu {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut FileEntry,
+        env: &Env,
+    ) {
+        if let Event::MouseDown(mouse) = event {
+            if mouse.button.is_right() {
+                ctx.show_context_menu(EntryMenu::menu(data), mouse.window_pos);
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
 #[allow(unused_variables)]
 impl Widget<AppState> for ProjectToolWindow {
     fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        self.inner.event(ctx, event, data, env);
+        // tree nodes show their own menu, this one is for the empty space
         match event {
-            Event::MouseDown(m) => self.send_mouse(ctx, data, env, m),
+            Event::MouseDown(m) if !ctx.is_handled() => self.send_mouse(ctx, data, env, m),
             _ => {}
         }
-        self.inner.event(ctx, event, data, env)
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
         if let LifeCycle::WidgetAdded = event {
-            self.rebuild_inner(data);
+            self.rebuild_inner(data, true);
         }
         self.inner.lifecycle(ctx, event, data, env)
     }
 
     fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, env: &Env) {
         if !old_data.current_dir.same(&data.current_dir) {
-            self.rebuild_inner(data);
+            // another project starts at the top
+            self.kept.replace(KeptView::default());
+            self.rebuild_inner(data, true);
+            ctx.children_changed();
+        } else if !old_data.entry.same(&data.entry) || !old_data.revealed.same(&data.revealed) {
+            self.rebuild_inner(data, !old_data.revealed.same(&data.revealed));
             ctx.children_changed();
         } else {
             self.inner.update(ctx, old_data, data, env);
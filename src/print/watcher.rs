@@ -98,12 +98,16 @@ impl FileWatcher {
         let sender = Arc::new(Mutex::new(tx_event));
         let inner: RecommendedWatcher = Watcher::new_immediate(move |res| {
             let _ = sender.lock().unwrap().send(res);
-        }).expect("watcher should spawn");
+        })
+        .expect("watcher should spawn");
 
         thread::spawn(move || {
             while let Ok(Ok(event)) = rx_event.recv() {
                 let mut state = state_clone.lock().unwrap();
-                let WatcherState { ref mut events, ref mut watchees } = *state;
+                let WatcherState {
+                    ref mut events,
+                    ref mut watchees,
+                } = *state;
 
                 watchees
                     .iter()
@@ -156,7 +160,12 @@ impl FileWatcher {
 
         let mut state = self.state.lock().unwrap();
 
-        let w = Watchee { path, recursive, token, filter };
+        let w = Watchee {
+            path,
+            recursive,
+            token,
+            filter,
+        };
         let mode = mode_from_bool(w.recursive);
 
         if !state.watchees.iter().any(|w2| w.path == w2.path) {
@@ -174,7 +183,10 @@ impl FileWatcher {
     pub fn unwatch(&mut self, path: &Path, token: WatchToken) {
         let mut state = self.state.lock().unwrap();
 
-        let idx = state.watchees.iter().position(|w| w.token == token && w.path == path);
+        let idx = state
+            .watchees
+            .iter()
+            .position(|w| w.token == token && w.path == path);
 
         if let Some(idx) = idx {
             let removed = state.watchees.remove(idx);
@@ -224,10 +236,13 @@ impl FileWatcher {
 impl Watchee {
     fn wants_event(&self, event: &Event) -> bool {
         match &event.kind {
-            EventKind::Create(CreateKind::Any)
-            | EventKind::Remove(RemoveKind::Any)
+            EventKind::Create(_)
+            | EventKind::Remove(_)
             | EventKind::Modify(ModifyKind::Any)
-            | EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)) => {
+            | EventKind::Modify(ModifyKind::Data(_))
+            | EventKind::Modify(ModifyKind::Metadata(_))
+            | EventKind::Modify(ModifyKind::Name(RenameMode::From))
+            | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
                 if event.paths.len() == 1 {
                     self.applies_to_path(&event.paths[0])
                 } else {
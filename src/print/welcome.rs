@@ -0,0 +1,58 @@
+use druid::widget::{CrossAxisAlignment, Flex, Label, List};
+use druid::{commands, Command, Env, FileDialogOptions, Widget, WidgetExt};
+
+use crate::app_command::print_command;
+use crate::app_state::{AppState, RecentDir};
+use crate::components::icon_button::IconButton;
+
+/// Shown in place of the editor while no file or folder is open.
+pub fn welcome() -> impl Widget<AppState> {
+    let open_file = commands::SHOW_OPEN_PANEL.with(FileDialogOptions::new());
+    let open_folder = commands::SHOW_OPEN_PANEL.with(FileDialogOptions::new().select_directories());
+
+    let actions = Flex::row()
+        .with_child(action("Open File...", open_file))
+        .with_default_spacer()
+        .with_child(action("Open Folder...", open_folder))
+        .with_default_spacer()
+        .with_child(action("New File", commands::NEW_FILE.into()));
+
+    let recent = List::new(|| {
+        Label::new(|data: &RecentDir, _env: &Env| data.path.display().to_string())
+            .with_text_color(crate::theme::BASIC_TEXT_COLOR)
+            .padding((0.0, 2.0))
+            .on_click(|ctx, data: &mut RecentDir, _env| {
+                ctx.submit_command(print_command::OPEN_RECENT.with(data.path.clone()));
+            })
+    })
+    .lens(AppState::recent_dirs);
+
+    let recent_title = Label::new(|data: &AppState, _env: &Env| {
+        if data.recent_dirs.is_empty() {
+            "".to_string()
+        } else {
+            "Recent".to_string()
+        }
+    })
+    .with_text_color(crate::theme::BASIC_TEXT_COLOR);
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_child(
+            Label::new("Conan")
+                .with_text_size(28.0)
+                .with_text_color(crate::theme::BASIC_TEXT_COLOR),
+        )
+        .with_spacer(20.0)
+        .with_child(actions)
+        .with_spacer(20.0)
+        .with_child(recent_title)
+        .with_default_spacer()
+        .with_child(recent)
+        .center()
+}
+
+fn action(title: &str, command: Command) -> impl Widget<AppState> {
+    IconButton::from_label(Label::new(title).with_text_color(crate::theme::BASIC_TEXT_COLOR))
+        .on_click(move |ctx, _data: &mut AppState, _env| ctx.submit_command(command.clone()))
+}
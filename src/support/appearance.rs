@@ -0,0 +1,59 @@
+use std::process::Command;
+
+/// Whether the OS is set to a dark appearance, `None` when it can't be told.
+/// druid has no event for a change of it, so callers poll.
+#[cfg(target_os = "macos")]
+pub fn system_dark() -> Option<bool> {
+    // the key only exists in dark mode, reading it fails in light mode
+    let output = Command::new("defaults")
+        .args(&["read", "-g", "AppleInterfaceStyle"])
+        .output()
+        .ok()?;
+    Some(output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "Dark")
+}
+
+#[cfg(target_os = "windows")]
+pub fn system_dark() -> Option<bool> {
+    let output = Command::new("reg")
+        .args(&[
+            "query",
+            r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+            "/v",
+            "AppsUseLightTheme",
+        ])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // e.g. `AppsUseLightTheme    REG_DWORD    0x0`
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let value = stdout
+        .lines()
+        .find(|line| line.contains("AppsUseLightTheme"))?;
+    Some(value.trim_end().ends_with("0x0"))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+pub fn system_dark() -> Option<bool> {
+    // GNOME 42+ has a color scheme, older desktops only a theme name
+    if let Some(scheme) = gsettings("color-scheme") {
+        if scheme != "default" {
+            return Some(scheme == "prefer-dark");
+        }
+    }
+    gsettings("gtk-theme").map(|theme| theme.to_lowercase().contains("dark"))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn gsettings(key: &str) -> Option<String> {
+    let output = Command::new("gsettings")
+        .args(&["get", "org.gnome.desktop.interface", key])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let value = String::from_utf8_lossy(&output.stdout);
+    Some(value.trim().trim_matches('\'').to_string())
+}
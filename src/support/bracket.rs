@@ -0,0 +1,99 @@
+/// how far from the caret a matching bracket is looked for, in bytes.
+const MAX_SCAN: usize = 10_000;
+
+const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/// The byte offsets of the bracket next to `caret` and of its match,
+/// the bracket before the caret wins over the one after it.
+pub fn matching_bracket(text: &str, caret: usize) -> Option<(usize, usize)> {
+    let before = text.get(..caret)?.char_indices().next_back();
+    let after = text.get(caret..)?.chars().next().map(|ch| (caret, ch));
+
+    before
+        .into_iter()
+        .chain(after)
+        .find_map(|(offset, ch)| match_at(text, offset, ch).map(|other| (offset, other)))
+}
+
+fn match_at(text: &str, offset: usize, ch: char) -> Option<usize> {
+    for (open, close) in PAIRS.iter().copied() {
+        if ch == open {
+            let start = offset + ch.len_utf8();
+            let chars = text[start..]
+                .char_indices()
+                .take_while(|(index, _)| *index < MAX_SCAN);
+            return find_match(chars, open, close).map(|index| start + index);
+        }
+        if ch == close {
+            let chars = text[..offset]
+                .char_indices()
+                .rev()
+                .take_while(|(index, _)| offset - index <= MAX_SCAN);
+            return find_match(chars, close, open);
+        }
+    }
+    None
+}
+
+// walk away from a bracket until the one closing it, skipping nested pairs
+fn find_match(chars: impl Iterator<Item = (usize, char)>, from: char, to: char) -> Option<usize> {
+    let mut depth = 0;
+    for (index, ch) in chars {
+        if ch == from {
+            depth += 1;
+        } else if ch == to {
+            if depth == 0 {
+                return Some(index);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_nested_pairs() {
+        assert_eq!(matching_bracket("(a(b)c)", 0), Some((0, 6)));
+        assert_eq!(matching_bracket("(a(b)c)", 7), Some((6, 0)));
+        assert_eq!(matching_bracket("{[()]}", 2), Some((1, 4)));
+    }
+
+    #[test]
+    fn unbalanced_brackets_have_no_match() {
+        assert_eq!(matching_bracket("((a)", 0), None);
+        assert_eq!(matching_bracket("a)", 2), None);
+        assert_eq!(matching_bracket("(]", 0), None);
+    }
+
+    #[test]
+    fn multibyte_characters_next_to_the_bracket() {
+        // "é" and "ü" take two bytes each
+        let text = "é(ü)é";
+        assert_eq!(matching_bracket(text, 2), Some((2, 5)));
+        assert_eq!(matching_bracket(text, 6), Some((5, 2)));
+        // not on a character boundary
+        assert_eq!(matching_bracket(text, 1), None);
+    }
+
+    #[test]
+    fn gives_up_past_max_scan() {
+        let near = format!("({})", "a".repeat(MAX_SCAN - 1));
+        assert_eq!(matching_bracket(&near, 0), Some((0, MAX_SCAN)));
+        assert_eq!(matching_bracket(&near, near.len()), Some((MAX_SCAN, 0)));
+
+        let far = format!("({})", "a".repeat(MAX_SCAN));
+        assert_eq!(matching_bracket(&far, 0), None);
+        assert_eq!(matching_bracket(&far, far.len()), None);
+    }
+
+    #[test]
+    fn the_bracket_before_the_caret_wins() {
+        assert_eq!(matching_bracket("()()", 2), Some((1, 0)));
+        // unless it has no match
+        assert_eq!(matching_bracket(")()", 1), Some((1, 2)));
+    }
+}
@@ -1,36 +1,52 @@
 extern crate dirs;
 
-use crate::app_state::AppState;
+use crate::app_state::{AppState, ProjectConfig};
+use serde_json::Value;
 use std::fs;
 use std::fs::File;
-use std::path::PathBuf;
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 
 pub fn save_config(state: &AppState) {
+    let path = config_path().expect("lost home issue");
+    save_config_to(state, &path);
+}
+
+fn save_config_to(state: &AppState, path: &Path) {
     let result = serde_json::to_string_pretty(&state);
     match result {
-        Ok(str) => {
-            let path = config_path().expect("lost home issue");
-            if !path.exists() {
-                let _file = File::create(&path).unwrap();
-            }
-
-            let result = fs::write(&path, str);
-
-            match result {
-                Ok(_) => log::info!("save config: {:?}", path),
-                Err(e) => log::info!("failed to write data: {}", { e }),
-            }
-        }
+        Ok(str) => match write_atomic(path, &str) {
+            Ok(_) => log::info!("save config: {:?}", path),
+            Err(e) => log::info!("failed to write data: {}", { e }),
+        },
         Err(err) => {
             log::info!("serialize config error: {:?}", err);
         }
     }
 }
 
-#[allow(unused_assignments)]
+/// write to a temp file next to `path` and rename it into place, so a crash
+/// mid-write never leaves a truncated config behind.
+fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    let tmp_path = path.with_extension("json.tmp");
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&tmp_path, path)
+}
+
 pub fn read_config() -> AppState {
-    let mut app_state = AppState::default();
     let path = config_path().expect("lost home issue");
+    read_config_from(&path)
+}
+
+#[allow(unused_assignments)]
+fn read_config_from(path: &Path) -> AppState {
+    let mut app_state = AppState::default();
     let content;
     match fs::read_to_string(&path) {
         Ok(str) => {
@@ -45,19 +61,177 @@ pub fn read_config() -> AppState {
         Ok(state) => {
             app_state = state;
         }
-        Err(_err) => {
-            log::error!("error config: {}", content);
+        Err(err) => {
+            log::error!("error config: {:?}, {}", err, content);
+            backup_config(path);
+
+            match recover_config(&content) {
+                Some(state) => app_state = state,
+                None => {
+                    app_state.startup_alert = Some(format!(
+                        "The config could not be read, a copy was kept in {:?}.",
+                        path.with_extension("json.bak")
+                    ));
+                }
+            }
         }
     };
     return app_state;
 }
 
+/// best-effort parse of a config the current `AppState` can't read, keeping
+/// the open file, dir and theme when they are still there.
+fn recover_config(content: &str) -> Option<AppState> {
+    let value: Value = serde_json::from_str(content).ok()?;
+    let mut app_state = AppState::default();
+    let mut recovered = false;
+
+    if let Some(path) = value.get("current_file").and_then(Value::as_str) {
+        app_state.current_file = Some(PathBuf::from(path).into());
+        recovered = true;
+    }
+    if let Some(path) = value.get("current_dir").and_then(Value::as_str) {
+        app_state.current_dir = Some(PathBuf::from(path).into());
+        recovered = true;
+    }
+    if let Some(path) = value.get("last_dir").and_then(Value::as_str) {
+        app_state.last_dir = Some(PathBuf::from(path).into());
+    }
+    if let Some(dark_mode) = value.pointer("/params/dark_mode").and_then(Value::as_bool) {
+        app_state.params.dark_mode = dark_mode;
+        recovered = true;
+    }
+
+    if !recovered {
+        return None;
+    }
+
+    log::info!(
+        "recover config from version: {:?}",
+        value.get("config_version")
+    );
+    Some(app_state)
+}
+
+/// keep a config we failed to parse as `print.json.bak`, so it can be recovered.
+fn backup_config(path: &Path) {
+    let backup = path.with_extension("json.bak");
+    match fs::copy(path, &backup) {
+        Ok(_) => log::info!("backup broken config to: {:?}", backup),
+        Err(e) => log::info!("failed to backup config: {}", { e }),
+    }
+}
+
+/// `print.json` in the platform config dir, e.g. `$XDG_CONFIG_HOME/conan` on Linux
+/// and `%APPDATA%\conan` on Windows. A config left in the legacy `~/.print` is
+/// moved over the first time.
 pub fn config_path() -> Option<PathBuf> {
-    let home = dirs::home_dir()?;
-    let base = home.join(".print");
+    let base = dirs::config_dir()?.join("conan");
     if !&base.exists() {
         let _ = fs::create_dir_all(&base);
     }
     let config_path = base.join("print.json");
+
+    if !config_path.exists() {
+        migrate_legacy_config(&config_path);
+    }
+
     Some(config_path)
 }
+
+fn legacy_config_path() -> Option<PathBuf> {
+    let home = dirs::home_dir()?;
+    Some(home.join(".print").join("print.json"))
+}
+
+fn migrate_legacy_config(config_path: &PathBuf) {
+    let legacy = match legacy_config_path() {
+        Some(path) if path.exists() => path,
+        _ => return,
+    };
+
+    // rename fails across file systems, fall back to a copy
+    let result =
+        fs::rename(&legacy, config_path).or_else(|_| fs::copy(&legacy, config_path).map(|_| ()));
+    match result {
+        Ok(_) => log::info!("migrate config from {:?} to {:?}", legacy, config_path),
+        Err(e) => log::info!("failed to migrate config: {}", { e }),
+    }
+}
+
+pub fn project_config_path(dir: &Path) -> PathBuf {
+    dir.join(".print").join("project.json")
+}
+
+pub fn read_project_config(dir: &Path) -> Option<ProjectConfig> {
+    let path = project_config_path(dir);
+    let content = fs::read_to_string(&path).ok()?;
+
+    match serde_json::from_str(&content) {
+        Ok(config) => Some(config),
+        Err(err) => {
+            log::error!("error project config: {:?}, {:?}", path, err);
+            None
+        }
+    }
+}
+
+pub fn save_project_config(dir: &Path, config: &ProjectConfig) {
+    let path = project_config_path(dir);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let result = serde_json::to_string_pretty(config);
+    match result {
+        Ok(str) => match write_atomic(&path, &str) {
+            Ok(_) => log::info!("save project config: {:?}", path),
+            Err(e) => log::info!("failed to write data: {}", { e }),
+        },
+        Err(err) => {
+            log::info!("serialize project config error: {:?}", err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a fresh dir under the system temp dir for one test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("conan-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn a_partial_temp_file_leaves_the_config_alone() {
+        let dir = temp_dir("partial-write");
+        let path = dir.join("print.json");
+        let mut state = AppState::default();
+        state.params.font_size = 21.0;
+        save_config_to(&state, &path);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        // a crash in the middle of the next save
+        fs::write(path.with_extension("json.tmp"), "{\"params\": {\"font_si").unwrap();
+
+        assert_eq!(read_config_from(&path).params.font_size, 21.0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn a_broken_config_is_backed_up() {
+        let dir = temp_dir("backup");
+        let path = dir.join("print.json");
+        fs::write(&path, "{ not json").unwrap();
+
+        let state = read_config_from(&path);
+
+        assert!(state.startup_alert.is_some());
+        let backup = fs::read_to_string(path.with_extension("json.bak")).unwrap();
+        assert_eq!(backup, "{ not json");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
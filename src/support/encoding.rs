@@ -0,0 +1,200 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::error::Error;
+use std::fmt;
+
+/// the encodings offered in the "Reopen with Encoding" menu.
+pub const MENU_ENCODINGS: [&str; 7] = [
+    "UTF-8",
+    "UTF-16LE",
+    "UTF-16BE",
+    "GBK",
+    "Big5",
+    "Shift_JIS",
+    "windows-1252",
+];
+
+/// how much of a file is sniffed for null bytes by `is_binary`.
+pub const BINARY_SNIFF_LEN: usize = 8000;
+
+/// Whether `bytes` look like a binary file: a null byte early in the file
+/// that isn't explained by a UTF-16 BOM.
+pub fn is_binary(bytes: &[u8]) -> bool {
+    if Encoding::for_bom(bytes).is_some() {
+        return false;
+    }
+
+    let len = bytes.len().min(BINARY_SNIFF_LEN);
+    bytes[..len].contains(&0)
+}
+
+/// the text of a file, with what is needed to write it back the same way.
+#[derive(Clone, Debug)]
+pub struct DecodedText {
+    pub text: String,
+    pub encoding: String,
+    pub bom: bool,
+}
+
+/// Decode `bytes` with the encoding of its BOM, else the one guessed from
+/// its content.
+pub fn decode(bytes: &[u8]) -> DecodedText {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return decode_as(bytes, encoding);
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    decode_as(bytes, detector.guess(None, true))
+}
+
+/// Decode `bytes` as the encoding named `label`, falling back to detection
+/// for labels we don't know.
+pub fn decode_with(bytes: &[u8], label: &str) -> DecodedText {
+    match Encoding::for_label(label.as_bytes()) {
+        Some(encoding) => decode_as(bytes, encoding),
+        None => decode(bytes),
+    }
+}
+
+fn decode_as(bytes: &[u8], encoding: &'static Encoding) -> DecodedText {
+    let bom = match Encoding::for_bom(bytes) {
+        Some((bom_encoding, _)) => bom_encoding == encoding,
+        None => false,
+    };
+
+    let (text, _) = encoding.decode_with_bom_removal(bytes);
+    DecodedText {
+        text: text.into_owned(),
+        encoding: encoding.name().to_string(),
+        bom,
+    }
+}
+
+/// the text has characters the encoding of its file can't hold.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unmappable {
+    pub encoding: String,
+}
+
+impl fmt::Display for Unmappable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the text has characters {} can't hold", self.encoding)
+    }
+}
+
+impl Error for Unmappable {}
+
+/// Encode `text` back into the encoding named `label`, with a BOM when the
+/// file had one. Refuses text the encoding can't hold, rather than writing
+/// HTML entities in place of it.
+pub fn encode(text: &str, label: &str, bom: bool) -> Result<Vec<u8>, Unmappable> {
+    let encoding = Encoding::for_label(label.as_bytes()).unwrap_or(UTF_8);
+
+    let mut bytes = vec![];
+    // encoding_rs only encodes to UTF-8 for the UTF-16 encodings
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let little_endian = encoding == UTF_16LE;
+        if bom {
+            bytes.extend(to_utf16_bytes('\u{feff}' as u16, little_endian));
+        }
+        for unit in text.encode_utf16() {
+            bytes.extend(to_utf16_bytes(unit, little_endian));
+        }
+        return Ok(bytes);
+    }
+
+    if bom && encoding == UTF_8 {
+        bytes.extend(&[0xEF, 0xBB, 0xBF]);
+    }
+    let (encoded, _, had_errors) = encoding.encode(text);
+    if had_errors {
+        return Err(Unmappable {
+            encoding: encoding.name().to_string(),
+        });
+    }
+    bytes.extend(encoded.iter());
+    Ok(bytes)
+}
+
+fn to_utf16_bytes(unit: u16, little_endian: bool) -> [u8; 2] {
+    if little_endian {
+        unit.to_le_bytes()
+    } else {
+        unit.to_be_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf8_bom_round_trips() {
+        let bytes = b"\xEF\xBB\xBFhello";
+        let decoded = decode(bytes);
+        assert_eq!(decoded.text, "hello");
+        assert_eq!(decoded.encoding, "UTF-8");
+        assert!(decoded.bom);
+        assert_eq!(
+            encode(&decoded.text, &decoded.encoding, decoded.bom).unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn utf16le_bom_round_trips() {
+        let bytes = b"\xFF\xFEh\x00i\x00";
+        let decoded = decode(bytes);
+        assert_eq!(decoded.text, "hi");
+        assert_eq!(decoded.encoding, "UTF-16LE");
+        assert!(decoded.bom);
+        assert_eq!(
+            encode(&decoded.text, &decoded.encoding, decoded.bom).unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn utf16be_bom_round_trips() {
+        let bytes = b"\xFE\xFF\x00h\x00i";
+        let decoded = decode(bytes);
+        assert_eq!(decoded.text, "hi");
+        assert_eq!(decoded.encoding, "UTF-16BE");
+        assert!(decoded.bom);
+        assert_eq!(
+            encode(&decoded.text, &decoded.encoding, decoded.bom).unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn gbk_decodes_and_round_trips() {
+        // "中文" in GBK
+        let bytes = b"\xD6\xD0\xCE\xC4";
+        let decoded = decode_with(bytes, "GBK");
+        assert_eq!(decoded.text, "中文");
+        assert_eq!(decoded.encoding, "GBK");
+        assert!(!decoded.bom);
+        assert_eq!(
+            encode(&decoded.text, &decoded.encoding, decoded.bom).unwrap(),
+            bytes
+        );
+    }
+
+    #[test]
+    fn unmappable_text_is_refused() {
+        assert_eq!(
+            encode("caf\u{e9} \u{4e2d}", "windows-1252", false),
+            Err(Unmappable {
+                encoding: "windows-1252".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn a_bom_is_not_binary() {
+        assert!(!is_binary(b"\xFF\xFEh\x00i\x00"));
+        assert!(is_binary(b"ELF\x00\x01"));
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use similar::{DiffTag, TextDiff};
+
+/// The state of a path in the working tree, compared to the index and HEAD.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GitStatus {
+    Modified,
+    Added,
+    Renamed,
+    Deleted,
+    Untracked,
+    Conflicted,
+}
+
+impl GitStatus {
+    /// the letter shown next to the name in the file tree.
+    pub fn badge(&self) -> &'static str {
+        match self {
+            GitStatus::Modified => "M",
+            GitStatus::Added => "A",
+            GitStatus::Renamed => "R",
+            GitStatus::Deleted => "D",
+            GitStatus::Untracked => "U",
+            GitStatus::Conflicted => "C",
+        }
+    }
+
+    /// A dir shows the status of its most pressing change.
+    fn rank(&self) -> u8 {
+        match self {
+            GitStatus::Untracked => 0,
+            GitStatus::Added | GitStatus::Renamed => 1,
+            GitStatus::Modified | GitStatus::Deleted => 2,
+            GitStatus::Conflicted => 3,
+        }
+    }
+
+    /// the `XY` code of `git status --porcelain`, `None` for ignored files.
+    fn from_code(index: char, worktree: char) -> Option<GitStatus> {
+        let status = match (index, worktree) {
+            ('!', '!') => return None,
+            ('?', '?') => GitStatus::Untracked,
+            ('U', _) | (_, 'U') | ('A', 'A') | ('D', 'D') => GitStatus::Conflicted,
+            ('D', _) | (_, 'D') => GitStatus::Deleted,
+            ('R', _) => GitStatus::Renamed,
+            ('A', _) => GitStatus::Added,
+            _ => GitStatus::Modified,
+        };
+        Some(status)
+    }
+}
+
+/// How a run of lines of the buffer differs from HEAD.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// A run of changed lines of the buffer. A deletion has an empty range at
+/// the line the removed lines were in front of.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LineChange {
+    pub kind: ChangeKind,
+    pub lines: Range<usize>,
+    /// the lines HEAD has in place of `lines`.
+    pub original: String,
+}
+
+/// The root of the work tree `dir` is in, `None` outside of a repository or
+/// when git is not installed.
+pub fn work_tree(dir: &Path) -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(&["rev-parse", "--show-toplevel"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(PathBuf::from(root))
+}
+
+/// The status of every changed file below `dir`, keyed like `FileEntry::path`.
+/// The dirs leading to a change take the status of it too. `None` when `dir`
+/// is not in a git repository.
+pub fn status(dir: &Path) -> Option<HashMap<String, GitStatus>> {
+    let root = work_tree(dir)?;
+    let output = Command::new("git")
+        .args(&["status", "--porcelain", "-z", "--untracked-files=all"])
+        .current_dir(&root)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        log::info!(
+            "git status error: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return None;
+    }
+
+    // git reports the resolved root, the tree is built from `dir` as given
+    let canonical = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    let mut statuses: HashMap<String, GitStatus> = HashMap::new();
+    for (status, path) in parse_porcelain(&String::from_utf8_lossy(&output.stdout)) {
+        let relative = match root.join(&path).strip_prefix(&canonical) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => continue,
+        };
+
+        let mut path = Some(relative.as_path());
+        while let Some(current) = path.filter(|p| !p.as_os_str().is_empty()) {
+            let key = format!("{}", dir.join(current).display());
+            let stronger = match statuses.get(&key) {
+                Some(old) => status.rank() > old.rank(),
+                None => true,
+            };
+            if stronger {
+                statuses.insert(key, status);
+            }
+            path = current.parent();
+        }
+    }
+    Some(statuses)
+}
+
+/// The content of `path` in HEAD, `None` when it is not committed or not in
+/// a repository.
+pub fn head_text(path: &Path) -> Option<String> {
+    let root = work_tree(path.parent()?)?;
+    let path = path.canonicalize().ok()?;
+    let relative = path.strip_prefix(&root).ok()?;
+    // git wants forward slashes in the path of a blob, on every platform
+    let relative: Vec<_> = relative.iter().map(|name| name.to_string_lossy()).collect();
+
+    let output = Command::new("git")
+        .arg("show")
+        .arg(format!("HEAD:{}", relative.join("/")))
+        .current_dir(&root)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The runs of lines `text` changed compared to `head`, in order.
+pub fn line_changes(head: &str, text: &str) -> Vec<LineChange> {
+    let diff = TextDiff::from_lines(head, text);
+    let old_lines = diff.old_slices();
+
+    let mut changes = vec![];
+    for op in diff.ops() {
+        let (tag, old, new) = op.as_tag_tuple();
+        let kind = match tag {
+            DiffTag::Equal => continue,
+            DiffTag::Delete => ChangeKind::Deleted,
+            DiffTag::Insert => ChangeKind::Added,
+            DiffTag::Replace => ChangeKind::Modified,
+        };
+        changes.push(LineChange {
+            kind,
+            lines: new,
+            original: old_lines[old].concat(),
+        });
+    }
+    changes
+}
+
+/// Split the NUL separated output of `git status --porcelain -z`. Renames
+/// and copies carry the path they came from as an extra field, which is
+/// skipped.
+fn parse_porcelain(output: &str) -> Vec<(GitStatus, String)> {
+    let mut entries = vec![];
+    let mut fields = output.split('\0');
+    while let Some(field) = fields.next() {
+        let mut chars = field.chars();
+        let (index, worktree) = match (chars.next(), chars.next(), chars.next()) {
+            (Some(index), Some(worktree), Some(' ')) => (index, worktree),
+            _ => continue,
+        };
+        if index == 'R' || index == 'C' {
+            fields.next();
+        }
+        if let Some(status) = GitStatus::from_code(index, worktree) {
+            entries.push((status, field[3..].to_string()));
+        }
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_code_maps_the_porcelain_codes() {
+        let cases = [
+            (('?', '?'), Some(GitStatus::Untracked)),
+            (('R', ' '), Some(GitStatus::Renamed)),
+            (('U', 'U'), Some(GitStatus::Conflicted)),
+            (('A', 'A'), Some(GitStatus::Conflicted)),
+            ((' ', 'D'), Some(GitStatus::Deleted)),
+            (('A', ' '), Some(GitStatus::Added)),
+            ((' ', 'M'), Some(GitStatus::Modified)),
+            (('M', 'M'), Some(GitStatus::Modified)),
+            (('!', '!'), None),
+        ];
+        for ((index, worktree), expected) in cases.iter() {
+            assert_eq!(
+                GitStatus::from_code(*index, *worktree),
+                *expected,
+                "{}{}",
+                index,
+                worktree
+            );
+        }
+    }
+
+    #[test]
+    fn parse_porcelain_reads_each_entry() {
+        let output = "?? new.txt\0UU both.rs\0 D gone.md\0!! target/\0";
+        assert_eq!(
+            parse_porcelain(output),
+            vec![
+                (GitStatus::Untracked, "new.txt".to_string()),
+                (GitStatus::Conflicted, "both.rs".to_string()),
+                (GitStatus::Deleted, "gone.md".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_porcelain_skips_the_source_of_a_rename() {
+        let output = "R  new name.rs\0old name.rs\0 M src/main.rs\0";
+        assert_eq!(
+            parse_porcelain(output),
+            vec![
+                (GitStatus::Renamed, "new name.rs".to_string()),
+                (GitStatus::Modified, "src/main.rs".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use druid::Data;
+use serde::{Deserialize, Serialize};
+
+/// the widths the status bar offers to pick from.
+pub const WIDTHS: [usize; 3] = [2, 4, 8];
+
+/// how many lines `detect` looks at.
+const DETECT_LINES: usize = 1000;
+
+/// What one level of indentation is made of.
+#[derive(Serialize, Deserialize, Clone, Copy, Data, PartialEq, Debug)]
+pub struct Indentation {
+    pub tabs: bool,
+    /// the width of a level in columns, spaces or the width of a tab.
+    pub width: usize,
+}
+
+impl Default for Indentation {
+    fn default() -> Self {
+        Indentation {
+            tabs: true,
+            width: 4,
+        }
+    }
+}
+
+impl Indentation {
+    /// the text inserted for one level.
+    pub fn unit(&self) -> String {
+        if self.tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.width)
+        }
+    }
+
+    /// what the status bar shows, e.g. `Spaces: 4`.
+    pub fn label(&self) -> String {
+        if self.tabs {
+            format!("Tab Size: {}", self.width)
+        } else {
+            format!("Spaces: {}", self.width)
+        }
+    }
+}
+
+/// Guess the indentation of `text` from the leading whitespace of its first
+/// lines, `None` when it has no indented lines. Tabs keep the width of
+/// `fallback`, spaces take the most common step between lines.
+pub fn detect(text: &str, fallback: Indentation) -> Option<Indentation> {
+    let mut tab_lines = 0;
+    let mut space_lines = 0;
+    let mut steps = [0; 9];
+    let mut previous = 0;
+
+    for line in text.lines().take(DETECT_LINES) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('\t') {
+            tab_lines += 1;
+            continue;
+        }
+
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        if spaces > 0 {
+            space_lines += 1;
+        }
+        let step = if spaces > previous {
+            spaces - previous
+        } else {
+            0
+        };
+        if step > 1 && step < steps.len() {
+            steps[step] += 1;
+        }
+        previous = spaces;
+    }
+
+    if tab_lines == 0 && space_lines == 0 {
+        return None;
+    }
+    if tab_lines > space_lines {
+        return Some(Indentation {
+            tabs: true,
+            width: fallback.width,
+        });
+    }
+
+    let width = WIDTHS
+        .iter()
+        .rev()
+        .copied()
+        // the last of equals wins, so the narrower width on a tie
+        .max_by_key(|width| steps[*width])
+        .filter(|width| steps[*width] > 0)
+        .unwrap_or(fallback.width);
+    Some(Indentation { tabs: false, width })
+}
@@ -0,0 +1,308 @@
+use std::collections::{BTreeMap, HashMap};
+
+use druid::{KbKey, RawMods};
+
+/// The actions that can be rebound in the `keymap` of the config, with
+/// their default chords.
+pub const DEFAULT_KEYMAP: [(&str, &str); 23] = [
+    ("new_window", "Cmd+Shift+N"),
+    ("open_file", "Cmd+O"),
+    ("open_folder", "Cmd+Shift+O"),
+    ("save", "Cmd+S"),
+    ("save_as", "Cmd+Shift+S"),
+    ("go_to_file", "Cmd+P"),
+    ("go_to_line", "Cmd+L"),
+    ("indent", "Cmd+]"),
+    ("outdent", "Cmd+["),
+    ("toggle_comment", "Cmd+/"),
+    ("find_in_files", "Cmd+Shift+F"),
+    ("move_line_up", "Alt+Up"),
+    ("move_line_down", "Alt+Down"),
+    ("duplicate_line", "Cmd+Shift+D"),
+    ("select_next_occurrence", "Cmd+D"),
+    ("insert_literal_tab", "Ctrl+Tab"),
+    ("command_palette", "Cmd+Shift+P"),
+    ("zoom_in", "Cmd+="),
+    ("zoom_out", "Cmd+-"),
+    ("reset_zoom", "Cmd+0"),
+    ("close", "Cmd+W"),
+    ("reopen_closed", "Cmd+Shift+T"),
+    ("zen_mode", "Cmd+Shift+Enter"),
+];
+
+/// A key with the modifiers held down with it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chord {
+    pub mods: RawMods,
+    pub key: KbKey,
+}
+
+/// The chord of every action, the defaults overridden by the config.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: HashMap<&'static str, Chord>,
+}
+
+impl Keymap {
+    /// Apply the `overrides` from the config over `DEFAULT_KEYMAP`. An empty
+    /// chord or `none` unbinds an action. When two actions end up on the
+    /// same chord the last one wins, overrides in name order after the
+    /// defaults, and the other one is unbound with a warning.
+    pub fn load(overrides: &BTreeMap<String, String>) -> Keymap {
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+        };
+        for (action, chord) in DEFAULT_KEYMAP.iter() {
+            // the defaults are known to parse
+            keymap.bind(action, parse_chord(chord).ok());
+        }
+
+        for (action, chord) in overrides {
+            let action = match DEFAULT_KEYMAP.iter().find(|(name, _)| name == action) {
+                Some((name, _)) => *name,
+                None => {
+                    log::warn!("keymap: unknown action {:?}", action);
+                    continue;
+                }
+            };
+
+            let chord = chord.trim();
+            if chord.is_empty() || chord.eq_ignore_ascii_case("none") {
+                keymap.bind(action, None);
+                continue;
+            }
+            match parse_chord(chord) {
+                Ok(chord) => keymap.bind(action, Some(chord)),
+                Err(err) => log::warn!("keymap: {} for {:?}, keeping its default", err, action),
+            }
+        }
+        keymap
+    }
+
+    pub fn chord(&self, action: &str) -> Option<&Chord> {
+        self.bindings.get(action)
+    }
+
+    fn bind(&mut self, action: &'static str, chord: Option<Chord>) {
+        self.bindings.remove(action);
+        let chord = match chord {
+            Some(chord) => chord,
+            None => return,
+        };
+
+        let conflict = self
+            .bindings
+            .iter()
+            .find(|(_, bound)| **bound == chord)
+            .map(|(other, _)| *other);
+        if let Some(other) = conflict {
+            log::warn!(
+                "keymap: {:?} takes the chord of {:?}, which is unbound",
+                action,
+                other
+            );
+            self.bindings.remove(other);
+        }
+        self.bindings.insert(action, chord);
+    }
+}
+
+/// Parse a chord like `Cmd+Shift+S` or `Alt+Up`. `Cmd` is Command on macOS
+/// and Ctrl elsewhere, `Option` is an alias of `Alt`, and `Super` or `Win`
+/// of `Meta`.
+pub fn parse_chord(text: &str) -> Result<Chord, String> {
+    let parts: Vec<&str> = text.split('+').map(str::trim).collect();
+    // a chord ending in `+` binds the plus key itself
+    let (mods, key) = match parts.split_last() {
+        Some((last, mods)) if last.is_empty() && mods.last() == Some(&"") => {
+            (&mods[..mods.len() - 1], "+")
+        }
+        Some((last, mods)) => (mods, *last),
+        None => return Err(format!("empty chord {:?}", text)),
+    };
+
+    let (mut alt, mut ctrl, mut meta, mut shift) = (false, false, false, false);
+    for modifier in mods {
+        match modifier.to_lowercase().as_str() {
+            "cmd" | "cmdorctrl" => {
+                if cfg!(target_os = "macos") {
+                    meta = true
+                } else {
+                    ctrl = true
+                }
+            }
+            "ctrl" | "control" => ctrl = true,
+            "alt" | "option" => alt = true,
+            "shift" => shift = true,
+            "meta" | "super" | "win" => meta = true,
+            _ => return Err(format!("unknown modifier {:?} in {:?}", modifier, text)),
+        }
+    }
+
+    let key =
+        parse_key(key, shift).ok_or_else(|| format!("unknown key {:?} in {:?}", key, text))?;
+    Ok(Chord {
+        mods: raw_mods(alt, ctrl, meta, shift),
+        key,
+    })
+}
+
+fn parse_key(key: &str, shift: bool) -> Option<KbKey> {
+    let mut chars = key.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        // with Shift held the key event carries the upper case letter
+        let ch = if shift {
+            ch.to_ascii_uppercase()
+        } else {
+            ch.to_ascii_lowercase()
+        };
+        return Some(KbKey::Character(ch.to_string()));
+    }
+
+    let key = match key.to_lowercase().as_str() {
+        "up" => KbKey::ArrowUp,
+        "down" => KbKey::ArrowDown,
+        "left" => KbKey::ArrowLeft,
+        "right" => KbKey::ArrowRight,
+        "enter" | "return" => KbKey::Enter,
+        "tab" => KbKey::Tab,
+        "esc" | "escape" => KbKey::Escape,
+        "backspace" => KbKey::Backspace,
+        "delete" | "del" => KbKey::Delete,
+        "home" => KbKey::Home,
+        "end" => KbKey::End,
+        "pageup" => KbKey::PageUp,
+        "pagedown" => KbKey::PageDown,
+        "space" => KbKey::Character(" ".to_string()),
+        "plus" => KbKey::Character("+".to_string()),
+        "f1" => KbKey::F1,
+        "f2" => KbKey::F2,
+        "f3" => KbKey::F3,
+        "f4" => KbKey::F4,
+        "f5" => KbKey::F5,
+        "f6" => KbKey::F6,
+        "f7" => KbKey::F7,
+        "f8" => KbKey::F8,
+        "f9" => KbKey::F9,
+        "f10" => KbKey::F10,
+        "f11" => KbKey::F11,
+        "f12" => KbKey::F12,
+        _ => return None,
+    };
+    Some(key)
+}
+
+fn raw_mods(alt: bool, ctrl: bool, meta: bool, shift: bool) -> RawMods {
+    match (alt, ctrl, meta, shift) {
+        (false, false, false, false) => RawMods::None,
+        (true, false, false, false) => RawMods::Alt,
+        (false, true, false, false) => RawMods::Ctrl,
+        (false, false, true, false) => RawMods::Meta,
+        (false, false, false, true) => RawMods::Shift,
+        (true, true, false, false) => RawMods::AltCtrl,
+        (true, false, true, false) => RawMods::AltMeta,
+        (true, false, false, true) => RawMods::AltShift,
+        (false, true, true, false) => RawMods::CtrlMeta,
+        (false, true, false, true) => RawMods::CtrlShift,
+        (false, false, true, true) => RawMods::MetaShift,
+        (true, true, true, false) => RawMods::AltCtrlMeta,
+        (true, true, false, true) => RawMods::AltCtrlShift,
+        (true, false, true, true) => RawMods::AltMetaShift,
+        (false, true, true, true) => RawMods::CtrlMetaShift,
+        (true, true, true, true) => RawMods::AltCtrlMetaShift,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cmd is Command on macOS and Ctrl elsewhere.
+    fn cmd_shift() -> RawMods {
+        if cfg!(target_os = "macos") {
+            RawMods::MetaShift
+        } else {
+            RawMods::CtrlShift
+        }
+    }
+
+    fn chord(mods: RawMods, key: KbKey) -> Chord {
+        Chord { mods, key }
+    }
+
+    fn character(ch: &str) -> KbKey {
+        KbKey::Character(ch.to_string())
+    }
+
+    #[test]
+    fn parses_modifiers() {
+        assert_eq!(
+            parse_chord("Cmd+Shift+S"),
+            Ok(chord(cmd_shift(), character("S")))
+        );
+        assert_eq!(
+            parse_chord("ctrl+alt+delete"),
+            Ok(chord(RawMods::AltCtrl, KbKey::Delete))
+        );
+        assert_eq!(
+            parse_chord("Option+Up"),
+            Ok(chord(RawMods::Alt, KbKey::ArrowUp))
+        );
+        assert_eq!(
+            parse_chord("Super+A"),
+            Ok(chord(RawMods::Meta, character("a")))
+        );
+        assert_eq!(parse_chord("F5"), Ok(chord(RawMods::None, KbKey::F5)));
+    }
+
+    #[test]
+    fn a_trailing_plus_is_the_plus_key() {
+        assert_eq!(
+            parse_chord("Ctrl++"),
+            Ok(chord(RawMods::Ctrl, character("+")))
+        );
+        assert_eq!(
+            parse_chord("Ctrl+Plus"),
+            Ok(chord(RawMods::Ctrl, character("+")))
+        );
+    }
+
+    #[test]
+    fn refuses_unknown_keys_and_modifiers() {
+        assert!(parse_chord("Ctrl+Banana").is_err());
+        assert!(parse_chord("Hyper+A").is_err());
+        assert!(parse_chord("").is_err());
+    }
+
+    #[test]
+    fn the_last_of_two_overrides_on_a_chord_wins() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("save".to_string(), "Ctrl+Alt+Delete".to_string());
+        overrides.insert("open_file".to_string(), "Ctrl+Alt+Delete".to_string());
+        let keymap = Keymap::load(&overrides);
+
+        // overrides apply in name order, so `save` binds last and wins
+        assert_eq!(
+            keymap.chord("save"),
+            Some(&chord(RawMods::AltCtrl, KbKey::Delete))
+        );
+        assert_eq!(keymap.chord("open_file"), None);
+    }
+
+    #[test]
+    fn a_default_chord_moves_to_the_override() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("save_as".to_string(), "Cmd+S".to_string());
+        let keymap = Keymap::load(&overrides);
+
+        assert_eq!(keymap.chord("save_as"), parse_chord("Cmd+S").ok().as_ref());
+        assert_eq!(keymap.chord("save"), None);
+    }
+
+    #[test]
+    fn none_unbinds_an_action() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("zen_mode".to_string(), "none".to_string());
+        assert_eq!(Keymap::load(&overrides).chord("zen_mode"), None);
+    }
+}
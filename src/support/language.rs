@@ -0,0 +1,67 @@
+use std::path::Path;
+
+/// the language used when the extension is not known.
+pub const PLAIN_TEXT: &str = "Plain Text";
+
+/// the languages that can be picked for a file, by display name.
+pub const LANGUAGES: [&str; 16] = [
+    PLAIN_TEXT,
+    "C",
+    "C++",
+    "CSS",
+    "Go",
+    "HTML",
+    "Java",
+    "JavaScript",
+    "JSON",
+    "Markdown",
+    "Python",
+    "Rust",
+    "Shell",
+    "TOML",
+    "TypeScript",
+    "YAML",
+];
+
+/// Guess the language of `path` from its extension.
+pub fn language_for(path: &Path) -> &'static str {
+    let ext = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => ext.to_lowercase(),
+        None => return PLAIN_TEXT,
+    };
+
+    match ext.as_str() {
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" => "C++",
+        "css" => "CSS",
+        "go" => "Go",
+        "htm" | "html" => "HTML",
+        "java" => "Java",
+        "js" | "jsx" | "mjs" => "JavaScript",
+        "json" => "JSON",
+        "md" | "markdown" => "Markdown",
+        "py" => "Python",
+        "rs" => "Rust",
+        "sh" | "bash" | "zsh" => "Shell",
+        "toml" => "TOML",
+        "ts" | "tsx" => "TypeScript",
+        "yml" | "yaml" => "YAML",
+        _ => PLAIN_TEXT,
+    }
+}
+
+/// The token starting a line comment in `language`, `None` for languages
+/// that only have block comments or none at all.
+pub fn line_comment(language: &str) -> Option<&'static str> {
+    match language {
+        "C" | "C++" | "Go" | "Java" | "JavaScript" | "Rust" | "TypeScript" => Some("//"),
+        "Python" | "Shell" | "TOML" | "YAML" => Some("#"),
+        _ => None,
+    }
+}
+
+/// Whether two trailing spaces break a line in `language`, so stripping
+/// trailing whitespace has to keep them.
+pub fn has_hard_line_breaks(language: &str) -> bool {
+    language == "Markdown"
+}
@@ -0,0 +1,340 @@
+use crate::support::indentation::Indentation;
+
+/// what a line edit leaves behind: the new text and where the selection goes.
+pub struct LineEdit {
+    pub text: String,
+    pub anchor: usize,
+    pub active: usize,
+}
+
+/// Indent every non-blank line touched by the selection by one level.
+pub fn indent(text: &str, anchor: usize, active: usize, indentation: Indentation) -> LineEdit {
+    let unit = indentation.unit();
+    splice_lines(text, anchor, active, |line| {
+        if line.trim().is_empty() {
+            None
+        } else {
+            Some((0, 0, unit.clone()))
+        }
+    })
+}
+
+/// Remove one level of indentation, a tab or up to a level of spaces, from
+/// the lines touched by the selection.
+pub fn outdent(text: &str, anchor: usize, active: usize, indentation: Indentation) -> LineEdit {
+    splice_lines(text, anchor, active, |line| {
+        let removed = if line.starts_with('\t') {
+            1
+        } else {
+            line.chars()
+                .take(indentation.width)
+                .take_while(|ch| *ch == ' ')
+                .count()
+        };
+        if removed == 0 {
+            None
+        } else {
+            Some((0, removed, "".to_string()))
+        }
+    })
+}
+
+/// Comment out the lines touched by the selection with the line comment
+/// `token`, or uncomment them when all of them are commented already.
+pub fn toggle_comment(text: &str, anchor: usize, active: usize, token: &str) -> LineEdit {
+    let (start, end) = line_span(text, anchor, active);
+    let commented = text[start..end]
+        .split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| line.trim_start().starts_with(token));
+
+    splice_lines(text, anchor, active, |line| {
+        if line.trim().is_empty() {
+            return None;
+        }
+
+        let at = line.len() - line.trim_start().len();
+        if !commented {
+            return Some((at, 0, format!("{} ", token)));
+        }
+        let after = &line[at + token.len()..];
+        let removed = token.len() + if after.starts_with(' ') { 1 } else { 0 };
+        Some((at, removed, "".to_string()))
+    })
+}
+
+/// Swap the lines touched by the selection with the line above them,
+/// `None` when they start the text.
+pub fn move_lines_up(text: &str, anchor: usize, active: usize) -> Option<LineEdit> {
+    let (start, end) = line_span(text, anchor, active);
+    if start == 0 {
+        return None;
+    }
+
+    let above = text[..start - 1].rfind('\n').map_or(0, |index| index + 1);
+    let moved = format!("{}\n{}", &text[start..end], &text[above..start - 1]);
+    let shift = start - above;
+    Some(LineEdit {
+        text: format!("{}{}{}", &text[..above], moved, &text[end..]),
+        anchor: anchor - shift,
+        active: active - shift,
+    })
+}
+
+/// Swap the lines touched by the selection with the line below them,
+/// `None` when they end the text.
+pub fn move_lines_down(text: &str, anchor: usize, active: usize) -> Option<LineEdit> {
+    let (start, end) = line_span(text, anchor, active);
+    if end == text.len() {
+        return None;
+    }
+
+    let below = text[end + 1..]
+        .find('\n')
+        .map_or(text.len(), |index| end + 1 + index);
+    let moved = format!("{}\n{}", &text[end + 1..below], &text[start..end]);
+    let shift = below - end;
+    Some(LineEdit {
+        text: format!("{}{}{}", &text[..start], moved, &text[below..]),
+        anchor: anchor + shift,
+        active: active + shift,
+    })
+}
+
+/// Copy the lines touched by the selection below them, the selection
+/// moves along to the copy.
+pub fn duplicate_lines(text: &str, anchor: usize, active: usize) -> LineEdit {
+    let (start, end) = line_span(text, anchor, active);
+    let lines = &text[start..end];
+    let shift = lines.len() + 1;
+    LineEdit {
+        text: format!("{}\n{}{}", &text[..end], lines, &text[end..]),
+        anchor: anchor + shift,
+        active: active + shift,
+    }
+}
+
+/// the start of the first and the end of the last line the selection
+/// touches, a selection ending right at a line start leaves that line out.
+fn line_span(text: &str, anchor: usize, active: usize) -> (usize, usize) {
+    let start = anchor.min(active).min(text.len());
+    let mut end = anchor.max(active).min(text.len());
+    if end > start && text[..end].ends_with('\n') {
+        end -= 1;
+    }
+
+    let line_start = text[..start].rfind('\n').map_or(0, |index| index + 1);
+    let line_end = text[end..]
+        .find('\n')
+        .map_or(text.len(), |index| end + index);
+    (line_start, line_end)
+}
+
+/// Run `splice` over each line touched by the selection. It returns where
+/// to replace how many bytes of the line with what, or `None` to keep it.
+fn splice_lines(
+    text: &str,
+    anchor: usize,
+    active: usize,
+    splice: impl Fn(&str) -> Option<(usize, usize, String)>,
+) -> LineEdit {
+    let (start, end) = line_span(text, anchor, active);
+    let mut out = String::with_capacity(text.len() + 64);
+    out.push_str(&text[..start]);
+
+    let (mut new_anchor, mut new_active) = (anchor, active);
+    let mut line_start = start;
+    for (index, line) in text[start..end].split('\n').enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+        let new_start = out.len();
+        let (at, removed, inserted) = splice(line).unwrap_or((0, 0, "".to_string()));
+        out.push_str(&line[..at]);
+        out.push_str(&inserted);
+        out.push_str(&line[at + removed..]);
+
+        // carets after the splice move with the text, ones inside it go to its start
+        let line_end = line_start + line.len();
+        let move_offset = |offset: usize| {
+            let column = offset - line_start;
+            let column = if column >= at + removed {
+                column - removed + inserted.len()
+            } else {
+                column.min(at)
+            };
+            new_start + column
+        };
+        if (line_start..=line_end).contains(&anchor) {
+            new_anchor = move_offset(anchor);
+        }
+        if (line_start..=line_end).contains(&active) {
+            new_active = move_offset(active);
+        }
+        line_start = line_end + 1;
+    }
+
+    // carets after the lines move by how much they grew or shrank
+    if anchor > end {
+        new_anchor = anchor - end + out.len();
+    }
+    if active > end {
+        new_active = active - end + out.len();
+    }
+    out.push_str(&text[end..]);
+
+    LineEdit {
+        text: out,
+        anchor: new_anchor,
+        active: new_active,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPACES: Indentation = Indentation {
+        tabs: false,
+        width: 4,
+    };
+    const TABS: Indentation = Indentation {
+        tabs: true,
+        width: 4,
+    };
+
+    /// the text and selection of an edit, to compare in one go.
+    fn parts(edit: LineEdit) -> (String, usize, usize) {
+        (edit.text, edit.anchor, edit.active)
+    }
+
+    #[test]
+    fn indent_covers_the_first_and_last_line() {
+        assert_eq!(
+            parts(indent("a\nb\nc", 0, 5, SPACES)),
+            ("    a\n    b\n    c".to_string(), 4, 17)
+        );
+    }
+
+    #[test]
+    fn indent_moves_the_caret_with_its_line() {
+        assert_eq!(
+            parts(indent("x\ny", 1, 1, TABS)),
+            ("\tx\ny".to_string(), 2, 2)
+        );
+    }
+
+    #[test]
+    fn indent_skips_blank_lines() {
+        assert_eq!(
+            parts(indent("a\n\nb", 0, 4, TABS)),
+            ("\ta\n\n\tb".to_string(), 1, 6)
+        );
+    }
+
+    #[test]
+    fn a_selection_ending_at_a_line_start_leaves_that_line_out() {
+        assert_eq!(
+            parts(indent("a\nb\nc", 0, 2, SPACES)),
+            ("    a\nb\nc".to_string(), 4, 6)
+        );
+    }
+
+    #[test]
+    fn outdent_removes_a_tab_or_up_to_a_level_of_spaces() {
+        assert_eq!(
+            parts(outdent("    a\n\tb\n  c", 0, 12, SPACES)),
+            ("a\nb\nc".to_string(), 0, 5)
+        );
+    }
+
+    #[test]
+    fn outdent_moves_a_caret_inside_the_indentation_to_the_line_start() {
+        assert_eq!(
+            parts(outdent("    a", 2, 2, SPACES)),
+            ("a".to_string(), 0, 0)
+        );
+    }
+
+    #[test]
+    fn outdent_keeps_unindented_lines() {
+        assert_eq!(
+            parts(outdent("a\nb", 0, 3, SPACES)),
+            ("a\nb".to_string(), 0, 3)
+        );
+    }
+
+    #[test]
+    fn toggle_comment_comments_at_the_indentation() {
+        assert_eq!(
+            parts(toggle_comment("a\n  b", 0, 5, "//")),
+            ("// a\n  // b".to_string(), 3, 11)
+        );
+    }
+
+    #[test]
+    fn toggle_comment_uncomments_with_and_without_a_trailing_space() {
+        assert_eq!(toggle_comment("// a\n  // b", 0, 11, "//").text, "a\n  b");
+        assert_eq!(toggle_comment("//a\n//b", 0, 7, "//").text, "a\nb");
+        assert_eq!(toggle_comment("//a\n// b", 0, 8, "//").text, "a\nb");
+    }
+
+    #[test]
+    fn toggle_comment_comments_all_when_one_line_is_not() {
+        assert_eq!(toggle_comment("// a\nb", 0, 6, "//").text, "// // a\n// b");
+    }
+
+    #[test]
+    fn move_lines_up_swaps_with_the_line_above() {
+        assert_eq!(
+            move_lines_up("a\nb\nc", 4, 4).map(parts),
+            Some(("a\nc\nb".to_string(), 2, 2))
+        );
+        assert!(move_lines_up("a\nb", 0, 0).is_none());
+    }
+
+    #[test]
+    fn move_lines_down_swaps_with_the_line_below() {
+        assert_eq!(
+            move_lines_down("a\nb\nc", 0, 0).map(parts),
+            Some(("b\na\nc".to_string(), 2, 2))
+        );
+        assert!(move_lines_down("a\nb", 2, 2).is_none());
+    }
+
+    #[test]
+    fn move_lines_down_leaves_the_line_a_selection_ends_at() {
+        assert_eq!(
+            move_lines_down("a\nb\nc", 0, 2).map(parts),
+            Some(("b\na\nc".to_string(), 2, 4))
+        );
+    }
+
+    #[test]
+    fn duplicate_lines_selects_the_copy() {
+        assert_eq!(
+            parts(duplicate_lines("a\nb", 0, 0)),
+            ("a\na\nb".to_string(), 2, 2)
+        );
+        assert_eq!(
+            parts(duplicate_lines("a\nb", 3, 3)),
+            ("a\nb\nb".to_string(), 5, 5)
+        );
+    }
+
+    #[test]
+    fn splice_lines_moves_carets_after_the_lines() {
+        let edit = splice_lines("a\nb\nc", 0, 5, |line| {
+            if line == "b" {
+                Some((0, 1, "bbb".to_string()))
+            } else {
+                None
+            }
+        });
+        assert_eq!(parts(edit), ("a\nbbb\nc".to_string(), 0, 7));
+
+        // the selection ends at the start of the last line, which is left out
+        let edit = splice_lines("a\nb", 0, 2, |_| Some((0, 0, "xx".to_string())));
+        assert_eq!(parts(edit), ("xxa\nb".to_string(), 2, 4));
+    }
+}
@@ -1,2 +1,12 @@
+pub mod appearance;
+pub mod bracket;
 pub mod directory;
+pub mod encoding;
+pub mod git;
+pub mod indentation;
+pub mod keymap;
+pub mod language;
 pub mod line;
+pub mod line_edit;
+pub mod snippet;
+pub mod whitespace;
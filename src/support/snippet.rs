@@ -0,0 +1,47 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::Local;
+
+use crate::support::line_edit::LineEdit;
+
+/// the format `$DATE` expands to.
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+/// the format `$TIME` expands to.
+pub const TIME_FORMAT: &str = "%H:%M";
+/// the format of Insert Date/Time unless the config has another.
+pub const DEFAULT_DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// The local time in the strftime `format`, the default format is used when
+/// it has a specifier chrono doesn't know.
+pub fn now(format: &str) -> String {
+    let format = if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        log::warn!(
+            "invalid date format {:?}, using {:?}",
+            format,
+            DEFAULT_DATE_TIME_FORMAT
+        );
+        DEFAULT_DATE_TIME_FORMAT
+    } else {
+        format
+    };
+    Local::now().format(format).to_string()
+}
+
+/// Fill in `$DATE`, `$TIME` and `$FILENAME` of a snippet.
+pub fn expand(template: &str, filename: &str) -> String {
+    template
+        .replace("$DATE", &now(DATE_FORMAT))
+        .replace("$TIME", &now(TIME_FORMAT))
+        .replace("$FILENAME", filename)
+}
+
+/// Replace the selection with `inserted`, the caret ends up after it.
+pub fn insert(text: &str, anchor: usize, active: usize, inserted: &str) -> LineEdit {
+    let start = anchor.min(active).min(text.len());
+    let end = anchor.max(active).min(text.len());
+    let caret = start + inserted.len();
+    LineEdit {
+        text: format!("{}{}{}", &text[..start], inserted, &text[end..]),
+        anchor: caret,
+        active: caret,
+    }
+}
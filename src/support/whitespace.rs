@@ -0,0 +1,115 @@
+use std::ops::Range;
+
+/// The byte offsets of the spaces and tabs within `range` of `text`.
+pub fn whitespace(text: &str, range: Range<usize>) -> Vec<(usize, char)> {
+    let range = clamp(text, range);
+    text[range.clone()]
+        .char_indices()
+        .filter(|(_, ch)| *ch == ' ' || *ch == '\t')
+        .map(|(index, ch)| (range.start + index, ch))
+        .collect()
+}
+
+/// The spaces and tabs ending the lines that start within `range` of `text`.
+pub fn trailing_whitespace(text: &str, range: Range<usize>) -> Vec<Range<usize>> {
+    let range = clamp(text, range);
+    let start = text[..range.start].rfind('\n').map_or(0, |index| index + 1);
+
+    let mut trailing = vec![];
+    let mut line_start = start;
+    for raw in text[start..].split('\n') {
+        if line_start > range.end {
+            break;
+        }
+        let line = raw.strip_suffix('\r').unwrap_or(raw);
+        let content = line.trim_end_matches(|ch| ch == ' ' || ch == '\t');
+        if content.len() < line.len() {
+            trailing.push(line_start + content.len()..line_start + line.len());
+        }
+        line_start += raw.len() + 1;
+    }
+    trailing
+}
+
+// keep the range within the text and on char boundaries
+fn clamp(text: &str, range: Range<usize>) -> Range<usize> {
+    let mut start = range.start.min(text.len());
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = range.end.min(text.len()).max(start);
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+    start..end
+}
+
+/// What saving fixes up in the whitespace of a text.
+pub struct Tidy {
+    /// strip the spaces and tabs ending each line.
+    pub trim_lines: bool,
+    /// keep runs of two or more spaces after some content, they break the
+    /// line in markdown.
+    pub keep_hard_breaks: bool,
+    /// end the text in a single line break.
+    pub final_newline: bool,
+}
+
+impl Tidy {
+    /// The tidied `text` and the byte ranges removed from it, see `shift_offset`.
+    pub fn apply(&self, text: &str) -> (String, Vec<Range<usize>>) {
+        let mut removed = if self.trim_lines {
+            trailing_whitespace(text, 0..text.len())
+        } else {
+            vec![]
+        };
+        if self.keep_hard_breaks {
+            removed.retain(|range| !is_hard_break(text, range));
+        }
+
+        let mut append = None;
+        if self.final_newline && !text.is_empty() {
+            let content_end = text
+                .trim_end_matches(|ch| ch == ' ' || ch == '\t' || ch == '\r' || ch == '\n')
+                .len();
+            match text[content_end..].find('\n') {
+                Some(index) => {
+                    let tail = content_end + index + 1;
+                    removed.retain(|range| range.start < tail);
+                    if tail < text.len() {
+                        removed.push(tail..text.len());
+                    }
+                }
+                None if text.contains("\r\n") => append = Some("\r\n"),
+                None => append = Some("\n"),
+            }
+        }
+
+        let mut tidied = String::with_capacity(text.len());
+        let mut last = 0;
+        for range in &removed {
+            tidied.push_str(&text[last..range.start]);
+            last = range.end;
+        }
+        tidied.push_str(&text[last..]);
+        if let Some(newline) = append {
+            tidied.push_str(newline);
+        }
+        (tidied, removed)
+    }
+}
+
+/// Where `offset` ends up once the `removed` ranges, in order, are cut.
+pub fn shift_offset(offset: usize, removed: &[Range<usize>]) -> usize {
+    let cut: usize = removed
+        .iter()
+        .take_while(|range| range.start < offset)
+        .map(|range| range.end.min(offset) - range.start)
+        .sum();
+    offset - cut
+}
+
+fn is_hard_break(text: &str, range: &Range<usize>) -> bool {
+    let after_content = range.start > 0 && !text[..range.start].ends_with('\n');
+    after_content && range.len() >= 2 && text[range.clone()].bytes().all(|byte| byte == b' ')
+}
@@ -4,6 +4,19 @@ pub const SIDEBAR_BACKGROUND: Key<Color> = Key::new("print.sidebar-background");
 pub const SIDEBAR_EDGE_STROKE: Key<Color> = Key::new("print.sidebar-edge-stroke");
 
 pub const TOOL_WINDOW_COLOR: Key<Color> = Key::new("print.tool-window-color");
+pub const TREE_SELECTED_COLOR: Key<Color> = Key::new("print.tree-selected-color");
+/// background of the bracket next to the caret and of its match.
+pub const MATCH_BRACKET: Key<Color> = Key::new("print.match-bracket");
+/// dots and arrows drawn for spaces and tabs.
+pub const WHITESPACE: Key<Color> = Key::new("print.whitespace");
+/// background of the spaces and tabs ending a line.
+pub const TRAILING_WHITESPACE: Key<Color> = Key::new("print.trailing-whitespace");
+/// file tree names by their git status.
+pub const GIT_MODIFIED: Key<Color> = Key::new("print.git-modified");
+pub const GIT_ADDED: Key<Color> = Key::new("print.git-added");
+pub const GIT_UNTRACKED: Key<Color> = Key::new("print.git-untracked");
+pub const GIT_DELETED: Key<Color> = Key::new("print.git-deleted");
+pub const GIT_CONFLICTED: Key<Color> = Key::new("print.git-conflicted");
 
 pub const FOREGROUND_LIGHT: Key<Color> = Key::new("print.theme.foreground_light");
 pub const FOREGROUND_DARK: Key<Color> = Key::new("print.theme.foreground_dark");
@@ -22,26 +35,72 @@ pub const WRITING_FONT: Key<FontDescriptor> = Key::new("print.theme.writing");
 
 #[rustfmt::skip]
 pub fn configure_env(env: &mut Env) {
-    env.set(druid::theme::BACKGROUND_LIGHT, Color::WHITE);
-    env.set(druid::theme::CURSOR_COLOR, Color::BLACK);
-
-    env.set(crate::theme::SIDEBAR_BACKGROUND, Color::from_hex_str("#fff").unwrap());
-    env.set(crate::theme::BACKGROUND_COLOR,Color::from_hex_str("#e7e7e7").unwrap());
-    env.set(crate::theme::TOOL_WINDOW_COLOR,Color::from_hex_str("#fff").unwrap());
-    env.set(crate::theme::SIDEBAR_BACKGROUND,Color::from_hex_str("#fff").unwrap());
-    env.set(crate::theme::SIDEBAR_EDGE_STROKE,Color::from_hex_str("#c7c7c7").unwrap());
-    env.set(crate::theme::BUTTON_LIGHT,Color::from_hex_str("#e7e7e7").unwrap());
-    env.set(crate::theme::BUTTON_DARK,Color::from_hex_str("#b9b9b9").unwrap());
-    env.set(crate::theme::BASIC_TEXT_COLOR,Color::from_hex_str("#000").unwrap());
-    env.set(crate::theme::FOREGROUND_LIGHT,Color::from_hex_str("#fff").unwrap());
-    env.set(crate::theme::FOREGROUND_DARK,Color::from_hex_str("#000").unwrap());
+    apply_dark_mode(env, false);
+
     env.set(crate::theme::BORDERED_WIDGET_HEIGHT,   32.0);
     env.set(crate::theme::BUTTON_BORDER_WIDTH,    2.0);
     env.set(crate::theme::BASIC_TEXT_SIZE,   12.0);
 
-    let family = FontFamily::new_unchecked("Microsoft Yahei");
-    env.set(crate::theme::WRITING_FONT,   FontDescriptor::new(family)
+    apply_writing_font(env, Some("Microsoft Yahei"), 15.0);
+}
+
+/// Set the font used for the text being written, `None` for the monospace
+/// fallback.
+#[rustfmt::skip]
+pub fn apply_writing_font(env: &mut Env, family: Option<&str>, size: f64) {
+    let family = family.map(FontFamily::new_unchecked).unwrap_or(FontFamily::MONOSPACE);
+    env.set(crate::theme::WRITING_FONT, FontDescriptor::new(family)
         .with_style(FontStyle::Regular)
         .with_weight(FontWeight::LIGHT)
-        .with_size(15.0));
+        .with_size(size));
+}
+
+/// Switch the palette between the light and the dark colors.
+#[rustfmt::skip]
+pub fn apply_dark_mode(env: &mut Env, enabled: bool) {
+    if !enabled {
+        env.set(druid::theme::BACKGROUND_LIGHT, Color::WHITE);
+        env.set(druid::theme::CURSOR_COLOR, Color::BLACK);
+
+        env.set(crate::theme::BACKGROUND_COLOR,Color::from_hex_str("#e7e7e7").unwrap());
+        env.set(crate::theme::TOOL_WINDOW_COLOR,Color::from_hex_str("#fff").unwrap());
+        env.set(crate::theme::SIDEBAR_BACKGROUND,Color::from_hex_str("#fff").unwrap());
+        env.set(crate::theme::SIDEBAR_EDGE_STROKE,Color::from_hex_str("#c7c7c7").unwrap());
+        env.set(crate::theme::TREE_SELECTED_COLOR,Color::from_hex_str("#d5e1f2").unwrap());
+        env.set(crate::theme::MATCH_BRACKET,Color::from_hex_str("#c9dcc5").unwrap());
+        env.set(crate::theme::WHITESPACE,Color::from_hex_str("#c4c4c4").unwrap());
+        env.set(crate::theme::TRAILING_WHITESPACE,Color::from_hex_str("#f5d5d3").unwrap());
+        env.set(crate::theme::GIT_MODIFIED,Color::from_hex_str("#c7711e").unwrap());
+        env.set(crate::theme::GIT_ADDED,Color::from_hex_str("#3a7d36").unwrap());
+        env.set(crate::theme::GIT_UNTRACKED,Color::from_hex_str("#4f9a3c").unwrap());
+        env.set(crate::theme::GIT_DELETED,Color::from_hex_str("#9a4a48").unwrap());
+        env.set(crate::theme::GIT_CONFLICTED,Color::from_hex_str("#c4282a").unwrap());
+        env.set(crate::theme::BUTTON_LIGHT,Color::from_hex_str("#e7e7e7").unwrap());
+        env.set(crate::theme::BUTTON_DARK,Color::from_hex_str("#b9b9b9").unwrap());
+        env.set(crate::theme::BASIC_TEXT_COLOR,Color::from_hex_str("#000").unwrap());
+        env.set(crate::theme::FOREGROUND_LIGHT,Color::from_hex_str("#fff").unwrap());
+        env.set(crate::theme::FOREGROUND_DARK,Color::from_hex_str("#000").unwrap());
+    } else {
+        env.set(druid::theme::BACKGROUND_LIGHT, Color::from_hex_str("#1e1f22").unwrap());
+        env.set(druid::theme::CURSOR_COLOR, Color::WHITE);
+
+        env.set(crate::theme::BACKGROUND_COLOR,Color::from_hex_str("#2b2d30").unwrap());
+        env.set(crate::theme::TOOL_WINDOW_COLOR,Color::from_hex_str("#1e1f22").unwrap());
+        env.set(crate::theme::SIDEBAR_BACKGROUND,Color::from_hex_str("#1e1f22").unwrap());
+        env.set(crate::theme::SIDEBAR_EDGE_STROKE,Color::from_hex_str("#393b40").unwrap());
+        env.set(crate::theme::TREE_SELECTED_COLOR,Color::from_hex_str("#2e436e").unwrap());
+        env.set(crate::theme::MATCH_BRACKET,Color::from_hex_str("#43454a").unwrap());
+        env.set(crate::theme::WHITESPACE,Color::from_hex_str("#4e5157").unwrap());
+        env.set(crate::theme::TRAILING_WHITESPACE,Color::from_hex_str("#5c3436").unwrap());
+        env.set(crate::theme::GIT_MODIFIED,Color::from_hex_str("#e2a056").unwrap());
+        env.set(crate::theme::GIT_ADDED,Color::from_hex_str("#73bd79").unwrap());
+        env.set(crate::theme::GIT_UNTRACKED,Color::from_hex_str("#8cc265").unwrap());
+        env.set(crate::theme::GIT_DELETED,Color::from_hex_str("#b36664").unwrap());
+        env.set(crate::theme::GIT_CONFLICTED,Color::from_hex_str("#f75464").unwrap());
+        env.set(crate::theme::BUTTON_LIGHT,Color::from_hex_str("#393b40").unwrap());
+        env.set(crate::theme::BUTTON_DARK,Color::from_hex_str("#4e5157").unwrap());
+        env.set(crate::theme::BASIC_TEXT_COLOR,Color::from_hex_str("#dfe1e5").unwrap());
+        env.set(crate::theme::FOREGROUND_LIGHT,Color::from_hex_str("#000").unwrap());
+        env.set(crate::theme::FOREGROUND_DARK,Color::from_hex_str("#dfe1e5").unwrap());
+    }
 }